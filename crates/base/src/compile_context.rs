@@ -9,4 +9,12 @@ pub struct CompileContext {
     pub build_pkg: PathBuf,
     pub build_dir: PathBuf,
     pub run_immediately: bool,
+    /// Run the program with the interpreter instead of compiling it to C.
+    pub interpret: bool,
+    /// C libraries the program is linked against.
+    pub link_libraries: Vec<String>,
+    /// Point the C compiler and debuggers at the Pine source with `#line` directives.
+    pub line_directives: bool,
+    /// A directory with a C runtime to build against instead of the one embedded into the compiler.
+    pub runtime_dir: Option<PathBuf>,
 }
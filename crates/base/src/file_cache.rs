@@ -50,6 +50,17 @@ impl FileCache {
     pub fn load(&mut self, src: SourceId) -> Result<(), FileCacheError> {
         self.fetch(src).map(|_| ())
     }
+
+    /// Adds a file that does not exist on disk, like an input of the REPL.
+    pub fn insert(&mut self, src: SourceId, content: String) {
+        self.files.insert(src, content);
+    }
+
+    /// The one-based line and column of a byte offset in a file that was already loaded.
+    pub fn line_column(&self, src: SourceId, byte_index: usize) -> Option<(usize, usize)> {
+        let location = self.location(src, byte_index)?;
+        Some((location.line_number, location.column_number))
+    }
 }
 
 impl<'a> Files<'a> for FileCache {
@@ -0,0 +1,24 @@
+use std::{env, fs, path::Path};
+
+/// Lists the files of the C runtime in `core_c.rs`, which `lib_core` embeds into the compiler.
+fn main() {
+    let core_c = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/lib_core/core_c");
+    println!("cargo:rerun-if-changed={}", core_c.display());
+
+    let mut files: Vec<_> = (fs::read_dir(&core_c).expect("failed to read the C runtime"))
+        .map(|entry| entry.expect("failed to read the C runtime").path())
+        .filter(|path| matches!(path.extension(), Some(ext) if ext == "h" || ext == "c"))
+        .collect();
+    files.sort();
+
+    let mut entries = String::new();
+    for path in files {
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        entries.push_str(&format!(
+            "    ({:?}, include_str!({:?})),\n",
+            file_name, path
+        ));
+    }
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("core_c.rs");
+    fs::write(out, format!("&[\n{}]\n", entries)).expect("failed to write core_c.rs");
+}
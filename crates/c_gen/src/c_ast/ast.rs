@@ -77,21 +77,45 @@ pub enum CDeclaration {
     FunctionDeclaration(CFunctionDeclaration),
     GlobalVariableDeclaration(CGlobalVariableDeclaration),
     StructDeclaration(CStructDeclaration),
+    /// `typedef struct Name Name;`, which allows pointers to a struct that is defined later.
+    ForwardStructDeclaration(CIdentifier),
+    UnionDeclaration(CUnionDeclaration),
+    EnumDeclaration(CEnumDeclaration),
+}
+
+impl CDeclaration {
+    /// Whether this declares a type rather than a function or variable.
+    pub fn is_type_declaration(&self) -> bool {
+        matches!(
+            self,
+            CDeclaration::StructDeclaration(_)
+                | CDeclaration::ForwardStructDeclaration(_)
+                | CDeclaration::UnionDeclaration(_)
+                | CDeclaration::EnumDeclaration(_)
+        )
+    }
 }
 
 impl_from!(CGlobalVariableDeclaration => CDeclaration => GlobalVariableDeclaration);
 impl_from!(CFunctionDeclaration => CDeclaration => FunctionDeclaration);
 impl_from!(CStructDeclaration => CDeclaration => StructDeclaration);
+impl_from!(CUnionDeclaration => CDeclaration => UnionDeclaration);
+impl_from!(CEnumDeclaration => CDeclaration => EnumDeclaration);
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum CStmt {
     Empty,
     Continue,
     Break,
+    Goto(CIdentifier),
+    Label(CIdentifier),
+    /// The place in the Pine source the statements that follow were generated from.
+    Line(CLineDirective),
     Return(CReturnStmt),
     Block(CBlockStmt),
     If(CIfStmt),
     While(CWhileStmt),
+    For(CForStmt),
     VariableDeclaration(CVariableDeclaration),
     Expr(CExpr),
 }
@@ -100,6 +124,7 @@ impl_from!(CReturnStmt => CStmt => Return);
 impl_from!(CBlockStmt => CStmt => Block);
 impl_from!(CIfStmt => CStmt => If);
 impl_from!(CWhileStmt => CStmt => While);
+impl_from!(CForStmt => CStmt => For);
 impl_from!(CExpr => CStmt => Expr);
 impl_from!(CVariableDeclaration => CStmt => VariableDeclaration);
 
@@ -115,6 +140,8 @@ pub enum CExpr {
     SizeOf(CType),
     Assignment(CAssignment),
     Type(CType),
+    /// `{a, b, c}`, only valid as the initializer of a declaration.
+    InitializerList(Vec<CExpr>),
 }
 
 impl_from!(CIdentifier => CExpr => Identifier);
@@ -148,14 +175,53 @@ impl CAssignment {
 pub struct CStructDeclaration {
     pub name: CIdentifier,
     pub members: Vec<CTypedParam>,
+    /// Instances of generic types are defined by every unit that uses them, so headers only
+    /// declare them.
+    pub is_instance: bool,
 }
 
 impl CStructDeclaration {
+    pub fn new(name: CIdentifier, members: Vec<CTypedParam>) -> Self {
+        Self {
+            name,
+            members,
+            is_instance: false,
+        }
+    }
+
+    pub fn instance(name: CIdentifier, members: Vec<CTypedParam>) -> Self {
+        Self {
+            name,
+            members,
+            is_instance: true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CUnionDeclaration {
+    pub name: CIdentifier,
+    pub members: Vec<CTypedParam>,
+}
+
+impl CUnionDeclaration {
     pub fn new(name: CIdentifier, members: Vec<CTypedParam>) -> Self {
         Self { name, members }
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct CEnumDeclaration {
+    pub name: CIdentifier,
+    pub variants: Vec<CIdentifier>,
+}
+
+impl CEnumDeclaration {
+    pub fn new(name: CIdentifier, variants: Vec<CIdentifier>) -> Self {
+        Self { name, variants }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct CHeaderInclude {
     pub name: String,
@@ -207,6 +273,11 @@ pub struct CFunctionDeclaration {
     pub params: Vec<CTypedParam>,
     pub ret_ty: CType,
     pub body: Option<CStmt>,
+    /// Static functions are only visible in their unit and left out of its header.
+    pub is_static: bool,
+    /// The line of the Pine source the function is lowered from. Functions without one are
+    /// generated by the compiler.
+    pub line: Option<CLineDirective>,
 }
 
 impl CFunctionDeclaration {
@@ -221,6 +292,17 @@ impl CFunctionDeclaration {
             params,
             ret_ty,
             body,
+            is_static: false,
+            line: None,
+        }
+    }
+
+    /// The declaration without the body.
+    pub fn prototype(&self) -> Self {
+        Self {
+            body: None,
+            line: None,
+            ..self.clone()
         }
     }
 }
@@ -325,6 +407,19 @@ pub enum CMemberOperator {
     Indirect,
 }
 
+/// #line 12 "main.pine"
+#[derive(Debug, PartialEq, Clone)]
+pub struct CLineDirective {
+    pub line: usize,
+    pub file: String,
+}
+
+impl CLineDirective {
+    pub fn new(line: usize, file: String) -> Self {
+        Self { line, file }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct CReturnStmt {
     pub expr: Option<Box<CExpr>>,
@@ -381,6 +476,31 @@ impl CWhileStmt {
     }
 }
 
+/// `for (init; condition; step) body`
+#[derive(Debug, PartialEq, Clone)]
+pub struct CForStmt {
+    pub init: Option<CExpr>,
+    pub condition: Option<CExpr>,
+    pub step: Option<CExpr>,
+    pub body: Box<CStmt>,
+}
+
+impl CForStmt {
+    pub fn new(
+        init: Option<CExpr>,
+        condition: Option<CExpr>,
+        step: Option<CExpr>,
+        body: CStmt,
+    ) -> Self {
+        Self {
+            init,
+            condition,
+            step,
+            body: Box::new(body),
+        }
+    }
+}
+
 impl From<String> for CIdentifier {
     fn from(name: String) -> Self {
         Self { name }
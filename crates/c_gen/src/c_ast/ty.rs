@@ -25,10 +25,19 @@ pub enum CType {
     F32,
     F64,
 
+    Char,
     String,
     Struct(String),
+    Union(String),
+    Enum(String),
     Pointer(Box<CType>),
     Reference(Box<CType>),
+    Const(Box<CType>),
+    /// An array of unknown size, only used in compound literals like `(int64_t[]){1, 2}`.
+    Array(Box<CType>),
+    /// A pointer to a function with the return and parameter types, only used in casts like
+    /// `(int64_t (*)(void*, int64_t))f`.
+    FunctionPointer(Box<CType>, Vec<CType>),
 }
 
 impl CType {
@@ -56,10 +65,22 @@ impl Display for CType {
             CType::ISize => write!(f, "ssize_t"),
             CType::F32 => write!(f, "float"),
             CType::F64 => write!(f, "double"),
+            CType::Char => write!(f, "char"),
             CType::String => write!(f, "char*"),
             CType::Struct(name) => write!(f, "{}", name),
+            CType::Union(name) => write!(f, "{}", name),
+            CType::Enum(name) => write!(f, "{}", name),
             CType::Pointer(ty) => write!(f, "{}*", ty),
             CType::Reference(ty) => write!(f, "{}&", ty),
+            CType::Const(ty) => write!(f, "const {}", ty),
+            CType::Array(ty) => write!(f, "{}[]", ty),
+            CType::FunctionPointer(ret, params) if params.is_empty() => {
+                write!(f, "{} (*)(void)", ret)
+            }
+            CType::FunctionPointer(ret, params) => {
+                let params = params.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                write!(f, "{} (*)({})", ret, params.join(", "))
+            }
         }
     }
 }
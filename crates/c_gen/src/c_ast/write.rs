@@ -1,9 +1,17 @@
 use crate::c_ast::ast::*;
 use crate::c_ast::op::{CAssignmentOperator, CBinaryOperator, CUnaryOperator};
 use crate::c_ast::ty::CType;
+use std::path::Path;
 
 pub struct CAstWriter {
     string: Vec<String>,
+    // The file the C is written to, if `#line` directives point the C compiler and debuggers
+    // at the Pine source. Code the compiler generates points back at this file.
+    line_directives: Option<String>,
+    // The number of lines written so far.
+    lines: usize,
+    // Whether the last `#line` directive points at the Pine source.
+    at_pine_source: bool,
 }
 
 impl Default for CAstWriter {
@@ -13,17 +21,45 @@ impl Default for CAstWriter {
 }
 
 impl CAstWriter {
-    pub fn write_unit(unit: &CTranslationUnit) -> String {
+    /// Writes the unit. With `line_directives`, the path the unit is written to, the C points
+    /// at the Pine source it is lowered from.
+    pub fn write_unit(unit: &CTranslationUnit, line_directives: Option<&Path>) -> String {
         let mut writer = CAstWriter::new();
+        writer.line_directives = line_directives.map(|path| path.display().to_string());
         writer.write_translation_unit(unit);
         writer.get_string()
     }
 
+    /// Renumbers the `#line` directives that point at `file` itself, after formatting moved its
+    /// lines.
+    pub fn renumber_line_directives(c: &str, file: &Path) -> String {
+        let file = format!("\"{}\"", Self::escape_string(&file.display().to_string()));
+        let mut renumbered = String::with_capacity(c.len());
+        for (index, line) in c.lines().enumerate() {
+            let directive = line.trim_start().strip_prefix("#line ");
+            match directive.and_then(|d| d.split_once(' ')) {
+                // a directive sets the number of the line after it
+                Some((_, to)) if to.trim() == file => {
+                    renumbered.push_str(&format!("#line {} {}", index + 2, file))
+                }
+                _ => renumbered.push_str(line),
+            }
+            renumbered.push('\n');
+        }
+        renumbered
+    }
+
     pub fn new() -> Self {
-        Self { string: Vec::new() }
+        Self {
+            string: Vec::new(),
+            line_directives: None,
+            lines: 0,
+            at_pine_source: false,
+        }
     }
 
     pub fn write<S: AsRef<str>>(&mut self, s: S) {
+        self.lines += s.as_ref().matches('\n').count();
         self.string.push(s.as_ref().to_string());
     }
 
@@ -37,13 +73,30 @@ impl CAstWriter {
     }
 
     pub fn write_translation_unit(&mut self, tu: &CTranslationUnit) {
+        // headers of modules can be included more than once through different imports
+        let guard = format!("PINE_UNIT_{}_H", tu.name.to_uppercase());
+        if tu.is_header {
+            self.write(format!("#ifndef {}\n#define {}\n", guard, guard));
+        }
+
         for header_include in &tu.header_includes {
             self.write_header_include(header_include);
         }
 
         for decl in &tu.implementation {
+            match decl {
+                CDeclaration::FunctionDeclaration(CFunctionDeclaration {
+                    line: Some(line),
+                    ..
+                }) => self.write_line_directive(line),
+                _ => self.write_generated_line_directive(),
+            }
             self.write_declaration(decl);
         }
+
+        if tu.is_header {
+            self.write(format!("\n#endif // {}\n", guard));
+        }
     }
 
     fn write_header_include(&mut self, header_include: &CHeaderInclude) {
@@ -67,15 +120,45 @@ impl CAstWriter {
             CStmt::Block(block) => self.write_block(block),
             CStmt::If(if_stmt) => self.write_if(if_stmt),
             CStmt::While(while_stmt) => self.write_while(while_stmt),
+            CStmt::For(for_stmt) => self.write_for(for_stmt),
             CStmt::Return(return_stmt) => self.write_return(return_stmt),
             CStmt::Expr(expr_stmt) => self.write_expr_stmt(expr_stmt),
             CStmt::Continue => self.write("continue;"),
             CStmt::Break => self.write("break;"),
+            CStmt::Goto(label) => self.write(format!("goto {};", label.name)),
+            // the empty statement lets a label end a block
+            CStmt::Label(label) => self.write(format!("{}:;\n", label.name)),
+            CStmt::Line(line) => self.write_line_directive(line),
             CStmt::Empty => {}
             CStmt::VariableDeclaration(var_decl) => self.write_variable_declaration(var_decl),
         }
     }
 
+    /// Directives have to be on a line of their own.
+    fn write_line_directive(&mut self, line: &CLineDirective) {
+        if self.line_directives.is_some() {
+            let file = Self::escape_string(&line.file);
+            self.write(format!("\n#line {} \"{}\"\n", line.line, file));
+            self.at_pine_source = true;
+        }
+    }
+
+    /// Points the code that follows back at the generated file, unless it already is.
+    fn write_generated_line_directive(&mut self) {
+        let Some(file) = &self.line_directives else {
+            return;
+        };
+        if !self.at_pine_source {
+            return;
+        }
+        let file = Self::escape_string(file);
+        self.write("\n");
+        // the directive sets the number of the line after it
+        let line = self.lines + 2;
+        self.write(format!("#line {} \"{}\"\n", line, file));
+        self.at_pine_source = false;
+    }
+
     fn write_assignment(&mut self, ass: &CAssignment) {
         let op = self.get_assignment_op_token(&ass.op);
         self.write_expr(&ass.lhs);
@@ -94,6 +177,15 @@ impl CAstWriter {
             CDeclaration::StructDeclaration(struct_decl) => {
                 self.write_struct_declaration(struct_decl)
             }
+            CDeclaration::ForwardStructDeclaration(name) => {
+                self.write("typedef struct ");
+                self.write_identifier(name);
+                self.write(" ");
+                self.write_identifier(name);
+                self.write(";");
+            }
+            CDeclaration::UnionDeclaration(union_decl) => self.write_union_declaration(union_decl),
+            CDeclaration::EnumDeclaration(enum_decl) => self.write_enum_declaration(enum_decl),
         }
     }
 
@@ -101,8 +193,24 @@ impl CAstWriter {
         self.write("typedef struct ");
         self.write_identifier(&struct_decl.name);
         self.write("{");
+        self.write_members(&struct_decl.members);
+        self.write("}");
+        self.write_identifier(&struct_decl.name);
+        self.write(";");
+    }
+
+    fn write_union_declaration(&mut self, union_decl: &CUnionDeclaration) {
+        self.write("typedef union ");
+        self.write_identifier(&union_decl.name);
+        self.write("{");
+        self.write_members(&union_decl.members);
+        self.write("}");
+        self.write_identifier(&union_decl.name);
+        self.write(";");
+    }
 
-        for member in &struct_decl.members {
+    fn write_members(&mut self, members: &[CTypedParam]) {
+        for member in members {
             if member.ty.points_to_struct() {
                 self.write("struct ");
             }
@@ -111,13 +219,27 @@ impl CAstWriter {
             self.write_identifier(&member.name);
             self.write(";");
         }
+    }
 
+    fn write_enum_declaration(&mut self, enum_decl: &CEnumDeclaration) {
+        self.write("typedef enum ");
+        self.write_identifier(&enum_decl.name);
+        self.write("{");
+        for (i, variant) in enum_decl.variants.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.write_identifier(variant);
+        }
         self.write("}");
-        self.write_identifier(&struct_decl.name);
+        self.write_identifier(&enum_decl.name);
         self.write(";");
     }
 
     fn write_function_declaration(&mut self, function_decl: &CFunctionDeclaration) {
+        if function_decl.is_static {
+            self.write("static ");
+        }
         self.write_type(&function_decl.ret_ty);
         self.write(" ");
         self.write_identifier(&function_decl.name);
@@ -186,6 +308,23 @@ impl CAstWriter {
         self.write_stmt(&while_stmt.body);
     }
 
+    fn write_for(&mut self, for_stmt: &CForStmt) {
+        self.write("for (");
+        if let Some(init) = &for_stmt.init {
+            self.write_expr(init);
+        }
+        self.write("; ");
+        if let Some(condition) = &for_stmt.condition {
+            self.write_expr(condition);
+        }
+        self.write("; ");
+        if let Some(step) = &for_stmt.step {
+            self.write_expr(step);
+        }
+        self.write(") ");
+        self.write_stmt(&for_stmt.body);
+    }
+
     fn write_return(&mut self, return_stmt: &CReturnStmt) {
         self.write("return");
         if let Some(expr) = &return_stmt.expr {
@@ -212,9 +351,21 @@ impl CAstWriter {
             CExpr::SizeOf(sizeof) => self.write_sizeof(sizeof),
             CExpr::Assignment(ass) => self.write_assignment(ass),
             CExpr::Type(ty) => self.write_type_expr(ty),
+            CExpr::InitializerList(values) => self.write_initializer_list(values),
         }
     }
 
+    fn write_initializer_list(&mut self, values: &[CExpr]) {
+        self.write("{");
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.write_expr(value);
+        }
+        self.write("}");
+    }
+
     fn write_type_expr(&mut self, ty: &CType) {
         self.write_type(ty);
     }
@@ -225,8 +376,29 @@ impl CAstWriter {
         self.write(")");
     }
 
+    /// Writes an operand of another expression, in parentheses if the operator binds less tightly
+    /// than the postfix operators. The AST does not keep the parentheses of the source, so this
+    /// keeps the structure of nested expressions intact.
+    fn write_operand(&mut self, expr: &CExpr) {
+        let needs_parens = match expr {
+            CExpr::Binary(binary) => binary.operator != CBinaryOperator::Index,
+            CExpr::Unary(_) | CExpr::Cast(_) | CExpr::Assignment(_) => true,
+            // a - -1 is not a decrement
+            CExpr::Constant(CConstant::Integer(i)) => *i < 0,
+            CExpr::Constant(CConstant::Float(f)) => f.is_sign_negative(),
+            _ => false,
+        };
+        if needs_parens {
+            self.write("(");
+            self.write_expr(expr);
+            self.write(")");
+        } else {
+            self.write_expr(expr);
+        }
+    }
+
     fn write_member(&mut self, member: &CMemberExpr) {
-        self.write_expr(&member.expression);
+        self.write_operand(&member.expression);
         self.write_member_operator(&member.operator);
         self.write_identifier(&member.identifier);
     }
@@ -243,7 +415,7 @@ impl CAstWriter {
     }
 
     fn write_call(&mut self, call: &CCallExpr) {
-        self.write_expr(&call.callee);
+        self.write_operand(&call.callee);
         self.write("(");
         for (i, arg) in call.args.iter().enumerate() {
             if i > 0 {
@@ -258,16 +430,20 @@ impl CAstWriter {
         self.write("(");
         self.write_type(&cast.ty);
         self.write(")");
-        self.write_expr(&cast.expr);
+        self.write_operand(&cast.expr);
     }
 
     fn write_binary(&mut self, binary: &CBinaryExpr) {
         let (left, right) = self.get_binary_op_token_left_right(&binary.operator);
-        self.write_expr(&binary.left);
+        self.write_operand(&binary.left);
         self.write(left);
-        self.write_expr(&binary.right);
-        if let Some(right) = right {
-            self.write(right);
+        match right {
+            // The index is already delimited by the brackets.
+            Some(right) => {
+                self.write_expr(&binary.right);
+                self.write(right);
+            }
+            None => self.write_operand(&binary.right),
         }
     }
 
@@ -319,7 +495,7 @@ impl CAstWriter {
         if let Some(before) = before {
             self.write(before);
         }
-        self.write_expr(&unary.expr);
+        self.write_operand(&unary.expr);
         if let Some(after) = after {
             self.write(after);
         }
@@ -345,12 +521,33 @@ impl CAstWriter {
 
     fn write_constant(&mut self, constant: &CConstant) {
         match constant {
-            CConstant::Integer(i) => self.write(&i.to_string()),
-            CConstant::Float(f) => self.write(&f.to_string()),
-            CConstant::String(s) => self.write(&format!("\"{}\"", s)),
+            CConstant::Integer(i) => self.write(i.to_string()),
+            // the debug format always has a fraction or an exponent, like 3.0 and 1e20
+            CConstant::Float(f) => self.write(format!("{:?}", f)),
+            CConstant::String(s) => self.write(format!("\"{}\"", Self::escape_string(s))),
         }
     }
 
+    /// Escapes a string so that the C literal contains exactly its UTF-8 bytes.
+    /// Everything outside of printable ASCII is written as an octal escape, which unlike hex
+    /// escapes never swallows the characters that follow it.
+    fn escape_string(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len());
+        for byte in s.bytes() {
+            match byte {
+                b'"' => escaped.push_str("\\\""),
+                b'\\' => escaped.push_str("\\\\"),
+                // `??` could start a trigraph
+                b'?' => escaped.push_str("\\?"),
+                b'\n' => escaped.push_str("\\n"),
+                b'\t' => escaped.push_str("\\t"),
+                b' '..=b'~' => escaped.push(byte as char),
+                _ => escaped.push_str(&format!("\\{:03o}", byte)),
+            }
+        }
+        escaped
+    }
+
     fn write_type(&mut self, ty: &CType) {
         match ty {
             CType::Void => self.write("void"),
@@ -364,8 +561,11 @@ impl CAstWriter {
             CType::I64 => self.write("int64_t"),
             CType::F32 => self.write("float"),
             CType::F64 => self.write("double"),
+            CType::Char => self.write("char"),
             CType::String => self.write("char*"),
             CType::Struct(s) => self.write(s),
+            CType::Union(s) => self.write(s),
+            CType::Enum(s) => self.write(s),
             CType::ISize => todo!(),
             CType::USize => todo!(),
             CType::Pointer(p) => {
@@ -376,6 +576,28 @@ impl CAstWriter {
                 self.write("&");
                 self.write_type(r);
             }
+            CType::Const(c) => {
+                self.write("const ");
+                self.write_type(c);
+            }
+            CType::Array(a) => {
+                self.write_type(a);
+                self.write("[]");
+            }
+            CType::FunctionPointer(ret, params) => {
+                self.write_type(ret);
+                self.write(" (*)(");
+                if params.is_empty() {
+                    self.write("void");
+                }
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.write_type(param);
+                }
+                self.write(")");
+            }
         }
     }
 }
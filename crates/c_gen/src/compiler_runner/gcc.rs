@@ -42,6 +42,10 @@ impl CodegenRunner for Gcc {
         cmd.arg("-Werror");
 
         cmd.args(options.build_files);
+        // libraries have to come after the files that use them
+        for library in &options.link_libraries {
+            cmd.arg(format!("-l{}", library));
+        }
 
         match options.optimization_level {
             OptLevel::Debug => {
@@ -13,6 +13,8 @@ pub struct RunnerOptions {
     pub output_name: String,
     pub output_path: PathBuf,
     pub build_files: Vec<PathBuf>,
+    /// Libraries to link against, passed to the compiler as `-l<name>`.
+    pub link_libraries: Vec<String>,
     pub optimization_level: OptLevel,
 }
 
@@ -1,10 +1,11 @@
-use std::{path::PathBuf, process::Command};
+use std::{fs, io::ErrorKind, path::PathBuf, process::Command};
 
-use c_ast::{ast::*, ty::CType};
+use c_ast::{ast::*, ty::CType, write::CAstWriter};
 
 pub mod c_ast;
 pub mod compiler_runner;
 pub mod lib_core;
+mod mangle;
 pub mod passes;
 
 const INTERNAL_MAIN: &str = "pine_lang_main";
@@ -12,13 +13,32 @@ const KI_GC_NAME: &str = "gc";
 const KI_GC_CLASS: &str = "GarbageCollector";
 const KI_GC_NEW_CALL_SUFFIX: &str = "__internal__new_gc";
 const KI_GC_NEW_CALL_PREFIX: &str = "_";
+const KI_STR_CLASS: &str = "PineStr";
+const KI_LIST_CLASS: &str = "PineList";
+const KI_CLOSURE_CLASS: &str = "PineClosure";
+const KI_CLOSURE_ENV: &str = "_env";
+const KI_ENUM_TAG: &str = "tag";
+const KI_ENUM_DATA: &str = "data";
 
 pub fn format_generated(source: PathBuf) {
-    // run clang-format on generated source
+    // run clang-format on generated source. The source compiles the same without it, so it is
+    // left as it is when clang-format is not installed.
     let mut cmd = Command::new("clang-format");
     cmd.arg("-i").arg(&source);
     cmd.arg("-style=file");
-    cmd.output().expect("failed to format generated source");
+    if let Err(err) = cmd.output() {
+        if err.kind() == ErrorKind::NotFound {
+            return;
+        }
+        panic!("failed to format generated source: {}", err);
+    }
+
+    // formatting moves lines, so the directives pointing back at the source have to follow
+    let c = fs::read_to_string(&source).expect("failed to read formatted source");
+    let renumbered = CAstWriter::renumber_line_directives(&c, &source);
+    if renumbered != c {
+        fs::write(&source, renumbered).expect("failed to write formatted source");
+    }
 }
 
 pub fn build_c_main_file(internal_main_include_name: String) -> CTranslationUnit {
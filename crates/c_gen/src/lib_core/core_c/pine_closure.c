@@ -0,0 +1,16 @@
+#include "pine_closure.h"
+#include "pine_gc.h"
+
+/**
+ * Allocates a closure on the gc heap.
+ *
+ * @param fn The lifted function, cast to the generic function pointer type.
+ * @param env The gc allocated environment with the captured variables.
+ */
+PineClosure *pine_closure_new(void (*fn)(void), void *env)
+{
+    PineClosure *closure = (PineClosure *)gc_malloc(&gc, sizeof(PineClosure));
+    closure->fn = fn;
+    closure->env = env;
+    return closure;
+}
@@ -0,0 +1,12 @@
+#ifndef PINE_CLOSURE
+#define PINE_CLOSURE
+
+typedef struct PineClosure
+{
+    void (*fn)(void); // the lifted function, which takes env as its first argument
+    void *env;        // the captured variables, NULL if nothing is captured
+} PineClosure;
+
+PineClosure *pine_closure_new(void (*fn)(void), void *env);
+
+#endif
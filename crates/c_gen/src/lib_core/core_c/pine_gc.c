@@ -1,4 +1,6 @@
 #include "pine_gc.h"
+// log.h defines a `log` macro, so it is kept out of the headers generated code includes
+#include "log.h"
 #include <setjmp.h>
 #include <string.h>
 
@@ -286,25 +288,14 @@ void gc_mark_stack(KiGc *gc)
     char *bot = gc->bottom;
     char *top = &stk;
 
-    if (bot == top)
+    // the stack grows down on most platforms, but not on all of them
+    char *low = bot < top ? bot : top;
+    char *high = bot < top ? top : bot;
+    // pointers on the stack are aligned, `stk` is not necessarily
+    low = (char *)((uintptr_t)low & ~(uintptr_t)(sizeof(char *) - 1));
+    for (char *p = low; p + sizeof(char *) <= high; p += sizeof(char *))
     {
-        return;
-    }
-
-    if (bot > top)
-    {
-        for (char *p = top; p >= bot; p = ((char *)p) - sizeof(char *))
-        {
-            gc_mark_alloc(gc, *((void **)p));
-        }
-    }
-
-    if (bot < top)
-    {
-        for (char *p = top; p <= bot; p = ((char *)p) + sizeof(char *))
-        {
-            gc_mark_alloc(gc, *((void **)p));
-        }
+        gc_mark_alloc(gc, *((void **)p));
     }
 }
 
@@ -388,6 +379,9 @@ size_t gc_sweep(KiGc *gc)
     }
     LOG_INFO("GC sweep: %lu allocations (%lu bytes)", freed_allocs, freed_bytes);
     gc_alloc_map_resize_to_fit(gc->allocs);
+    // without this, a heap that stays close to the old limit is collected on almost every allocation
+    AllocationMap *am = gc->allocs;
+    am->sweep_limit = gc_calc_sweep_limit(am->nitems, am->nslots, am->sweep_factor);
     return freed_bytes;
 }
 
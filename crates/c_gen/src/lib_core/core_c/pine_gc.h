@@ -1,7 +1,6 @@
 #ifndef PINE_GC
 #define PINE_GC
 
-#include "log.h"
 #include <stdbool.h>
 #include <stdint.h>
 #include <stdio.h>
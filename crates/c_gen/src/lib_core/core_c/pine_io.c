@@ -15,4 +15,10 @@ void print_bool(uint8_t b)
     {
         printf("false\n");
     }
+}
+
+void print_str(PineStr *s)
+{
+    fwrite(s->data, 1, s->len, stdout);
+    printf("\n");
 }
\ No newline at end of file
@@ -4,7 +4,10 @@
 #include <stdint.h>
 #include <stdio.h>
 
+#include "pine_str.h"
+
 void print_int(int64_t i);
 void print_bool(uint8_t b);
+void print_str(PineStr *s);
 
 #endif
\ No newline at end of file
@@ -0,0 +1,92 @@
+#include "pine_list.h"
+#include "pine_gc.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define PINE_LIST_MIN_CAP 4
+
+/**
+ * Allocates an array of len elements on the gc heap.
+ *
+ * @param init The initial elements, or NULL for an empty array.
+ */
+void *pine_array_new(int64_t elem_size, int64_t len, const void *init)
+{
+    // gc_malloc does not like allocations of size 0
+    void *data = gc_malloc(&gc, len > 0 ? elem_size * len : 1);
+    if (init)
+    {
+        memcpy(data, init, elem_size * len);
+    }
+    return data;
+}
+
+PineList *pine_list_new(int64_t elem_size, int64_t len, const void *init)
+{
+    PineList *list = (PineList *)gc_malloc(&gc, sizeof(PineList));
+    list->len = len;
+    list->cap = len > PINE_LIST_MIN_CAP ? len : PINE_LIST_MIN_CAP;
+    list->elem_size = elem_size;
+    list->data = (char *)pine_array_new(elem_size, list->cap, NULL);
+    if (init)
+    {
+        memcpy(list->data, init, elem_size * len);
+    }
+    return list;
+}
+
+void pine_list_push(PineList *list, const void *elem)
+{
+    if (list->len == list->cap)
+    {
+        list->cap *= 2;
+        char *data = (char *)pine_array_new(list->elem_size, list->cap, NULL);
+        memcpy(data, list->data, list->elem_size * list->len);
+        list->data = data;
+    }
+    memcpy(list->data + list->elem_size * list->len, elem, list->elem_size);
+    list->len++;
+}
+
+/**
+ * Removes the last element of the list.
+ *
+ * @return A pointer to the removed element. It stays valid until the next push.
+ */
+void *pine_list_pop(PineList *list, const char *location)
+{
+    if (list->len == 0)
+    {
+        fprintf(stderr, "%s: pop from an empty list\n", location);
+        exit(1);
+    }
+    list->len--;
+    return list->data + list->elem_size * list->len;
+}
+
+/**
+ * A bounds checked pointer to the element at index.
+ */
+void *pine_list_at(PineList *list, int64_t index, const char *location)
+{
+    return list->data + list->elem_size * pine_check_index(index, list->len, location);
+}
+
+/**
+ * Aborts the program if the index is out of bounds.
+ *
+ * @param location The position of the index expression in the Pine source.
+ * @return The index.
+ */
+int64_t pine_check_index(int64_t index, int64_t len, const char *location)
+{
+    if (index < 0 || index >= len)
+    {
+        fprintf(stderr, "%s: index %lld is out of bounds for length %lld\n", location, (long long)index,
+                (long long)len);
+        exit(1);
+    }
+    return index;
+}
@@ -0,0 +1,23 @@
+#ifndef PINE_LIST
+#define PINE_LIST
+
+#include <stdint.h>
+
+typedef struct PineList
+{
+    int64_t len;       // number of elements in the list
+    int64_t cap;       // number of elements that fit into the buffer
+    int64_t elem_size; // size of a single element in bytes
+    char *data;        // gc allocated buffer holding the elements
+} PineList;
+
+void *pine_array_new(int64_t elem_size, int64_t len, const void *init);
+
+PineList *pine_list_new(int64_t elem_size, int64_t len, const void *init);
+void pine_list_push(PineList *list, const void *elem);
+void *pine_list_pop(PineList *list, const char *location);
+void *pine_list_at(PineList *list, int64_t index, const char *location);
+
+int64_t pine_check_index(int64_t index, int64_t len, const char *location);
+
+#endif
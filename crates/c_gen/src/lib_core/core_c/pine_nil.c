@@ -0,0 +1,20 @@
+#include "pine_nil.h"
+
+#include <stdio.h>
+#include <stdlib.h>
+
+/**
+ * Aborts the program if an optional value is nil.
+ *
+ * @param location The position of the unwrapped expression in the Pine source.
+ * @return The value.
+ */
+void *pine_unwrap(void *value, const char *location)
+{
+    if (!value)
+    {
+        fprintf(stderr, "%s: unwrapped a nil value\n", location);
+        exit(1);
+    }
+    return value;
+}
@@ -0,0 +1,6 @@
+#ifndef PINE_NIL
+#define PINE_NIL
+
+void *pine_unwrap(void *value, const char *location);
+
+#endif
@@ -0,0 +1,88 @@
+#include "pine_str.h"
+#include "pine_gc.h"
+
+#include <string.h>
+
+/**
+ * Allocates a string of the given length on the gc heap.
+ *
+ * The bytes are stored right after the string header in the same allocation.
+ */
+static PineStr *pine_str_alloc(int64_t len, char **data)
+{
+    PineStr *s = (PineStr *)gc_malloc(&gc, sizeof(PineStr) + len + 1);
+    *data = (char *)(s + 1);
+    (*data)[len] = '\0';
+    s->len = len;
+    s->data = *data;
+    return s;
+}
+
+PineStr *pine_str_concat(PineStr *a, PineStr *b)
+{
+    char *data;
+    PineStr *s = pine_str_alloc(a->len + b->len, &data);
+    memcpy(data, a->data, a->len);
+    memcpy(data + a->len, b->data, b->len);
+    return s;
+}
+
+/**
+ * Copies a null terminated C string, e.g. one returned by an extern function, onto the gc heap.
+ * A null pointer becomes the empty string.
+ */
+PineStr *pine_str_from_cstr(const char *s)
+{
+    if (s == NULL)
+    {
+        s = "";
+    }
+    int64_t len = (int64_t)strlen(s);
+    char *data;
+    PineStr *str = pine_str_alloc(len, &data);
+    memcpy(data, s, len);
+    return str;
+}
+
+/**
+ * Like pine_str_from_cstr, but a null pointer becomes nil.
+ */
+PineStr *pine_str_from_nullable_cstr(const char *s)
+{
+    return s == NULL ? NULL : pine_str_from_cstr(s);
+}
+
+/**
+ * Passes an optional string to C, where nil becomes a null pointer.
+ */
+const char *pine_str_to_nullable_cstr(PineStr *s)
+{
+    return s == NULL ? NULL : s->data;
+}
+
+/**
+ * Compares two strings byte by byte.
+ *
+ * @return A negative value if a sorts before b, zero if they are equal and a positive value
+ * otherwise.
+ */
+int64_t pine_str_compare(PineStr *a, PineStr *b)
+{
+    int64_t len = a->len < b->len ? a->len : b->len;
+    int cmp = memcmp(a->data, b->data, len);
+    if (cmp != 0)
+    {
+        return cmp;
+    }
+    return a->len - b->len;
+}
+
+uint8_t pine_str_equals(PineStr *a, PineStr *b)
+{
+    return a->len == b->len && memcmp(a->data, b->data, a->len) == 0;
+}
+
+int64_t str_len(PineStr *s)
+{
+    return s->len;
+}
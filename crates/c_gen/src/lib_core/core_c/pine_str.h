@@ -0,0 +1,20 @@
+#ifndef PINE_STR
+#define PINE_STR
+
+#include <stdint.h>
+
+typedef struct PineStr
+{
+    int64_t len;      // number of bytes, without the trailing null byte
+    const char *data; // the bytes of the string, always null terminated
+} PineStr;
+
+PineStr *pine_str_concat(PineStr *a, PineStr *b);
+PineStr *pine_str_from_cstr(const char *s);
+PineStr *pine_str_from_nullable_cstr(const char *s);
+const char *pine_str_to_nullable_cstr(PineStr *s);
+int64_t pine_str_compare(PineStr *a, PineStr *b);
+uint8_t pine_str_equals(PineStr *a, PineStr *b);
+int64_t str_len(PineStr *s);
+
+#endif
@@ -3,26 +3,50 @@ use std::{
     path::{Path, PathBuf},
 };
 
-const PATH_CORE_C: &str = "crates/c_gen/src/lib_core/core_c";
+/// The runtime in `core_c`, embedded into the compiler so it runs from any directory. The
+/// build script lists its files.
+const CORE_C: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/core_c.rs"));
 
-pub fn fetch_core_c() -> Vec<PathBuf> {
-    let p = Path::new(PATH_CORE_C);
-    let c_std = fs::read_dir(p).unwrap();
-    c_std
+/// The `.c` and `.h` files of a runtime directory.
+pub fn fetch_core_c<P: AsRef<Path>>(runtime_dir: P) -> Vec<PathBuf> {
+    let runtime_dir = runtime_dir.as_ref();
+    let entries = fs::read_dir(runtime_dir).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read the runtime directory {}: {}",
+            runtime_dir.display(),
+            e
+        )
+    });
+    entries
         .map(|entry| entry.unwrap().path())
         .filter(|path| {
-            path.is_file() && path.extension().unwrap() == "h" || path.extension().unwrap() == "c"
+            path.is_file() && matches!(path.extension(), Some(ext) if ext == "h" || ext == "c")
         })
         .collect()
 }
 
-pub fn copy_core_c<P: AsRef<Path>>(copy_to: P) {
+/// Copies the runtime into `copy_to` and returns the paths of the copies.
+///
+/// The runtime is the one embedded into the compiler, unless `runtime_dir` points at another.
+pub fn copy_core_c<P: AsRef<Path>>(copy_to: P, runtime_dir: Option<&Path>) -> Vec<PathBuf> {
     let copy_to = copy_to.as_ref();
     fs::create_dir_all(copy_to).unwrap();
-    let file = fetch_core_c();
-    for header_file in file {
-        let file_name: &std::ffi::OsStr = header_file.file_name().unwrap();
-        let copy_to = copy_to.join(file_name);
-        fs::copy(header_file, copy_to).unwrap();
+    let mut copies = Vec::new();
+    match runtime_dir {
+        Some(runtime_dir) => {
+            for file in fetch_core_c(runtime_dir) {
+                let copy_to = copy_to.join(file.file_name().unwrap());
+                fs::copy(file, &copy_to).unwrap();
+                copies.push(copy_to);
+            }
+        }
+        None => {
+            for (file_name, contents) in CORE_C {
+                let copy_to = copy_to.join(file_name);
+                fs::write(&copy_to, contents).unwrap();
+                copies.push(copy_to);
+            }
+        }
     }
+    copies
 }
@@ -0,0 +1,288 @@
+//! The C names of Pine identifiers.
+//!
+//! Every identifier of a program gets the prefix `pn_`, which neither C, the headers the runtime
+//! includes nor the runtime itself use: `int` becomes `pn_int`, `SEEK_SET` becomes `pn_SEEK_SET`.
+//! The declarations of a module are prefixed with its path as well, so `geometry.shapes.area`
+//! becomes `pn_geometry__shapes__area`.
+//!
+//! The code generator joins names with `__` and prefixes its own names with `_`. Names that start
+//! or end with an underscore, contain `__` or are one of the words the generator joins with get a
+//! trailing underscore, so two identifiers never end up with the same name.
+//!
+//! The translation units are named after their files and keep the name, unless C, its library or
+//! the runtime could already use it. Those are escaped with a trailing underscore as well.
+
+/// The keywords of C99, and the macros `stdbool.h` defines.
+const C_KEYWORDS: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "bool", "true", "false",
+];
+
+/// The parts of the C standard library the runtime includes or links, which the generated code
+/// sees through its headers.
+const C_LIBRARY: &[&str] = &[
+    // types and macros
+    "size_t",
+    "ptrdiff_t",
+    "wchar_t",
+    "FILE",
+    "fpos_t",
+    "div_t",
+    "ldiv_t",
+    "va_list",
+    "jmp_buf",
+    "NULL",
+    "EOF",
+    "BUFSIZ",
+    "RAND_MAX",
+    "EXIT_SUCCESS",
+    "EXIT_FAILURE",
+    "errno",
+    "assert",
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+    "intptr_t",
+    "uintptr_t",
+    "intmax_t",
+    "uintmax_t",
+    "stdin",
+    "stdout",
+    "stderr",
+    // stdio.h
+    "printf",
+    "fprintf",
+    "sprintf",
+    "snprintf",
+    "vprintf",
+    "vfprintf",
+    "vsprintf",
+    "vsnprintf",
+    "scanf",
+    "fscanf",
+    "sscanf",
+    "puts",
+    "fputs",
+    "putchar",
+    "fputc",
+    "putc",
+    "getchar",
+    "fgetc",
+    "getc",
+    "fgets",
+    "gets",
+    "ungetc",
+    "fopen",
+    "fclose",
+    "freopen",
+    "fflush",
+    "fread",
+    "fwrite",
+    "fseek",
+    "ftell",
+    "rewind",
+    "feof",
+    "ferror",
+    "clearerr",
+    "perror",
+    "remove",
+    "rename",
+    "tmpfile",
+    "tmpnam",
+    "setbuf",
+    "setvbuf",
+    "fgetpos",
+    "fsetpos",
+    // stdlib.h
+    "malloc",
+    "calloc",
+    "realloc",
+    "free",
+    "abort",
+    "exit",
+    "atexit",
+    "getenv",
+    "system",
+    "atoi",
+    "atol",
+    "atoll",
+    "atof",
+    "strtol",
+    "strtoll",
+    "strtoul",
+    "strtoull",
+    "strtod",
+    "strtof",
+    "qsort",
+    "bsearch",
+    "abs",
+    "labs",
+    "llabs",
+    "div",
+    "ldiv",
+    "rand",
+    "srand",
+    // string.h
+    "memcpy",
+    "memmove",
+    "memset",
+    "memcmp",
+    "memchr",
+    "strcpy",
+    "strncpy",
+    "strcat",
+    "strncat",
+    "strcmp",
+    "strncmp",
+    "strlen",
+    "strchr",
+    "strrchr",
+    "strstr",
+    "strtok",
+    "strdup",
+    "strerror",
+    "strspn",
+    "strcspn",
+    "strpbrk",
+    "strcoll",
+    "strxfrm",
+    // setjmp.h
+    "setjmp",
+    "longjmp",
+    // the math functions gcc knows as builtins, even without including math.h
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "atan2",
+    "sinh",
+    "cosh",
+    "tanh",
+    "exp",
+    "log",
+    "log10",
+    "log2",
+    "pow",
+    "sqrt",
+    "cbrt",
+    "ceil",
+    "floor",
+    "round",
+    "trunc",
+    "fabs",
+    "fmod",
+    "fmin",
+    "fmax",
+    "hypot",
+];
+
+/// The names the runtime in `core_c` declares that the prefixes below do not cover.
+const RUNTIME: &[&str] = &[
+    "main",
+    "gc",
+    "KiGc",
+    "Allocation",
+    "AllocationMap",
+    "Gc_Flag",
+    "PineStr",
+    "PineList",
+    "PineClosure",
+    "print_int",
+    "print_bool",
+    "print_str",
+    "str_len",
+    "log_level_strings",
+    "LOGLEVEL",
+];
+
+/// Prefixes of the functions, types and macros of the runtime.
+const RUNTIME_PREFIXES: &[&str] = &["pine_", "PINE_", "gc_", "GC_", "LOG"];
+
+/// The words the code generator puts between the names it joins.
+const SEPARATORS: &[&str] = &["method", "Tag", "Data"];
+
+/// The prefix of every identifier of a program.
+const PREFIX: &str = "pn_";
+
+/// The name of a variable, parameter, field or variant.
+///
+/// int  =>  pn_int
+/// x_  =>  pn_x__
+pub fn identifier(name: &str) -> String {
+    format!("{}{}", PREFIX, segment(name))
+}
+
+/// The name of a declaration of a module, prefixed by the path of the module.
+///
+/// area  =>  pn_area
+/// geometry.shapes.area  =>  pn_geometry__shapes__area
+pub fn path_name(path: &[String], name: &str) -> String {
+    let segments = path.iter().map(String::as_str).chain([name]);
+    let segments = segments.map(segment).collect::<Vec<_>>();
+    format!("{}{}", PREFIX, segments.join("__"))
+}
+
+/// A name that is joined with others by `__`.
+///
+/// Circle  =>  Circle
+/// Tag  =>  Tag_
+pub fn segment(name: &str) -> String {
+    escape(name, is_ambiguous)
+}
+
+/// The name of a translation unit, which is also the name of its files.
+///
+/// main  =>  main_
+/// util  =>  util
+/// my-app  =>  my_app_
+pub fn unit_name(name: &str) -> String {
+    escape(name, is_reserved)
+}
+
+/// geometry.shapes  =>  geometry__shapes
+pub fn unit_path(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| unit_name(segment))
+        .collect::<Vec<_>>()
+        .join("__")
+}
+
+/// Replaces the characters C does not allow in names. Names that contained any of them or that
+/// are `reserved` get a trailing underscore.
+fn escape(name: &str, reserved: fn(&str) -> bool) -> String {
+    let valid = name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    match valid && !reserved(name) {
+        true => name.to_string(),
+        false => {
+            let name = name.replace(|c: char| !c.is_alphanumeric() && c != '_', "_");
+            format!("{}_", name)
+        }
+    }
+}
+
+/// Whether joining the name with others could produce the name of another declaration.
+fn is_ambiguous(name: &str) -> bool {
+    name.starts_with('_')
+        || name.ends_with('_')
+        || name.contains("__")
+        || SEPARATORS.contains(&name)
+}
+
+fn is_reserved(name: &str) -> bool {
+    is_ambiguous(name)
+        || C_KEYWORDS.contains(&name)
+        || C_LIBRARY.contains(&name)
+        || RUNTIME.contains(&name)
+        || RUNTIME_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
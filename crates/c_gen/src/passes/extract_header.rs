@@ -1,4 +1,4 @@
-use crate::c_ast::ast::{CDeclaration, CFunctionDeclaration, CHeaderInclude, CTranslationUnit};
+use crate::c_ast::ast::{CDeclaration, CHeaderInclude, CTranslationUnit};
 
 /// A utility struct for extracting header information from a C translation unit.
 pub struct ExtractHeader {}
@@ -6,45 +6,43 @@ pub struct ExtractHeader {}
 impl ExtractHeader {
     /// Extracts header information from the provided C translation unit.
     /// Every source file gets a matching header file since C cares about function ordering.
-    /// Function declarations as well as struct declarations are stored in the header units.
-    /// Struct declarations are removed from the source unit.
+    /// Function declarations as well as type declarations are stored in the header units.
+    /// Type declarations come first so that every function prototype can refer to them, and are
+    /// removed from the source unit. Instances of generic types stay in the source unit, since
+    /// several headers may need them, and are only forward declared in the header. Static
+    /// functions are left out.
     pub fn extract(source: &mut CTranslationUnit) -> CTranslationUnit {
-        let mut declarations = Vec::new();
+        let mut types = Vec::new();
+        let mut functions = Vec::new();
         for decl in &source.implementation {
             match decl {
+                CDeclaration::FunctionDeclaration(d) if d.is_static => {}
                 CDeclaration::FunctionDeclaration(d) => {
-                    declarations.push(CDeclaration::FunctionDeclaration(
-                        Self::extract_header_from_function(d),
-                    ));
+                    functions.push(CDeclaration::FunctionDeclaration(d.prototype()));
                 }
-                CDeclaration::StructDeclaration(_) => {
-                    declarations.push(decl.clone());
+                CDeclaration::StructDeclaration(s) if s.is_instance => {
+                    types.push(CDeclaration::ForwardStructDeclaration(s.name.clone()));
+                }
+                CDeclaration::StructDeclaration(_)
+                | CDeclaration::ForwardStructDeclaration(_)
+                | CDeclaration::UnionDeclaration(_)
+                | CDeclaration::EnumDeclaration(_) => {
+                    types.push(decl.clone());
                 }
                 CDeclaration::GlobalVariableDeclaration(_) => {}
             }
         }
+        let declarations = types.into_iter().chain(functions).collect();
 
-        // filter out structs
-        source
-            .implementation
-            .retain(|decl| !matches!(decl, CDeclaration::StructDeclaration(_)));
+        // filter out type declarations
+        source.implementation.retain(|decl| match decl {
+            CDeclaration::StructDeclaration(s) => s.is_instance,
+            decl => !decl.is_type_declaration(),
+        });
 
         let includes = source.header_includes.clone();
         let name = source.name.clone();
         source.header_includes = vec![CHeaderInclude::new(format!("{}.h", name), false)];
         CTranslationUnit::new(name, true, includes, declarations)
     }
-
-    fn extract_header_from_function(decl: &CFunctionDeclaration) -> CFunctionDeclaration {
-        let name = decl.name.clone();
-        let params = decl.params.clone();
-        let ret_ty = decl.ret_ty.clone();
-
-        CFunctionDeclaration {
-            name,
-            params,
-            ret_ty,
-            body: None,
-        }
-    }
 }
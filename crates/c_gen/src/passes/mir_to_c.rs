@@ -0,0 +1,1468 @@
+use crate::c_ast::ast::*;
+use crate::c_ast::op::{CAssignmentOperator, CBinaryOperator, CUnaryOperator};
+use crate::c_ast::ty::CType;
+use crate::mangle;
+use crate::{
+    INTERNAL_MAIN, KI_CLOSURE_CLASS, KI_CLOSURE_ENV, KI_ENUM_DATA, KI_ENUM_TAG, KI_GC_NAME,
+    KI_GC_NEW_CALL_PREFIX, KI_GC_NEW_CALL_SUFFIX, KI_LIST_CLASS, KI_STR_CLASS,
+};
+use base::{file_cache::FileCache, located::Located};
+use hir::ir::{
+    expr::Literal, ty::Ty, EnumId, Function, FunctionId, FunctionKind, Item, LocalId, Module,
+    ModuleId, Package, StructId,
+};
+use mir::ir::{
+    BlockId, Body, LocalDecl, Operand, Package as Mir, Place, PlaceBase, Projection, Rvalue,
+    Statement, StatementS, Terminator,
+};
+use std::collections::HashSet;
+use syntax::ast::op::{OpInfix, OpPrefix};
+use syntax::builtins::Intrinsic;
+
+/// Generates C from the MIR of the function bodies, and the HIR of the declarations.
+pub struct MirToCAst<'a> {
+    package: &'a Package,
+    mir: &'a Mir,
+    // Used to point runtime errors at the Pine source.
+    files: &'a FileCache,
+    // The function that is lowered, whose locals are referred to by id.
+    function: Option<&'a Function>,
+    body: Option<&'a Body>,
+    // String literals are emitted as global `PineStr`s that the code points to.
+    string_literals: Vec<CDeclaration>,
+    // Closures without captured variables are emitted as global `PineClosure`s as well.
+    static_closures: Vec<CDeclaration>,
+    unit_name: String,
+    // The C names of the local declarations this unit has already generated.
+    instances: HashSet<String>,
+    // Instances of generic functions and lifted lambdas this unit uses, which are generated
+    // once the declarations of the module are done.
+    used_functions: HashSet<FunctionId>,
+    pending_functions: Vec<FunctionId>,
+    // The declarations only this unit uses, like the instances of generic types with their
+    // constructors, instances of generic functions, lifted lambdas with their environments and
+    // the functions calling closures.
+    local_decls: Vec<CDeclaration>,
+}
+
+impl<'a> MirToCAst<'a> {
+    pub fn new(package: &'a Package, mir: &'a Mir, files: &'a FileCache) -> Self {
+        Self {
+            package,
+            mir,
+            files,
+            function: None,
+            body: None,
+            string_literals: Vec::new(),
+            static_closures: Vec::new(),
+            unit_name: String::new(),
+            instances: HashSet::new(),
+            used_functions: HashSet::new(),
+            pending_functions: Vec::new(),
+            local_decls: Vec::new(),
+        }
+    }
+
+    /// Lowers one module of the package into a translation unit.
+    pub fn transform(
+        package: &'a Package,
+        mir: &'a Mir,
+        module: ModuleId,
+        files: &'a FileCache,
+    ) -> CTranslationUnit {
+        let mut gen = Self::new(package, mir, files);
+        gen.build_translation_unit(package.module(module))
+    }
+
+    /// The name of the translation unit of a module. `geometry.shapes` becomes
+    /// `geometry__shapes`, the root module keeps the name of its file.
+    pub fn unit_name(module: &Module) -> String {
+        match module.is_root() {
+            true => mangle::unit_name(&module.source.filename()),
+            false => mangle::unit_path(&module.path),
+        }
+    }
+
+    fn build_translation_unit(&mut self, module: &'a Module) -> CTranslationUnit {
+        let name = Self::unit_name(module);
+        self.unit_name = name.clone();
+        let header_includes = self.include_headers(module);
+
+        // transform the declarations
+        let decls = module
+            .items
+            .iter()
+            .flat_map(|item| self.build_item(item))
+            .collect::<Vec<_>>();
+
+        // the init functions for the structs and enum variants
+        let inits = module
+            .items
+            .iter()
+            .flat_map(|item| self.build_new_gc_inits(item))
+            .collect::<Vec<_>>();
+
+        // the instances and lambdas the module uses, which can use further ones
+        while let Some(id) = self.pending_functions.pop() {
+            let function = self.build_function(id);
+            self.local_decls.push(function.into());
+        }
+        let local_decls = std::mem::take(&mut self.local_decls);
+        let (local_types, local_functions): (Vec<_>, Vec<_>) = local_decls
+            .into_iter()
+            .partition(CDeclaration::is_type_declaration);
+        // static functions are not declared in the header, so they get their prototypes here
+        let local_prototypes = local_functions.iter().map(|decl| match decl {
+            CDeclaration::FunctionDeclaration(f) => f.prototype().into(),
+            _ => unreachable!("Local declarations are types or functions"),
+        });
+
+        // the string literals have to be defined before the functions using them
+        let string_literals = std::mem::take(&mut self.string_literals);
+        let static_closures = std::mem::take(&mut self.static_closures);
+        let declarations = local_types
+            .into_iter()
+            .chain(local_prototypes.collect::<Vec<_>>())
+            .chain(string_literals)
+            .chain(static_closures)
+            .chain(decls)
+            .chain(inits)
+            .chain(local_functions)
+            .collect::<Vec<_>>();
+
+        CTranslationUnit {
+            name,
+            is_header: false,
+            header_includes,
+            implementation: declarations,
+        }
+    }
+
+    fn include_headers(&mut self, module: &Module) -> Vec<CHeaderInclude> {
+        let mut includes = vec![
+            CHeaderInclude::new("stdint.h", true),
+            CHeaderInclude::new("pine_gc.h", false),
+            CHeaderInclude::new("pine_io.h", false),
+            CHeaderInclude::new("pine_str.h", false),
+            CHeaderInclude::new("pine_list.h", false),
+            CHeaderInclude::new("pine_nil.h", false),
+            CHeaderInclude::new("pine_closure.h", false),
+        ];
+        // the headers of imported modules declare their functions and types
+        for import in &module.imports {
+            let unit = Self::unit_name(self.package.module(*import));
+            includes.push(CHeaderInclude::new(format!("{}.h", unit), false));
+        }
+        for item in &module.items {
+            let Item::Function(id) = item else {
+                continue;
+            };
+            if let FunctionKind::Extern {
+                header: Some(header),
+            } = &self.package.function(*id).kind
+            {
+                let include = CHeaderInclude::new(header, true);
+                if !includes.contains(&include) {
+                    includes.push(include);
+                }
+            }
+        }
+        includes
+    }
+
+    fn build_item(&mut self, item: &Item) -> Vec<CDeclaration> {
+        match item {
+            Item::Function(id) => match &self.package.function(*id).kind {
+                // functions from a header are declared by including it
+                FunctionKind::Extern { header: Some(_) } => vec![],
+                FunctionKind::Extern { header: None } => {
+                    vec![self.build_extern_prototype(*id).into()]
+                }
+                _ => vec![self.build_function(*id).into()],
+            },
+            Item::Struct(id) => vec![self.build_struct_declaration(*id).into()],
+            Item::Enum(id) => self.build_enum_declaration(*id),
+        }
+    }
+
+    fn build_new_gc_inits(&mut self, item: &Item) -> Vec<CDeclaration> {
+        match item {
+            Item::Function(_) => vec![],
+            Item::Struct(id) if !self.package.struct_(*id).constructed => vec![],
+            Item::Struct(id) => {
+                let members = self.build_struct_members(*id);
+                let name = self.struct_name(*id);
+                vec![self.build_struct_new_gc(&name, members).into()]
+            }
+            Item::Enum(id) => {
+                let variants = 0..self.package.enum_(*id).variants.len();
+                variants
+                    .map(|variant| self.build_variant_new_gc(*id, variant).into())
+                    .collect()
+            }
+        }
+    }
+
+    /// Makes sure the unit generates the function, if it is an instance or a lambda.
+    fn use_function(&mut self, id: FunctionId) {
+        if self.package.function(id).is_local() && self.used_functions.insert(id) {
+            self.pending_functions.push(id);
+        }
+    }
+
+    /// The C name of a function. The functions of the root module keep their name, apart from
+    /// `main` and the names `mangle` escapes.
+    ///
+    /// geometry.shapes.area  =>  pn_geometry__shapes__area
+    /// p.len()               =>  pn_Point__method__len
+    /// first<i64, str>       =>  pn_first__i64__str
+    /// a lambda              =>  _main__lambda_0
+    /// free                  =>  pn_free
+    fn function_name(&self, id: FunctionId) -> String {
+        let function = self.package.function(id);
+        let module = self.package.module(function.module);
+        match &function.kind {
+            FunctionKind::Function => Self::path_name(&module.path, &function.name),
+            FunctionKind::Method { of } => {
+                Self::method_name(self.type_name(of), mangle::segment(&function.name))
+            }
+            FunctionKind::Instance { type_args } => format!(
+                "{}__{}",
+                Self::path_name(&module.path, &function.name),
+                self.mangle_type_args(type_args)
+            ),
+            FunctionKind::Lambda { .. } => {
+                format!("_{}__{}", Self::unit_name(module), function.name)
+            }
+            // extern functions keep their C name
+            FunctionKind::Extern { .. } => function.name.clone(),
+        }
+    }
+
+    fn path_name(module_path: &[String], name: &str) -> String {
+        match module_path.is_empty() && name == "main" {
+            true => INTERNAL_MAIN.to_string(),
+            false => mangle::path_name(module_path, name),
+        }
+    }
+
+    /// The name of a type object or enum, which methods are prefixed with.
+    fn type_name(&self, ty: &Ty) -> String {
+        match ty {
+            Ty::Struct(id) => self.struct_name(*id),
+            Ty::Enum(id) => self.enum_name(*id),
+            _ => unreachable!("Only type objects and enums have methods"),
+        }
+    }
+
+    /// Pair<i64, str>   =>  pn_Pair__i64__str
+    /// geometry.Circle  =>  pn_geometry__Circle
+    fn struct_name(&self, id: StructId) -> String {
+        let s = self.package.struct_(id);
+        let name = mangle::path_name(&self.package.module(s.module).path, &s.name);
+        match s.is_instance() {
+            true => format!("{}__{}", name, self.mangle_type_args(&s.type_args)),
+            false => name,
+        }
+    }
+
+    /// geometry.Shape  =>  pn_geometry__Shape
+    fn enum_name(&self, id: EnumId) -> String {
+        let e = self.package.enum_(id);
+        mangle::path_name(&self.package.module(e.module).path, &e.name)
+    }
+
+    /// [i64; 3], List<str?>  =>  array3_i64__list_opt_str
+    fn mangle_type_args(&self, args: &[Ty]) -> String {
+        args.iter()
+            .map(|arg| self.mangle_type(arg))
+            .collect::<Vec<_>>()
+            .join("__")
+    }
+
+    fn mangle_type(&self, ty: &Ty) -> String {
+        match ty {
+            Ty::Unit => "unit".to_string(),
+            Ty::Bool => "bool".to_string(),
+            Ty::I8 => "i8".to_string(),
+            Ty::I32 => "i32".to_string(),
+            Ty::I64 => "i64".to_string(),
+            Ty::U8 => "u8".to_string(),
+            Ty::U32 => "u32".to_string(),
+            Ty::U64 => "u64".to_string(),
+            Ty::F32 => "f32".to_string(),
+            Ty::F64 => "f64".to_string(),
+            Ty::Str => "str".to_string(),
+            Ty::Struct(id) => self.struct_name(*id),
+            Ty::Enum(id) => self.enum_name(*id),
+            Ty::Array(elem, len) => format!("array{}_{}", len, self.mangle_type(elem)),
+            Ty::List(elem) => format!("list_{}", self.mangle_type(elem)),
+            Ty::Optional(inner) => format!("opt_{}", self.mangle_type(inner)),
+            Ty::Function(params, ret) => {
+                let types = params.iter().chain(std::iter::once(ret.as_ref()));
+                let types = types.map(|ty| self.mangle_type(ty)).collect::<Vec<_>>();
+                format!("fun{}_{}", params.len(), types.join("_"))
+            }
+        }
+    }
+
+    /// Generates the instance of a generic type object in this unit, together with its
+    /// constructor if the program creates objects of it.
+    fn struct_instance(&mut self, id: StructId) {
+        let name = self.struct_name(id);
+        if !self.instances.insert(name.clone()) {
+            return;
+        }
+        let members = self.build_struct_members(id);
+        let instance = CStructDeclaration::instance(CIdentifier::new(&name), members.clone());
+        self.local_decls.push(instance.into());
+        if self.package.struct_(id).constructed {
+            let mut new_gc = self.build_struct_new_gc(&name, members);
+            new_gc.is_static = true;
+            self.local_decls.push(new_gc.into());
+        }
+    }
+
+    fn build_struct_members(&mut self, id: StructId) -> Vec<CTypedParam> {
+        let fields = &self.package.struct_(id).fields;
+        fields
+            .iter()
+            .map(|field| {
+                let name = CIdentifier::new(mangle::identifier(&field.name));
+                CTypedParam::new(name, self.build_ty(&field.ty))
+            })
+            .collect()
+    }
+
+    fn build_struct_declaration(&mut self, id: StructId) -> CStructDeclaration {
+        let name = CIdentifier::new(self.struct_name(id));
+        let members = self.build_struct_members(id);
+        CStructDeclaration::new(name, members)
+    }
+
+    /// Input:
+    ///     enum Shape { Circle(f64), Empty }
+    /// Output:
+    ///     typedef enum pn_Shape__Tag {
+    ///         pn_Shape__Tag__Circle, pn_Shape__Tag__Empty
+    ///     } pn_Shape__Tag;
+    ///     typedef struct pn_Shape__Circle { double _0; } pn_Shape__Circle;
+    ///     typedef union pn_Shape__Data { pn_Shape__Circle pn_Circle; } pn_Shape__Data;
+    ///     typedef struct pn_Shape { pn_Shape__Tag tag; pn_Shape__Data data; } pn_Shape;
+    fn build_enum_declaration(&mut self, id: EnumId) -> Vec<CDeclaration> {
+        let e = self.package.enum_(id);
+        let name = self.enum_name(id);
+        let mut decls = Vec::new();
+
+        let tags = e
+            .variants
+            .iter()
+            .map(|v| CIdentifier::new(Self::enum_tag(&name, mangle::segment(&v.name))))
+            .collect();
+        let tag_ty = Self::enum_tag_ty(&name);
+        decls.push(CEnumDeclaration::new(CIdentifier::new(&tag_ty), tags).into());
+
+        let mut data_members = Vec::new();
+        for variant in e.variants.iter().filter(|v| !v.payload.is_empty()) {
+            let variant_name = mangle::segment(&variant.name);
+            let payload_ty = Self::enum_payload_ty(&name, &variant_name);
+            let members = variant
+                .payload
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| CTypedParam::new(Self::payload_field(i), self.build_ty(ty)))
+                .collect();
+            decls.push(CStructDeclaration::new(CIdentifier::new(&payload_ty), members).into());
+            data_members.push(CTypedParam::new(
+                CIdentifier::new(mangle::identifier(&variant.name)),
+                CType::Struct(payload_ty),
+            ));
+        }
+
+        let mut members = vec![CTypedParam::new(
+            CIdentifier::new(KI_ENUM_TAG),
+            CType::Enum(tag_ty),
+        )];
+        // C does not allow empty unions, so enums without any payload only store the tag.
+        if !data_members.is_empty() {
+            let data_ty = Self::enum_data_ty(&name);
+            decls.push(CUnionDeclaration::new(CIdentifier::new(&data_ty), data_members).into());
+            members.push(CTypedParam::new(
+                CIdentifier::new(KI_ENUM_DATA),
+                CType::Union(data_ty),
+            ));
+        }
+        decls.push(CStructDeclaration::new(CIdentifier::new(&name), members).into());
+        decls
+    }
+
+    /// pn_Shape *_pn_Shape__Circle__internal__new_gc(double _0)
+    /// {
+    ///    pn_Shape *_newobj_alloc = (pn_Shape *)gc_malloc(&gc, sizeof(pn_Shape));
+    ///    _newobj_alloc->tag = pn_Shape__Tag__Circle;
+    ///    _newobj_alloc->data.pn_Circle._0 = _0;
+    ///    return _newobj_alloc;
+    /// }
+    fn build_variant_new_gc(&mut self, id: EnumId, variant: usize) -> CFunctionDeclaration {
+        let name = self.enum_name(id);
+        let variant = &self.package.enum_(id).variants[variant];
+        let variant_name = mangle::segment(&variant.name);
+        let member = mangle::identifier(&variant.name);
+        let mut block = vec![];
+        let return_ty = Self::pointer_to_struct(&name);
+        let callee = CIdentifier::new(Self::gc_constructor_call(Self::enum_payload_ty(
+            &name,
+            &variant_name,
+        )));
+        let params = variant
+            .payload
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| CTypedParam::new(Self::payload_field(i), self.build_ty(ty)))
+            .collect::<Vec<_>>();
+        let alloc_obj_ident = CIdentifier::new("_newobj_alloc");
+        let new_gc_alloc_call = self.build_casted_gc_alloc_call(&CIdentifier::new(&name));
+
+        block.push(CVariableDeclaration::new(alloc_obj_ident.clone(), return_ty.clone()).into());
+        let assignment = CAssignment::new(
+            alloc_obj_ident.clone().into(),
+            CAssignmentOperator::Assign,
+            new_gc_alloc_call.into(),
+        );
+        block.push(CStmt::Expr(assignment.into()));
+
+        let tag = CMemberExpr::new(
+            alloc_obj_ident.clone().into(),
+            CMemberOperator::Indirect,
+            CIdentifier::new(KI_ENUM_TAG),
+        );
+        let tag_value = CIdentifier::new(Self::enum_tag(&name, &variant_name));
+        let assignment =
+            CAssignment::new(tag.into(), CAssignmentOperator::Assign, tag_value.into());
+        block.push(CStmt::Expr(assignment.into()));
+
+        for param in &params {
+            let field = Self::payload_member(alloc_obj_ident.clone().into(), &member, &param.name);
+            let assignment = CAssignment::new(
+                field.into(),
+                CAssignmentOperator::Assign,
+                param.name.clone().into(),
+            );
+            block.push(CStmt::Expr(assignment.into()));
+        }
+
+        block.push(CReturnStmt::new(Some(alloc_obj_ident.into())).into());
+
+        let body = CBlockStmt::new(block).into();
+        CFunctionDeclaration::new(callee, params, return_ty, Some(body))
+    }
+
+    /// Alpha *alpha_new_gc(int a, int b)
+    /// {
+    ///    Alpha *_newobj_alloc = (Alpha *)gc_malloc(&gc, sizeof(Alpha));
+    ///    _newobj_alloc->a = a;
+    ///    _newobj_alloc->b = b;
+    ///    return _newobj_alloc;
+    /// }
+    fn build_struct_new_gc(
+        &mut self,
+        name: &str,
+        params: Vec<CTypedParam>,
+    ) -> CFunctionDeclaration {
+        let mut block = vec![];
+        let return_ty = CType::Pointer(Box::new(CType::Struct(name.to_string())));
+        let callee = CIdentifier::new(Self::gc_constructor_call(name));
+        let alloc_obj_ident = CIdentifier::new("_newobj_alloc");
+        let new_gc_alloc_call = self.build_casted_gc_alloc_call(&CIdentifier::new(name));
+
+        let decl: CStmt =
+            CVariableDeclaration::new(alloc_obj_ident.clone(), return_ty.clone()).into();
+        block.push(decl);
+
+        let assignment: CExpr = CAssignment::new(
+            alloc_obj_ident.clone().into(),
+            CAssignmentOperator::Assign,
+            new_gc_alloc_call.into(),
+        )
+        .into();
+        let assignment = assignment.into();
+        block.push(assignment);
+
+        for param in &params {
+            let member = CMemberExpr::new(
+                alloc_obj_ident.clone().into(),
+                CMemberOperator::Indirect,
+                param.name.clone(),
+            );
+            let member = member.into();
+            let value = CExpr::Identifier(param.name.clone());
+            let assignment = CAssignment::new(member, CAssignmentOperator::Assign, value);
+            let assignment = CStmt::Expr(assignment.into());
+            block.push(assignment);
+        }
+
+        let return_: CStmt = CReturnStmt::new(Some(alloc_obj_ident.clone().into())).into();
+        block.push(return_);
+
+        let body = CBlockStmt::new(block).into();
+        CFunctionDeclaration::new(callee, params, return_ty, Some(body))
+    }
+
+    /// (Alpha *)gc_malloc(&gc, sizeof(Alpha))
+    fn build_casted_gc_alloc_call(&mut self, s: &CIdentifier) -> CCastExpr {
+        let new_gc_alloc_call = self.build_new_gc_alloc_call(&CIdentifier::new(&s.name));
+
+        CCastExpr::new(
+            CType::Pointer(Box::new(CType::Struct(s.name.to_string()))),
+            new_gc_alloc_call.into(),
+        )
+    }
+
+    ///  gc_malloc(&gc, sizeof(Alpha))
+    fn build_new_gc_alloc_call(&mut self, s: &CIdentifier) -> CCallExpr {
+        let gc_malloc = CIdentifier::new("gc_malloc").into();
+        let gc = CExpr::Type(CType::Reference(Box::new(CType::Struct(
+            KI_GC_NAME.to_string(),
+        ))));
+        let size_of = CExpr::SizeOf(CType::Struct(s.name.clone()));
+        CCallExpr::new(gc_malloc, vec![gc, size_of])
+    }
+
+    /// extern fun puts(s: str) -> i32;  =>  int32_t puts(const char* pn_s);
+    fn build_extern_prototype(&mut self, id: FunctionId) -> CFunctionDeclaration {
+        let function = self.package.function(id);
+        let name = CIdentifier::new(&function.name);
+        let ret_ty = self.build_extern_ty(&function.ret_ty);
+        let params = function
+            .params
+            .iter()
+            .map(|p| {
+                let local = function.local(*p);
+                CTypedParam::new(
+                    CIdentifier::new(mangle::identifier(&local.name)),
+                    self.build_extern_ty(&local.ty),
+                )
+            })
+            .collect();
+        let mut decl = CFunctionDeclaration::new(name, params, ret_ty, None);
+        decl.line = Some(self.source_line(&function.location));
+        decl
+    }
+
+    /// Instances and lambdas are static, since every unit that uses them generates them.
+    ///
+    /// The variables are declared up front, followed by the blocks of the body.
+    ///
+    /// Lambdas take their environment as the first parameter. The environment holds copies of
+    /// the captured variables.
+    ///
+    /// Input:
+    ///     fun(x: i64) -> i64 { return x + n; }
+    /// Output:
+    ///     struct _main__lambda_0__env { int64_t pn_n; };
+    ///     static int64_t _main__lambda_0(void* _env_ptr, int64_t pn_x) {
+    ///         _main__lambda_0__env* _env;
+    ///         _env = _env_ptr;
+    ///         int64_t _t1;
+    ///         _t1 = pn_x + _env->pn_n;
+    ///         return _t1;
+    ///     }
+    fn build_function(&mut self, id: FunctionId) -> CFunctionDeclaration {
+        let function = self.package.function(id);
+        let body = self
+            .mir
+            .body(id)
+            .expect("Only extern functions have no body");
+        self.function = Some(function);
+        self.body = Some(body);
+        let name = self.function_name(id);
+
+        let mut params = Vec::new();
+        let mut stmts = Vec::new();
+        if let FunctionKind::Lambda { captures } = &function.kind {
+            params.push(CTypedParam::new(
+                CIdentifier::new("_env_ptr"),
+                Self::void_pointer(),
+            ));
+            if !captures.is_empty() {
+                let env_name = format!("{}__env", name);
+                let members = captures
+                    .iter()
+                    .map(|c| {
+                        let name = CIdentifier::new(mangle::identifier(&c.name));
+                        CTypedParam::new(name, self.build_ty(&c.ty))
+                    })
+                    .collect::<Vec<_>>();
+                let env = CStructDeclaration::new(CIdentifier::new(&env_name), members.clone());
+                let mut new_gc = self.build_struct_new_gc(&env_name, members);
+                new_gc.is_static = true;
+                self.local_decls.push(env.into());
+                self.local_decls.push(new_gc.into());
+
+                let env = CIdentifier::new(KI_CLOSURE_ENV);
+                let env_ty = Self::pointer_to_struct(&env_name);
+                stmts.push(CVariableDeclaration::new(env.clone(), env_ty).into());
+                let env_ptr = CIdentifier::new("_env_ptr").into();
+                let assignment = CAssignment::new(env.into(), CAssignmentOperator::Assign, env_ptr);
+                stmts.push(CStmt::Expr(assignment.into()));
+            }
+        }
+        for param in &function.params {
+            let ty = self.build_ty(&body.local(*param).ty);
+            params.push(CTypedParam::new(self.local(*param), ty));
+        }
+        let locals = (0..body.locals.len())
+            .map(LocalId)
+            .filter(|id| !function.params.contains(id));
+        for local in locals {
+            let ty = self.build_local_ty(body.local(local));
+            stmts.push(CVariableDeclaration::new(self.local(local), ty).into());
+        }
+        let ret_ty = self.build_ty(&function.ret_ty);
+        stmts.extend(self.build_blocks(body));
+
+        let body = Some(CBlockStmt::new(stmts).into());
+        let mut decl = CFunctionDeclaration::new(CIdentifier::new(name), params, ret_ty, body);
+        decl.is_static = function.is_local();
+        decl.line = Some(self.source_line(&function.location));
+        decl
+    }
+
+    /// Objects that do not escape are stored in the local itself.
+    ///
+    /// let p: Point;  =>  pn_Point* pn_p;
+    /// let p: Point;  =>  pn_Point pn_p;  (on the stack)
+    fn build_local_ty(&mut self, local: &LocalDecl) -> CType {
+        let ty = self.build_ty(&local.ty);
+        match (local.on_stack, ty) {
+            (true, CType::Pointer(object)) => *object,
+            (_, ty) => ty,
+        }
+    }
+
+    /// The C name of a variable of the function that is lowered. The temporaries of the MIR
+    /// keep their name, which no escaped identifier can have.
+    fn local(&self, id: LocalId) -> CIdentifier {
+        let function = self.function.expect("Variables are only used in functions");
+        let name = &self.body().local(id).name;
+        match id.0 < function.locals.len() {
+            true => CIdentifier::new(mangle::identifier(name)),
+            false => CIdentifier::new(name),
+        }
+    }
+
+    fn body(&self) -> &'a Body {
+        self.body.expect("Variables are only used in functions")
+    }
+
+    fn place_ty(&self, place: &Place) -> Ty {
+        let function = self.function.expect("Variables are only used in functions");
+        self.body().place_ty(self.package, function, place)
+    }
+
+    fn operand_ty(&self, operand: &Operand) -> Ty {
+        let function = self.function.expect("Variables are only used in functions");
+        self.body().operand_ty(self.package, function, operand)
+    }
+
+    /// bb3
+    fn label(id: BlockId) -> CIdentifier {
+        CIdentifier::new(format!("bb{}", id.0))
+    }
+
+    /// The blocks follow each other in order, so a jump to the next block is left out. Only
+    /// blocks that are jumped to get a label.
+    ///
+    /// Input:
+    ///     bb0: { _t1 = x < 10; branch _t1, bb1, bb2; }
+    ///     bb1: { x = x + 1; goto bb0; }
+    ///     bb2: { return; }
+    /// Output:
+    ///     bb0:;
+    ///     _t1 = x < 10;
+    ///     if (!_t1) goto bb2;
+    ///     x = x + 1;
+    ///     goto bb0;
+    ///     bb2:;
+    ///     return;
+    fn build_blocks(&mut self, body: &Body) -> Vec<CStmt> {
+        let mut targets = HashSet::new();
+        let mut blocks = Vec::new();
+        for id in body.block_ids() {
+            let block = body.block(id);
+            let mut stmts = block
+                .stmts
+                .iter()
+                .flat_map(|stmt| [self.line_directive(stmt), self.build_statement(stmt)])
+                .collect::<Vec<_>>();
+            let next = BlockId(id.0 + 1);
+            let mut goto = |target: BlockId| {
+                targets.insert(target);
+                CStmt::Goto(Self::label(target))
+            };
+            let start = stmts.len();
+            match &block.terminator.value {
+                Terminator::Goto(target) if *target == next => {}
+                Terminator::Goto(target) => stmts.push(goto(*target)),
+                Terminator::Branch(cond, then, else_) => {
+                    let cond = self.build_operand(cond);
+                    if *then == next {
+                        let cond = CUnaryExpr::new(CUnaryOperator::Negate, cond).into();
+                        stmts.push(CIfStmt::new(cond, goto(*else_), None).into());
+                    } else if *else_ == next {
+                        stmts.push(CIfStmt::new(cond, goto(*then), None).into());
+                    } else {
+                        let then = goto(*then);
+                        stmts.push(CIfStmt::new(cond, then, Some(goto(*else_))).into());
+                    }
+                }
+                Terminator::Switch {
+                    scrutinee,
+                    enum_,
+                    targets,
+                    otherwise,
+                } => {
+                    let e = self.package.enum_(*enum_);
+                    let name = self.enum_name(*enum_);
+                    for (variant, target) in targets {
+                        let tag = CMemberExpr::new(
+                            self.local(*scrutinee).into(),
+                            CMemberOperator::Indirect,
+                            CIdentifier::new(KI_ENUM_TAG),
+                        );
+                        let variant = mangle::segment(&e.variants[*variant].name);
+                        let tag_value = CIdentifier::new(Self::enum_tag(&name, variant));
+                        let cond =
+                            CBinaryExpr::new(CBinaryOperator::Equals, tag.into(), tag_value.into());
+                        stmts.push(CIfStmt::new(cond.into(), goto(*target), None).into());
+                    }
+                    if *otherwise != next {
+                        stmts.push(goto(*otherwise));
+                    }
+                }
+                Terminator::Return(value) => {
+                    let value = value.as_ref().map(|v| self.build_operand(v));
+                    stmts.push(CReturnStmt::new(value).into());
+                }
+                Terminator::Unreachable => {}
+            }
+            // a jump to the next block is left out and needs no line either
+            if stmts.len() > start {
+                stmts.insert(start, self.line_directive(&block.terminator));
+            }
+            blocks.push((id, stmts));
+        }
+        let mut stmts = Vec::new();
+        for (id, block) in blocks {
+            if targets.contains(&id) {
+                stmts.push(CStmt::Label(Self::label(id)));
+            }
+            stmts.extend(block);
+        }
+        stmts
+    }
+
+    fn build_statement(&mut self, stmt: &StatementS) -> CStmt {
+        match &stmt.value {
+            Statement::Assign(place, rvalue) => {
+                let ty = self.place_ty(place);
+                let target = self.build_place(place);
+                let value = match rvalue {
+                    Rvalue::Struct(id, values)
+                        if place.projections.is_empty() && self.is_on_stack(place) =>
+                    {
+                        self.build_stack_init(*id, values)
+                    }
+                    _ => self.build_rvalue(stmt, rvalue, &ty),
+                };
+                CStmt::Expr(CAssignment::new(target, CAssignmentOperator::Assign, value).into())
+            }
+            Statement::Eval(rvalue) => CStmt::Expr(self.build_rvalue(stmt, rvalue, &Ty::Unit)),
+        }
+    }
+
+    /// Builds the value `stmt` computes, which has the type `ty`.
+    fn build_rvalue(&mut self, stmt: &StatementS, rvalue: &Rvalue, ty: &Ty) -> CExpr {
+        match rvalue {
+            Rvalue::Use(op) => self.build_operand(op),
+            Rvalue::FunctionRef(id) => self.build_function_ref(*id, ty),
+            Rvalue::BuiltinRef(builtin) => {
+                let target = builtin.name.to_string();
+                let call =
+                    |args| CCallExpr::new(CIdentifier::new(builtin.name).into(), args).into();
+                self.build_ref_closure(ty, target, call)
+            }
+            Rvalue::Call(id, args) => self.build_call(*id, args),
+            Rvalue::CallBuiltin(builtin, args) => {
+                let args = args.iter().map(|arg| self.build_operand(arg)).collect();
+                CCallExpr::new(CIdentifier::new(builtin.name).into(), args).into()
+            }
+            Rvalue::CallClosure(callee, args) => self.build_closure_call(callee, args),
+            Rvalue::Intrinsic(intrinsic, args) => self.build_intrinsic(stmt, *intrinsic, args, ty),
+            Rvalue::Prefix(op, rt) => self.build_prefix(*op, rt).into(),
+            Rvalue::Infix(lt, op, rt) => self.build_infix(lt, *op, rt),
+            Rvalue::Struct(id, values) => self.build_structure_init(*id, values).into(),
+            Rvalue::Variant(id, variant, payload) => {
+                self.build_variant_init(*id, *variant, payload).into()
+            }
+            Rvalue::Array(elements) => self.build_array(ty, elements),
+            Rvalue::Unwrap(value) => self.build_unwrap(stmt, value, ty),
+            Rvalue::Closure(id, values) => self.build_closure(*id, values),
+        }
+    }
+
+    fn build_operand(&mut self, operand: &Operand) -> CExpr {
+        match operand {
+            Operand::Copy(place) => self.build_place(place),
+            Operand::Constant(Literal::Str(s), _) => self.build_string_literal(s).into(),
+            Operand::Constant(Literal::Nil, _) => CIdentifier::new("NULL").into(),
+            Operand::Constant(literal, _) => Self::build_literal(literal).into(),
+        }
+    }
+
+    /// Whether the place starts at a local that stores its object on the stack.
+    fn is_on_stack(&self, place: &Place) -> bool {
+        match place.base {
+            PlaceBase::Local(id) => self.body().local(id).on_stack,
+            PlaceBase::Captured(_) => false,
+        }
+    }
+
+    /// p.x          =>  pn_p->pn_x
+    /// p.x          =>  pn_p.pn_x  (on the stack)
+    /// a[i]         =>  pn_a[pine_check_index(pn_i, 3, "main.pine:1:1")]
+    /// l[i]         =>  *(int64_t*)pine_list_at(pn_l, pn_i, "main.pine:1:1")
+    /// _match_0.Circle.0  =>  pn__match_0_->data.pn_Circle._0
+    fn build_place(&mut self, place: &Place) -> CExpr {
+        let mut expr = match place.base {
+            PlaceBase::Local(id) => self.local(id).into(),
+            PlaceBase::Captured(index) => self.build_captured(index),
+        };
+        let mut ty = self.place_ty(&Place {
+            base: place.base,
+            projections: Vec::new(),
+        });
+        let mut on_stack = self.is_on_stack(place);
+        for projection in &place.projections {
+            match (projection, &ty) {
+                (Projection::Field(field), Ty::Struct(id)) => {
+                    // the fields of a generic instance are only known once it is defined in
+                    // this unit
+                    self.build_ty(&ty);
+                    let field = &self.package.struct_(*id).fields[*field];
+                    let name = CIdentifier::new(mangle::identifier(&field.name));
+                    let operator = match std::mem::take(&mut on_stack) {
+                        true => CMemberOperator::Direct,
+                        false => CMemberOperator::Indirect,
+                    };
+                    expr = CMemberExpr::new(expr, operator, name).into();
+                    ty = field.ty.clone();
+                }
+                (Projection::Index(index, at), Ty::Array(elem, len)) => {
+                    let i = self.build_operand(index);
+                    let location = self.source_location(at).into();
+                    let len = CConstant::Integer(*len as i64).into();
+                    let checked = Self::runtime_call("pine_check_index", vec![i, len, location]);
+                    expr = CBinaryExpr::new(CBinaryOperator::Index, expr, checked).into();
+                    ty = *elem.clone();
+                }
+                (Projection::Index(index, at), Ty::List(elem)) => {
+                    let i = self.build_operand(index);
+                    let location = self.source_location(at).into();
+                    let call = Self::runtime_call("pine_list_at", vec![expr, i, location]);
+                    expr = self.deref_as(elem, call);
+                    ty = *elem.clone();
+                }
+                (Projection::Payload(variant, index), Ty::Enum(id)) => {
+                    let variant = &self.package.enum_(*id).variants[*variant];
+                    let field = Self::payload_field(*index);
+                    let member = mangle::identifier(&variant.name);
+                    expr = Self::payload_member(expr, member, &field).into();
+                    ty = variant.payload[*index].clone();
+                }
+                _ => unreachable!("Projections match the type they are applied to"),
+            }
+        }
+        expr
+    }
+
+    /// _env->x
+    fn build_captured(&self, index: usize) -> CExpr {
+        let function = self.function.expect("Variables are only used in functions");
+        let FunctionKind::Lambda { captures } = &function.kind else {
+            unreachable!("Only lambdas capture variables")
+        };
+        let env = CIdentifier::new(KI_CLOSURE_ENV).into();
+        let name = CIdentifier::new(mangle::identifier(&captures[index].name));
+        CMemberExpr::new(env, CMemberOperator::Indirect, name).into()
+    }
+
+    /// Creates the closure of a lifted lambda with a copy of the captured variables.
+    ///
+    /// pine_closure_new((void (*)(void))_main__lambda_0, __main__lambda_0__env__internal__new_gc(n))
+    fn build_closure(&mut self, id: FunctionId, values: &[Operand]) -> CExpr {
+        self.use_function(id);
+        let name = self.function_name(id);
+        if values.is_empty() {
+            let closure = format!("{}__closure", name);
+            return self.build_static_closure(closure, &name);
+        }
+        let values = values.iter().map(|v| self.build_operand(v)).collect();
+        let env = Self::build_struct_new_call(&format!("{}__env", name), values).into();
+        Self::runtime_call("pine_closure_new", vec![Self::erased_function(&name), env])
+    }
+
+    /// Functions used as values are wrapped in a static function that ignores the environment,
+    /// and passed around as a global closure.
+    ///
+    /// Input:
+    ///     square
+    /// Output:
+    ///     static int64_t _main__pn_square__ref(void* _env_ptr, int64_t _0) { return pn_square(_0); }
+    ///     PineClosure _main__pn_square__ref__closure = {(void (*)(void))_main__pn_square__ref, NULL};
+    ///     &_main__pn_square__ref__closure
+    fn build_function_ref(&mut self, id: FunctionId, ty: &Ty) -> CExpr {
+        self.use_function(id);
+        let target = self.function_name(id);
+        let is_extern = matches!(self.package.function(id).kind, FunctionKind::Extern { .. });
+        let Ty::Function(params, ret_ty) = ty else {
+            unreachable!("Functions used as values have a function type")
+        };
+        let call = |args: Vec<CExpr>| match is_extern {
+            true => Self::build_extern_call(&target, args.into_iter().zip(params.clone()), ret_ty),
+            false => CCallExpr::new(CIdentifier::new(&target).into(), args).into(),
+        };
+        let name = target.clone();
+        self.build_ref_closure(ty, name, call)
+    }
+
+    /// The global closure of the wrapper of the function `target`, calling it with `call`.
+    fn build_ref_closure(
+        &mut self,
+        ty: &Ty,
+        target: String,
+        call: impl FnOnce(Vec<CExpr>) -> CExpr,
+    ) -> CExpr {
+        let wrapper = format!("_{}__{}__ref", self.unit_name, target);
+        let closure = format!("{}__closure", wrapper);
+        if !self.instances.insert(wrapper.clone()) {
+            let closure = CIdentifier::new(closure).into();
+            return CUnaryExpr::new(CUnaryOperator::Address, closure).into();
+        }
+
+        let Ty::Function(params, ret_ty) = ty else {
+            unreachable!("Functions used as values have a function type")
+        };
+        let args = (0..params.len()).map(|i| Self::payload_field(i).into());
+        let call = call(args.collect());
+        let env_param = CTypedParam::new(CIdentifier::new("_env_ptr"), Self::void_pointer());
+        let params = std::iter::once(env_param)
+            .chain(
+                params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| CTypedParam::new(Self::payload_field(i), self.build_ty(ty))),
+            )
+            .collect();
+        let body = Some(Self::return_call(call, ret_ty));
+        let ret_ty = self.build_ty(ret_ty);
+        let mut function =
+            CFunctionDeclaration::new(CIdentifier::new(&wrapper), params, ret_ty, body);
+        function.is_static = true;
+        self.local_decls.push(function.into());
+        self.build_static_closure(closure, &wrapper)
+    }
+
+    /// Closures without an environment are globals of the unit.
+    ///
+    /// PineClosure _main__lambda_0__closure = {(void (*)(void))_main__lambda_0, NULL};
+    /// &_main__lambda_0__closure
+    fn build_static_closure(&mut self, name: String, function: &str) -> CExpr {
+        let name = CIdentifier::new(name);
+        let address = CUnaryExpr::new(CUnaryOperator::Address, name.clone().into()).into();
+        if !self.instances.insert(name.name.clone()) {
+            return address;
+        }
+        let value = CExpr::InitializerList(vec![
+            Self::erased_function(function),
+            CIdentifier::new("NULL").into(),
+        ]);
+        let global = CGlobalVariableDeclaration::new(
+            name,
+            CType::Struct(KI_CLOSURE_CLASS.to_string()),
+            Some(value),
+        );
+        self.static_closures.push(global.into());
+        address
+    }
+
+    /// Closures store their function as `void (*)(void)` since C has no generic function pointer.
+    fn erased_function(function: &str) -> CExpr {
+        let ty = CType::FunctionPointer(Box::new(CType::Void), vec![]);
+        CCastExpr::new(ty, CIdentifier::new(function).into()).into()
+    }
+
+    fn void_pointer() -> CType {
+        CType::Pointer(Box::new(CType::Void))
+    }
+
+    /// The body of a function that returns the result of `call`, unless it returns nothing.
+    fn return_call(call: CExpr, ret_ty: &Ty) -> CStmt {
+        let stmt = match ret_ty {
+            Ty::Unit => CStmt::Expr(call),
+            _ => CReturnStmt::new(Some(call)).into(),
+        };
+        CBlockStmt::new(vec![stmt]).into()
+    }
+
+    fn build_literal(literal: &Literal) -> CConstant {
+        match literal {
+            Literal::Bool(b) => CConstant::Integer(*b as i64),
+            Literal::Int(i) => CConstant::Integer(*i),
+            Literal::Float(f) => CConstant::Float(*f),
+            Literal::Str(_) => unreachable!("Handled by build_string_literal"),
+            Literal::Nil => unreachable!("Lowered to NULL by build_expr"),
+        }
+    }
+
+    /// Input:
+    ///     "hello"
+    /// Output:
+    ///     PineStr _main__str_0 = {5, "hello"};
+    ///     &_main__str_0
+    fn build_string_literal(&mut self, s: &str) -> CUnaryExpr {
+        let name = CIdentifier::new(format!(
+            "_{}__str_{}",
+            self.unit_name,
+            self.string_literals.len()
+        ));
+        let value = CExpr::InitializerList(vec![
+            CConstant::Integer(s.len() as i64).into(),
+            CConstant::String(s.to_string()).into(),
+        ]);
+        let global = CGlobalVariableDeclaration::new(
+            name.clone(),
+            CType::Struct(KI_STR_CLASS.to_string()),
+            Some(value),
+        );
+        self.string_literals.push(global.into());
+        CUnaryExpr::new(CUnaryOperator::Address, name.into())
+    }
+
+    /// Calls a function, method or instance. Extern functions get C strings.
+    ///
+    /// puts(s)       =>  puts(pn_s->data)
+    /// getenv(s)     =>  pine_str_from_cstr(getenv(pn_s->data))
+    /// getenv(s)     =>  pine_str_from_nullable_cstr(getenv(pn_s->data))  (returning `str?`)
+    /// first(p)      =>  pn_first__i64__str(pn_p)  (generic)
+    /// p.len(x)      =>  pn_Point__method__len(pn_p, pn_x)
+    fn build_call(&mut self, id: FunctionId, args: &[Operand]) -> CExpr {
+        self.use_function(id);
+        let name = self.function_name(id);
+        let function = self.package.function(id);
+        let c_args = args
+            .iter()
+            .map(|arg| self.build_operand(arg))
+            .collect::<Vec<_>>();
+        if !matches!(function.kind, FunctionKind::Extern { .. }) {
+            return CCallExpr::new(CIdentifier::new(name).into(), c_args).into();
+        }
+        let param_tys = function.param_tys();
+        Self::build_extern_call(&name, c_args.into_iter().zip(param_tys), &function.ret_ty)
+    }
+
+    /// Converts the strings passed to and returned from an extern function.
+    fn build_extern_call(
+        name: &str,
+        args: impl Iterator<Item = (CExpr, Ty)>,
+        ret_ty: &Ty,
+    ) -> CExpr {
+        let args = args
+            .map(|(c_arg, ty)| match ty {
+                Ty::Str => {
+                    CMemberExpr::new(c_arg, CMemberOperator::Indirect, CIdentifier::new("data"))
+                        .into()
+                }
+                Ty::Optional(inner) if *inner == Ty::Str => {
+                    Self::runtime_call("pine_str_to_nullable_cstr", vec![c_arg])
+                }
+                _ => c_arg,
+            })
+            .collect();
+        let call = CCallExpr::new(CIdentifier::new(name).into(), args).into();
+        match ret_ty {
+            Ty::Str => Self::runtime_call("pine_str_from_cstr", vec![call]),
+            Ty::Optional(inner) if **inner == Ty::Str => {
+                Self::runtime_call("pine_str_from_nullable_cstr", vec![call])
+            }
+            _ => call,
+        }
+    }
+
+    /// Calls a closure through a static function per function type that passes the environment
+    /// along, so the callee is only evaluated once.
+    ///
+    /// Input:
+    ///     f(1)
+    /// Output:
+    ///     static int64_t _call__fun1_i64_i64(PineClosure* closure, int64_t _0) {
+    ///         return ((int64_t (*)(void*, int64_t))closure->fn)(closure->env, _0);
+    ///     }
+    ///     _call__fun1_i64_i64(f, 1)
+    fn build_closure_call(&mut self, callee: &Operand, args: &[Operand]) -> CExpr {
+        let ty = &self.operand_ty(callee);
+        let name = format!("_call__{}", self.mangle_type(ty));
+        if self.instances.insert(name.clone()) {
+            let Ty::Function(params, ret_ty) = ty else {
+                unreachable!("Only functions are called")
+            };
+            let param_tys = params.iter().map(|p| self.build_ty(p)).collect::<Vec<_>>();
+            let ret = self.build_ty(ret_ty);
+            let closure = CIdentifier::new("closure");
+            let fn_ty = std::iter::once(Self::void_pointer()).chain(param_tys.iter().cloned());
+            let fn_ty = CType::FunctionPointer(Box::new(ret.clone()), fn_ty.collect());
+            let function = CMemberExpr::new(
+                closure.clone().into(),
+                CMemberOperator::Indirect,
+                CIdentifier::new("fn"),
+            );
+            let function = CCastExpr::new(fn_ty, function.into());
+            let env = CMemberExpr::new(
+                closure.clone().into(),
+                CMemberOperator::Indirect,
+                CIdentifier::new("env"),
+            );
+            let call_args = std::iter::once(env.into())
+                .chain((0..params.len()).map(|i| Self::payload_field(i).into()))
+                .collect();
+            let call = CCallExpr::new(function.into(), call_args).into();
+
+            let closure_param =
+                CTypedParam::new(closure, Self::pointer_to_struct(KI_CLOSURE_CLASS));
+            let c_params = std::iter::once(closure_param)
+                .chain(
+                    (param_tys.into_iter().enumerate())
+                        .map(|(i, ty)| CTypedParam::new(Self::payload_field(i), ty)),
+                )
+                .collect();
+            let body = Some(Self::return_call(call, ret_ty));
+            let mut function =
+                CFunctionDeclaration::new(CIdentifier::new(&name), c_params, ret, body);
+            function.is_static = true;
+            self.local_decls.push(function.into());
+        }
+        let callee = self.build_operand(callee);
+        let args = std::iter::once(callee)
+            .chain(args.iter().map(|arg| self.build_operand(arg)))
+            .collect();
+        CCallExpr::new(CIdentifier::new(name).into(), args).into()
+    }
+
+    /// len(a)        =>  3
+    /// len(l)        =>  l->len
+    /// push(l, x)    =>  pine_list_push(l, (int64_t[]){x})
+    /// pop(l)        =>  *(int64_t*)pine_list_pop(l, "main.pine:1:1")
+    fn build_intrinsic(
+        &mut self,
+        stmt: &StatementS,
+        intrinsic: Intrinsic,
+        args: &[Operand],
+        ty: &Ty,
+    ) -> CExpr {
+        let collection_ty = self.operand_ty(&args[0]);
+        let collection = self.build_operand(&args[0]);
+        match (intrinsic, collection_ty) {
+            (Intrinsic::Len, Ty::Array(_, len)) => CConstant::Integer(len as i64).into(),
+            (Intrinsic::Len, _) => CMemberExpr::new(
+                collection,
+                CMemberOperator::Indirect,
+                CIdentifier::new("len"),
+            )
+            .into(),
+            (Intrinsic::Push, _) => {
+                let elem_ty = self.operand_ty(&args[1]);
+                let elem = self.build_operand(&args[1]);
+                let elem = self.build_compound_array(&elem_ty, vec![elem]);
+                Self::runtime_call("pine_list_push", vec![collection, elem])
+            }
+            (Intrinsic::Pop, _) => {
+                let location = self.source_location(stmt).into();
+                let call = Self::runtime_call("pine_list_pop", vec![collection, location]);
+                self.deref_as(ty, call)
+            }
+        }
+    }
+
+    /// Input:
+    ///     [1, 2]
+    /// Output (array):
+    ///     (int64_t*)pine_array_new(sizeof(int64_t), 2, (int64_t[]){1, 2})
+    /// Output (list):
+    ///     pine_list_new(sizeof(int64_t), 2, (int64_t[]){1, 2})
+    fn build_array(&mut self, ty: &Ty, elements: &[Operand]) -> CExpr {
+        let (Ty::Array(elem_ty, _) | Ty::List(elem_ty)) = ty else {
+            unreachable!("Array literals are arrays or lists")
+        };
+        let elements = elements
+            .iter()
+            .map(|e| self.build_operand(e))
+            .collect::<Vec<_>>();
+        let len = CConstant::Integer(elements.len() as i64).into();
+        // C does not allow empty initializer lists
+        let init = if elements.is_empty() {
+            CIdentifier::new("NULL").into()
+        } else {
+            self.build_compound_array(elem_ty, elements)
+        };
+        let size = CExpr::SizeOf(self.build_ty(elem_ty));
+        match ty {
+            Ty::List(_) => Self::runtime_call("pine_list_new", vec![size, len, init]),
+            _ => {
+                let call = Self::runtime_call("pine_array_new", vec![size, len, init]);
+                CCastExpr::new(self.build_ty(ty), call).into()
+            }
+        }
+    }
+
+    /// p!  =>  (Point*)pine_unwrap(p, "main.pine:1:1")
+    fn build_unwrap(&mut self, stmt: &StatementS, value: &Operand, ty: &Ty) -> CExpr {
+        let value = self.build_operand(value);
+        let location = self.source_location(stmt).into();
+        let call = Self::runtime_call("pine_unwrap", vec![value, location]);
+        CCastExpr::new(self.build_ty(ty), call).into()
+    }
+
+    /// (int64_t[]){1, 2}
+    fn build_compound_array(&mut self, elem_ty: &Ty, elements: Vec<CExpr>) -> CExpr {
+        let ty = CType::Array(Box::new(self.build_ty(elem_ty)));
+        CCastExpr::new(ty, CExpr::InitializerList(elements)).into()
+    }
+
+    /// *(int64_t*)ptr
+    fn deref_as(&mut self, ty: &Ty, ptr: CExpr) -> CExpr {
+        let ptr_ty = CType::Pointer(Box::new(self.build_ty(ty)));
+        let cast = CCastExpr::new(ptr_ty, ptr).into();
+        CUnaryExpr::new(CUnaryOperator::Indirection, cast).into()
+    }
+
+    fn runtime_call(name: &str, args: Vec<CExpr>) -> CExpr {
+        CCallExpr::new(CIdentifier::new(name).into(), args).into()
+    }
+
+    /// #line 3 "main.pine", before every statement. Formatting the C can move statements to
+    /// other lines, so each of them gets its own.
+    fn line_directive<T>(&self, at: &Located<T>) -> CStmt {
+        CStmt::Line(self.source_line(at))
+    }
+
+    fn source_line<T>(&self, at: &Located<T>) -> CLineDirective {
+        let (line, _) = self
+            .files
+            .line_column(at.source, at.span.start)
+            .expect("Source files are loaded before they are lowered");
+        let file = at.source.to_path().display().to_string();
+        CLineDirective::new(line, file)
+    }
+
+    /// "main.pine:3:14", used by runtime checks to report where they failed.
+    fn source_location<T>(&self, at: &Located<T>) -> CConstant {
+        let (line, column) = self
+            .files
+            .line_column(at.source, at.span.start)
+            .expect("Source files are loaded before they are lowered");
+        let path = at.source.to_path();
+        CConstant::String(format!("{}:{}:{}", path.display(), line, column))
+    }
+
+    /// Point { y: 2, x: 1 }  =>  _pn_Point__internal__new_gc(1, 2)
+    ///
+    /// Pair { b: "x", a: 1 }  =>  _pn_Pair__i64__str__internal__new_gc(1, "x")  (generic)
+    fn build_structure_init(&mut self, id: StructId, values: &[Operand]) -> CCallExpr {
+        if self.package.struct_(id).is_instance() {
+            self.struct_instance(id);
+        }
+        let args = values.iter().map(|v| self.build_operand(v)).collect();
+        Self::build_struct_new_call(&self.struct_name(id), args)
+    }
+
+    /// Point { y: 2, x: 1 }  =>  (pn_Point){1, 2}  (on the stack)
+    fn build_stack_init(&mut self, id: StructId, values: &[Operand]) -> CExpr {
+        let ty = CType::Struct(self.struct_name(id));
+        let values = values.iter().map(|v| self.build_operand(v)).collect();
+        CCastExpr::new(ty, CExpr::InitializerList(values)).into()
+    }
+
+    /// _pn_Shape__Circle__internal__new_gc(args)
+    fn build_variant_init(&mut self, id: EnumId, variant: usize, payload: &[Operand]) -> CCallExpr {
+        let variant = mangle::segment(&self.package.enum_(id).variants[variant].name);
+        let payload_ty = Self::enum_payload_ty(self.enum_name(id), variant);
+        let args = payload.iter().map(|v| self.build_operand(v)).collect();
+        Self::build_struct_new_call(&payload_ty, args)
+    }
+
+    fn build_prefix(&mut self, op: OpPrefix, rt: &Operand) -> CUnaryExpr {
+        let operator = match op {
+            OpPrefix::Negate => CUnaryOperator::Minus,
+            OpPrefix::Not => CUnaryOperator::Negate,
+        };
+        let expr = self.build_operand(rt);
+        CUnaryExpr::new(operator, expr)
+    }
+
+    fn build_infix(&mut self, lt: &Operand, op: OpInfix, rt: &Operand) -> CExpr {
+        let left = self.build_operand(lt);
+        let right = self.build_operand(rt);
+        if self.operand_ty(lt) == Ty::Str {
+            return Self::build_string_infix(op, left, right);
+        }
+        let operator = Self::build_binary_op(op);
+        CBinaryExpr::new(operator, left, right).into()
+    }
+
+    /// Strings are compared by content, so every operator turns into a runtime call.
+    /// a + b   =>  pine_str_concat(a, b)
+    /// a == b  =>  pine_str_equals(a, b)
+    /// a != b  =>  !pine_str_equals(a, b)
+    /// a < b   =>  pine_str_compare(a, b) < 0
+    fn build_string_infix(op: OpInfix, left: CExpr, right: CExpr) -> CExpr {
+        let call = |name: &str| -> CExpr {
+            CCallExpr::new(CIdentifier::new(name).into(), vec![left, right]).into()
+        };
+        match op {
+            OpInfix::Add => call("pine_str_concat"),
+            OpInfix::Equal => call("pine_str_equals"),
+            OpInfix::NotEqual => {
+                CUnaryExpr::new(CUnaryOperator::Negate, call("pine_str_equals")).into()
+            }
+            OpInfix::Less | OpInfix::LessEqual | OpInfix::Greater | OpInfix::GreaterEqual => {
+                let operator = Self::build_binary_op(op);
+                let zero = CConstant::Integer(0).into();
+                CBinaryExpr::new(operator, call("pine_str_compare"), zero).into()
+            }
+            _ => unreachable!("The type checker only allows concatenation and comparison"),
+        }
+    }
+
+    fn build_binary_op(op: OpInfix) -> CBinaryOperator {
+        match op {
+            OpInfix::Add => CBinaryOperator::Plus,
+            OpInfix::Subtract => CBinaryOperator::Minus,
+            OpInfix::Multiply => CBinaryOperator::Multiply,
+            OpInfix::Divide => CBinaryOperator::Divide,
+            OpInfix::Modulo => CBinaryOperator::Modulo,
+            OpInfix::Less => CBinaryOperator::Less,
+            OpInfix::LessEqual => CBinaryOperator::LessOrEqual,
+            OpInfix::Greater => CBinaryOperator::Greater,
+            OpInfix::GreaterEqual => CBinaryOperator::GreaterOrEqual,
+            OpInfix::Equal => CBinaryOperator::Equals,
+            OpInfix::NotEqual => CBinaryOperator::NotEquals,
+            OpInfix::LogicAnd => CBinaryOperator::LogicalAnd,
+            OpInfix::LogicOr => CBinaryOperator::LogicalOr,
+        }
+    }
+
+    fn build_ty(&mut self, ty: &Ty) -> CType {
+        match ty {
+            Ty::Unit => CType::Void,
+            Ty::Bool => CType::U8,
+            Ty::U8 => CType::U8,
+            Ty::U32 => CType::U32,
+            Ty::U64 => CType::U64,
+            Ty::I8 => CType::I8,
+            Ty::I32 => CType::I32,
+            Ty::I64 => CType::I64,
+            Ty::F32 => CType::F32,
+            Ty::F64 => CType::F64,
+            Ty::Str => CType::Pointer(Box::new(CType::Struct(KI_STR_CLASS.to_string()))),
+            // Arrays are gc allocated and passed around as a pointer to their first element.
+            Ty::Array(elem, _) => CType::Pointer(Box::new(self.build_ty(elem))),
+            Ty::List(_) => CType::Pointer(Box::new(CType::Struct(KI_LIST_CLASS.to_string()))),
+            // We always pass structs by pointer
+            Ty::Struct(id) => {
+                if self.package.struct_(*id).is_instance() {
+                    self.struct_instance(*id);
+                }
+                Self::pointer_to_struct(self.struct_name(*id))
+            }
+            Ty::Enum(id) => Self::pointer_to_struct(self.enum_name(*id)),
+            // Functions and lambdas are passed around as closures.
+            Ty::Function(..) => Self::pointer_to_struct(KI_CLOSURE_CLASS),
+            // Only pointers can be optional, `nil` is NULL.
+            Ty::Optional(inner) => self.build_ty(inner),
+        }
+    }
+
+    /// Strings cross into C as null terminated `const char*`.
+    fn build_extern_ty(&mut self, ty: &Ty) -> CType {
+        match ty {
+            Ty::Str => CType::Pointer(Box::new(CType::Const(Box::new(CType::Char)))),
+            Ty::Optional(inner) => self.build_extern_ty(inner),
+            _ => self.build_ty(ty),
+        }
+    }
+
+    /// alpha_new_gc(a,b)
+    fn build_struct_new_call(s: &str, args: Vec<CExpr>) -> CCallExpr {
+        let callee = CIdentifier::new(Self::gc_constructor_call(s)).into();
+        CCallExpr::new(callee, args)
+    }
+
+    fn gc_constructor_call<S: AsRef<str>>(s: S) -> String {
+        format!(
+            "{}{}{}",
+            KI_GC_NEW_CALL_PREFIX,
+            s.as_ref(),
+            KI_GC_NEW_CALL_SUFFIX
+        )
+    }
+
+    /// pn_Point__method__len
+    fn method_name<S: AsRef<str>, T: AsRef<str>>(ty: S, method: T) -> String {
+        format!("{}__method__{}", ty.as_ref(), method.as_ref())
+    }
+
+    /// pn_Shape__Tag
+    fn enum_tag_ty<S: AsRef<str>>(enum_name: S) -> String {
+        format!("{}__Tag", enum_name.as_ref())
+    }
+
+    /// pn_Shape__Tag__Circle
+    fn enum_tag<S: AsRef<str>, T: AsRef<str>>(enum_name: S, variant: T) -> String {
+        format!("{}__Tag__{}", enum_name.as_ref(), variant.as_ref())
+    }
+
+    /// pn_Shape__Circle
+    fn enum_payload_ty<S: AsRef<str>, T: AsRef<str>>(enum_name: S, variant: T) -> String {
+        format!("{}__{}", enum_name.as_ref(), variant.as_ref())
+    }
+
+    /// pn_Shape__Data
+    fn enum_data_ty<S: AsRef<str>>(enum_name: S) -> String {
+        format!("{}__Data", enum_name.as_ref())
+    }
+
+    /// The name of the nth value in the payload of a variant.
+    fn payload_field(index: usize) -> CIdentifier {
+        CIdentifier::new(format!("_{}", index))
+    }
+
+    /// obj->data.pn_Circle._0
+    fn payload_member<S: AsRef<str>>(obj: CExpr, variant: S, field: &CIdentifier) -> CMemberExpr {
+        let data = CMemberExpr::new(
+            obj,
+            CMemberOperator::Indirect,
+            CIdentifier::new(KI_ENUM_DATA),
+        );
+        let payload = CMemberExpr::new(
+            data.into(),
+            CMemberOperator::Direct,
+            CIdentifier::new(variant),
+        );
+        CMemberExpr::new(payload.into(), CMemberOperator::Direct, field.clone())
+    }
+
+    fn pointer_to_struct<S: AsRef<str>>(ty: S) -> CType {
+        let ty = CType::Struct(ty.as_ref().to_string());
+        CType::Pointer(Box::new(ty))
+    }
+}
@@ -1,2 +1,2 @@
-pub mod ast_to_c;
 pub mod extract_header;
+pub mod mir_to_c;
@@ -0,0 +1,70 @@
+// Every test file uses only some of the helpers.
+#![allow(dead_code)]
+
+use std::{collections::HashMap, path::Path};
+
+use base::{file_cache::FileCache, source_id::SourceId};
+use c_gen::{c_ast::write::CAstWriter, passes::mir_to_c::MirToCAst};
+use hir::lower::AstToHir;
+use mir::lower::HirToMir;
+use resolve::resolver::Resolver;
+use syntax::{
+    ast::{Module, Package},
+    parser::Parser,
+};
+use typeck::checker::TypeChecker;
+
+/// The C of every module of a package made of modules with the given paths and sources, keyed
+/// by the name of its translation unit. The root module comes last and has an empty path.
+pub fn generate(modules: &[(&str, &str)]) -> HashMap<String, String> {
+    generate_with(modules, None)
+}
+
+/// Like `generate`, with `#line` directives in units written to `line_directives`.
+pub fn generate_with(
+    modules: &[(&str, &str)],
+    line_directives: Option<&Path>,
+) -> HashMap<String, String> {
+    let mut files = HashMap::new();
+    let modules = modules
+        .iter()
+        .map(|(path, source)| {
+            let file = match path.is_empty() {
+                true => "main.pine".to_string(),
+                false => format!("{}.pine", path),
+            };
+            let source_id = SourceId::from_path(file);
+            files.insert(source_id, source.to_string());
+            let (program, errors) = Parser::parse_file(source_id, source);
+            assert!(errors.is_empty(), "The test program has syntax errors");
+            let path = match path.is_empty() {
+                true => vec![],
+                false => path.split('.').map(str::to_string).collect(),
+            };
+            Module {
+                path,
+                source: source_id,
+                program,
+            }
+        })
+        .collect();
+    let mut package = Package { modules };
+    assert!(
+        Resolver::resolve(&mut package).is_empty(),
+        "The test program has name errors"
+    );
+    let (types, errors) = TypeChecker::check(&package);
+    assert!(errors.is_empty(), "The test program has type errors");
+    let hir = AstToHir::lower(&package, &types);
+    let mir = HirToMir::lower(&hir);
+    let files = FileCache::new(files);
+    hir.module_ids()
+        .map(|id| {
+            let unit = MirToCAst::transform(&hir, &mir, id, &files);
+            (
+                unit.name.clone(),
+                CAstWriter::write_unit(&unit, line_directives),
+            )
+        })
+        .collect()
+}
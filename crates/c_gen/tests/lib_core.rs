@@ -0,0 +1,35 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use c_gen::lib_core::{copy_core_c, fetch_core_c};
+
+#[test]
+fn the_embedded_runtime_is_the_core_c_directory() {
+    let core_c = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/lib_core/core_c");
+    let copy_to = env::temp_dir().join(format!("pine-core-c-{}", std::process::id()));
+    let mut copies = copy_core_c(&copy_to, None);
+    let mut files = fetch_core_c(&core_c);
+    copies.sort();
+    files.sort();
+
+    let names = |paths: &[PathBuf]| -> Vec<OsString> {
+        paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(names(&copies), names(&files));
+    for (copy, file) in copies.iter().zip(&files) {
+        let copied = fs::read_to_string(copy).unwrap();
+        assert!(
+            copied == fs::read_to_string(file).unwrap(),
+            "{} differs",
+            file.display()
+        );
+    }
+    fs::remove_dir_all(&copy_to).unwrap();
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::path::Path;
+
+use c_gen::c_ast::write::CAstWriter;
+use common::generate_with;
+
+const OUT: &str = "/out/main_.c";
+
+fn renumber(c: &str) -> String {
+    CAstWriter::renumber_line_directives(c, Path::new(OUT))
+}
+
+#[test]
+fn lines_split_by_formatting_move_the_directive_down() {
+    let formatted = "\
+#line 3 \"main.pine\"
+int64_t pn_f(int64_t pn_a,
+             int64_t pn_b) {
+  return pn_a;
+}
+
+#line 5 \"/out/main_.c\"
+pn_Point *_pn_Point__internal__new_gc(int64_t pn_x) {
+  return 0;
+}
+";
+    let expected = "\
+#line 3 \"main.pine\"
+int64_t pn_f(int64_t pn_a,
+             int64_t pn_b) {
+  return pn_a;
+}
+
+#line 8 \"/out/main_.c\"
+pn_Point *_pn_Point__internal__new_gc(int64_t pn_x) {
+  return 0;
+}
+";
+    assert_eq!(renumber(formatted), expected);
+}
+
+#[test]
+fn lines_merged_by_formatting_move_the_directive_up() {
+    let formatted = "\
+#line 1 \"main.pine\"
+void pn_f() { pn_g(); }
+#line 9 \"/out/main_.c\"
+void _call() {}
+";
+    let expected = "\
+#line 1 \"main.pine\"
+void pn_f() { pn_g(); }
+#line 4 \"/out/main_.c\"
+void _call() {}
+";
+    assert_eq!(renumber(formatted), expected);
+}
+
+#[test]
+fn only_directives_pointing_at_the_file_itself_change() {
+    let formatted = "\
+#line 7 \"main.pine\"
+void pn_f() {}
+  #line 7 \"/out/main_.c\"
+void _g() {}
+#line 7 \"/out/other.c\"
+#line 20 \"/out/main_.c\"
+";
+    let expected = "\
+#line 7 \"main.pine\"
+void pn_f() {}
+#line 4 \"/out/main_.c\"
+void _g() {}
+#line 7 \"/out/other.c\"
+#line 7 \"/out/main_.c\"
+";
+    assert_eq!(renumber(formatted), expected);
+}
+
+#[test]
+fn file_names_are_matched_escaped() {
+    let file = Path::new("/out/my \"app\"/main_.c");
+    let formatted = "int x;\n#line 1 \"/out/my \\\"app\\\"/main_.c\"\n";
+    let expected = "int x;\n#line 3 \"/out/my \\\"app\\\"/main_.c\"\n";
+    assert_eq!(
+        CAstWriter::renumber_line_directives(formatted, file),
+        expected
+    );
+}
+
+#[test]
+fn written_directives_already_have_the_right_numbers() {
+    let c = generate_with(
+        &[(
+            "",
+            "
+            type Point = { x: i64 }
+
+            fun get(p: Point) -> i64 {
+                return p.x;
+            }
+
+            fun main() -> () {
+                let f = fun(n: i64) -> i64 { return n + 1; };
+                print_int(f(get(Point { x: 1 })));
+            }
+            ",
+        )],
+        Some(Path::new(OUT)),
+    );
+    let c = &c["main_"];
+    // the functions point at their declarations, the generated code back at the C file
+    assert!(c.contains("#line 4 \"main.pine\"\nint64_t pn_get("));
+    assert!(c.contains("#line 8 \"main.pine\"\nvoid pine_lang_main("));
+    assert!(c.contains("\"/out/main_.c\"\npn_Point* _pn_Point__internal__new_gc("));
+    assert!(c.contains("#line 9 \"main.pine\"\nstatic int64_t _main___lambda_0("));
+    assert_eq!(renumber(c).trim_end(), c.trim_end());
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::generate;
+
+#[test]
+fn c_keywords_and_runtime_names_are_prefixed() {
+    let c = generate(&[(
+        "",
+        "
+        type int = { static: i64, gc: i64 }
+
+        fun free(malloc: i64) -> i64 {
+            let gc = malloc;
+            return gc;
+        }
+
+        fun printf(while_: int) -> () {}
+
+        fun main() -> () {
+            let x = int { static: 1, gc: 2 };
+            print_int(free(x.static));
+            printf(x);
+        }
+        ",
+    )]);
+    let c = &c["main_"];
+    assert!(c.contains("int64_t pn_free(int64_t pn_malloc)"));
+    assert!(c.contains("pn_gc=pn_malloc"));
+    assert!(c.contains("void pn_printf(pn_int* pn_while__)"));
+    assert!(c.contains("pn_free(_t"));
+    assert!(c.contains("->pn_static"));
+    assert!(!c.contains(" free("));
+}
+
+#[test]
+fn main_of_the_root_module_is_the_entry_point() {
+    let c = generate(&[
+        ("util", "fun main() -> i64 { return 1; }"),
+        (
+            "",
+            "
+            import util;
+
+            fun pine_lang_main() -> () {}
+
+            fun main() -> () {
+                print_int(util.main());
+                pine_lang_main();
+            }
+            ",
+        ),
+    ]);
+    assert!(c["main_"].contains("void pine_lang_main()"));
+    assert!(c["main_"].contains("void pn_pine_lang_main()"));
+    assert!(c["util"].contains("int64_t pn_util__main()"));
+}
+
+#[test]
+fn nested_module_paths_are_joined() {
+    let c = generate(&[
+        (
+            "geometry.shapes",
+            "
+            type Square = { side: i64 }
+
+            fun area(s: Square) -> i64 { return s.side * s.side; }
+            ",
+        ),
+        (
+            "",
+            "
+            import geometry.shapes;
+
+            fun main() -> () {
+                print_int(shapes.area(Square { side: 2 }));
+            }
+            ",
+        ),
+    ]);
+    let shapes = &c["geometry__shapes"];
+    assert!(shapes.contains("int64_t pn_geometry__shapes__area(pn_geometry__shapes__Square* pn_s)"));
+    assert!(c["main_"].contains("pn_geometry__shapes__area("));
+}
+
+#[test]
+fn modules_declaring_the_same_name_do_not_clash() {
+    let declarations = "
+        type Box = { value: i64 }
+
+        fun get() -> i64 { return 1; }
+        fun make() -> Box { return Box { value: 2 }; }
+    ";
+    let c = generate(&[
+        ("a", declarations),
+        ("b", declarations),
+        (
+            "",
+            "
+            import a;
+            import b;
+
+            fun get() -> i64 { return 3; }
+
+            fun main() -> () {
+                let x: a.Box = a.make();
+                print_int(x.value + b.get() + get());
+            }
+            ",
+        ),
+    ]);
+    assert!(c["a"].contains("int64_t pn_a__get()"));
+    assert!(c["a"].contains("pn_a__Box* pn_a__make()"));
+    assert!(c["b"].contains("int64_t pn_b__get()"));
+    assert!(c["b"].contains("pn_b__Box* pn_b__make()"));
+    assert!(c["main_"].contains("int64_t pn_get()"));
+}
+
+#[test]
+fn joined_names_do_not_collide() {
+    let c = generate(&[
+        ("a.b", "fun c() -> i64 { return 1; }"),
+        ("a", "fun b__c() -> i64 { return 2; }"),
+        (
+            "",
+            "
+            import a.b;
+            import a;
+
+            fun main() -> () {
+                print_int(b.c() + a.b__c());
+            }
+            ",
+        ),
+    ]);
+    assert!(c["a__b"].contains("int64_t pn_a__b__c()"));
+    assert!(c["a"].contains("int64_t pn_a__b__c_()"));
+}
@@ -1,19 +1,46 @@
 // pub mod rc_pass;
+mod loader;
+pub mod repl;
 
 use std::{
+    io::Write,
     path::{Path, PathBuf},
     sync::mpsc::Sender,
+    thread,
 };
 
-use base::{compile_context::CompileContext, source_id::SourceId};
+use base::compile_context::CompileContext;
 use c_gen::{
-    c_ast::{ast::CTranslationUnit, write::CAstWriter},
+    c_ast::{
+        ast::{CTranslationBundle, CTranslationUnit},
+        write::CAstWriter,
+    },
     compiler_runner::{gcc::Gcc, CodegenRunner, OptLevel, RunnerOptions},
     lib_core::copy_core_c,
-    passes::{ast_to_c::AstToCAst, extract_header::ExtractHeader},
+    passes::{extract_header::ExtractHeader, mir_to_c::MirToCAst},
 };
+use hir::{ir as hir_ir, lower::AstToHir};
+use interp::interpreter::Interpreter;
+use loader::PackageLoader;
 use messages::message::Message;
-use syntax::{ast::Program, parser::Parser};
+use mir::{escape::EscapeAnalysis, ir as mir_ir, lower::HirToMir, print::MirPrinter};
+use resolve::resolver::Resolver;
+use syntax::ast::Package;
+use typeck::{checker::TypeChecker, table::TypeTable};
+
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Runs `f` on a thread whose stack is large enough for deeply recursive programs.
+fn on_interpreter_stack<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(INTERPRETER_STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("Failed to start the interpreter")
+            .join()
+            .expect("The interpreter panicked")
+    })
+}
 
 pub struct Compiler<'a> {
     context: &'a mut CompileContext,
@@ -24,43 +51,74 @@ impl<'a> Compiler<'a> {
         Self { context }
     }
 
-    pub fn compile(&mut self, message_sender: Sender<Message>) {
-        let source_id = SourceId::from_path(&self.context.build_pkg);
+    /// Compiles the package and runs it, if the context asks for it. Returns the exit code of
+    /// the program that ran.
+    pub fn compile(&mut self, message_sender: Sender<Message>) -> Option<i32> {
+        let (mut package, errors) =
+            PackageLoader::load(&mut self.context.file_cache, &self.context.build_pkg);
+        if Self::report(&message_sender, errors) {
+            return None;
+        }
 
-        let file_content = self.context.file_cache.fetch(source_id).unwrap();
-        let parsed = match Parser::parse_file(source_id, file_content) {
-            Ok(parsed) => parsed,
-            Err(e) => {
-                message_sender.send(e).expect("Failed to send message");
-                return;
-            }
-        };
+        let errors = Resolver::resolve(&mut package);
+        if Self::report(&message_sender, errors) {
+            return None;
+        }
+
+        let (types, errors) = TypeChecker::check(&package);
+        if Self::report(&message_sender, errors) {
+            return None;
+        }
 
         if self.context.emit_irs {
-            self.write_parsed_to_file(&parsed);
+            self.write_parsed_to_file(&package);
+            self.write_types_to_file(&types);
         }
 
-        let mut source_unit = AstToCAst::transform(&parsed, source_id.filename());
-        let header_unit: CTranslationUnit = ExtractHeader::extract(&mut source_unit);
+        if self.context.interpret {
+            return Some(self.interpret(&package, &types));
+        }
 
-        let source = self.write_c_to_file(&source_unit, &self.context.build_dir);
-        c_gen::format_generated(source);
-        let header = self.write_c_to_file(&header_unit, &self.context.build_dir);
-        c_gen::format_generated(header);
+        let mut hir = AstToHir::lower(&package, &types);
+        opt::optimize(&mut hir);
+        let mut mir = HirToMir::lower(&hir);
+        EscapeAnalysis::run(&hir, &mut mir);
+        if self.context.emit_irs {
+            self.write_hir_to_file(&hir);
+            self.write_mir_to_file(&hir, &mir);
+        }
 
-        let main_file = c_gen::build_c_main_file(source_id.filename());
+        // every module becomes its own translation unit
+        let mut build_files = Vec::new();
+        for module in hir.module_ids() {
+            let mut source_unit =
+                MirToCAst::transform(&hir, &mir, module, &self.context.file_cache);
+            let header_unit: CTranslationUnit = ExtractHeader::extract(&mut source_unit);
+            let bundle = CTranslationBundle::new(header_unit, source_unit);
+            build_files.push(self.write_bundle(&bundle));
+        }
+
+        let root = hir
+            .modules
+            .last()
+            .expect("The root module is always loaded");
+        let main_file = c_gen::build_c_main_file(MirToCAst::unit_name(root));
         let main_file = self.write_c_to_file(&main_file, &self.context.build_dir);
-        c_gen::format_generated(main_file);
+        c_gen::format_generated(main_file.clone());
+        build_files.push(main_file);
 
-        copy_core_c(&self.context.build_dir);
+        let core_c = copy_core_c(&self.context.build_dir, self.context.runtime_dir.as_deref());
+        build_files.extend(
+            core_c
+                .into_iter()
+                .filter(|f| f.extension() == Some("c".as_ref())),
+        );
 
         let options = RunnerOptions {
             output_name: String::from("out"),
             output_path: self.context.build_dir.clone(),
-            build_files: vec![
-                self.context.build_dir.join("*.h"),
-                self.context.build_dir.join("*.c"),
-            ],
+            build_files,
+            link_libraries: self.context.link_libraries.clone(),
             optimization_level: OptLevel::Debug,
         };
 
@@ -74,22 +132,87 @@ impl<'a> Compiler<'a> {
             Err(e) => panic!("Failed C Compilation: {}", e),
         };
 
-        if self.context.run_immediately {
-            let start = std::time::Instant::now();
-            let _ = std::process::Command::new(out)
-                .spawn()
-                .unwrap()
-                .wait_with_output();
-            let end = std::time::Instant::now();
-            let duration = end - start;
-            println!("Ran in {:?}", duration);
+        if !self.context.run_immediately {
+            return None;
+        }
+        let start = std::time::Instant::now();
+        let status = std::process::Command::new(out)
+            .status()
+            .expect("Failed to run the compiled program");
+        // a program killed by a signal has no exit code
+        let code = status.code().unwrap_or(1);
+        if code == 0 {
+            println!("Ran in {:?}", start.elapsed());
         }
+        Some(code)
     }
 
-    fn write_parsed_to_file(&self, program: &Program) {
+    /// Runs the package with the interpreter. Runtime errors abort the program like they do in
+    /// the C runtime, which exits with 1.
+    fn interpret(&self, package: &Package, types: &TypeTable) -> i32 {
+        let files = &self.context.file_cache;
+        let start = std::time::Instant::now();
+        // deeply recursive programs need more stack than the main thread has
+        let result = on_interpreter_stack(|| {
+            let mut out = std::io::stdout().lock();
+            Interpreter::run(package, types, files, &mut out)
+        });
+        let _ = std::io::stdout().flush();
+        match result {
+            Ok(()) => {
+                println!("Ran in {:?}", start.elapsed());
+                0
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                1
+            }
+        }
+    }
+
+    /// Sends all messages to the receiver. Returns `true` if there were any.
+    fn report(message_sender: &Sender<Message>, messages: Vec<Message>) -> bool {
+        let has_messages = !messages.is_empty();
+        for msg in messages {
+            message_sender.send(msg).expect("Failed to send message");
+        }
+        has_messages
+    }
+
+    fn write_parsed_to_file(&self, package: &Package) {
         let dir = self.context.build_dir.join("parsed.txt");
         let mut file = std::fs::File::create(dir).unwrap();
-        std::io::Write::write_all(&mut file, format!("{:#?}", program).as_bytes()).unwrap();
+        std::io::Write::write_all(&mut file, format!("{:#?}", package).as_bytes()).unwrap();
+    }
+
+    fn write_types_to_file(&self, types: &TypeTable) {
+        let dir = self.context.build_dir.join("types.txt");
+        let mut file = std::fs::File::create(dir).unwrap();
+        for (source, span, ty) in types.sorted() {
+            let line = format!("{}:{:?}: {}\n", source, span, ty);
+            std::io::Write::write_all(&mut file, line.as_bytes()).unwrap();
+        }
+    }
+
+    fn write_hir_to_file(&self, hir: &hir_ir::Package) {
+        let dir = self.context.build_dir.join("hir.txt");
+        let mut file = std::fs::File::create(dir).unwrap();
+        std::io::Write::write_all(&mut file, format!("{:#?}", hir).as_bytes()).unwrap();
+    }
+
+    fn write_mir_to_file(&self, hir: &hir_ir::Package, mir: &mir_ir::Package) {
+        let dir = self.context.build_dir.join("mir.txt");
+        let mut file = std::fs::File::create(dir).unwrap();
+        std::io::Write::write_all(&mut file, MirPrinter::print(hir, mir).as_bytes()).unwrap();
+    }
+
+    /// Writes the header and the source of a module. Returns the path of the source file.
+    fn write_bundle(&self, bundle: &CTranslationBundle) -> PathBuf {
+        let header = self.write_c_to_file(&bundle.header, &self.context.build_dir);
+        c_gen::format_generated(header);
+        let source = self.write_c_to_file(&bundle.source, &self.context.build_dir);
+        c_gen::format_generated(source.clone());
+        source
     }
 
     fn write_c_to_file(&self, c: &CTranslationUnit, path: &Path) -> PathBuf {
@@ -97,8 +220,9 @@ impl<'a> Compiler<'a> {
             true => "h",
             false => "c",
         };
-        let s = CAstWriter::write_unit(c);
         let dir = path.join(&c.name).with_extension(ext);
+        let line_directives = self.context.line_directives.then_some(dir.as_path());
+        let s = CAstWriter::write_unit(c, line_directives);
         let mut file = std::fs::File::create(&dir).unwrap();
         std::io::Write::write_all(&mut file, s.as_bytes()).unwrap();
         dir
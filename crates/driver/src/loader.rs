@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use base::{file_cache::FileCache, located::Located, source_id::SourceId};
+use messages::{import::ImportError, message::Message};
+use syntax::{
+    ast::{stmt::Import, Module, Package},
+    parser::Parser,
+};
+
+/// Parses the root module of a package and every module it imports, directly or through other
+/// modules.
+///
+/// Modules are looked up relative to the directory of the root module, so
+/// `import geometry.shapes;` loads `geometry/shapes.pine`.
+pub struct PackageLoader<'a> {
+    files: &'a mut FileCache,
+    root_dir: PathBuf,
+    /// The modules that are currently being loaded, starting at the root module.
+    stack: Vec<(SourceId, String)>,
+    package: Package,
+    errors: Vec<Message>,
+}
+
+impl<'a> PackageLoader<'a> {
+    pub fn load(files: &'a mut FileCache, root: &Path) -> (Package, Vec<Message>) {
+        let mut loader = Self {
+            files,
+            root_dir: root.parent().map(Path::to_path_buf).unwrap_or_default(),
+            stack: Vec::new(),
+            package: Package::default(),
+            errors: Vec::new(),
+        };
+        loader.load_module(Vec::new(), SourceId::from_path(root));
+        (loader.package, loader.errors)
+    }
+
+    fn load_module(&mut self, path: Vec<String>, source: SourceId) {
+        let content = self.files.fetch(source).unwrap();
+        let (program, errors) = Parser::parse_file(source, content);
+        self.errors.extend(errors);
+
+        let name = match path.is_empty() {
+            true => source.filename(),
+            false => path.join("."),
+        };
+        self.stack.push((source, name));
+        for import in program.imports() {
+            self.load_import(import);
+        }
+        self.stack.pop();
+
+        // Imported modules are pushed first, so every module comes after its imports.
+        self.package.modules.push(Module {
+            path,
+            source,
+            program,
+        });
+    }
+
+    fn load_import(&mut self, import: &Import) {
+        let path = import.module_path();
+        let file = path
+            .iter()
+            .fold(self.root_dir.clone(), |dir, segment| dir.join(segment))
+            .with_extension("pine");
+        let source = SourceId::from_path(&file);
+
+        let first = import
+            .path
+            .first()
+            .expect("Imports have at least one segment");
+        let last = import
+            .path
+            .last()
+            .expect("Imports have at least one segment");
+        let name = Located::new(
+            first.source,
+            first.span.start..last.span.end,
+            path.join("."),
+        );
+
+        if let Some(start) = self.stack.iter().position(|(s, _)| *s == source) {
+            let mut cycle = self.stack[start..]
+                .iter()
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<_>>();
+            cycle.push(self.stack[start].1.clone());
+            self.error(ImportError::ImportCycle { name, cycle });
+            return;
+        }
+        if self.package.modules.iter().any(|m| m.source == source) {
+            return;
+        }
+        if self.files.load(source).is_err() {
+            let file = file.display().to_string();
+            self.error(ImportError::ModuleNotFound { name, file });
+            return;
+        }
+        self.load_module(path, source);
+    }
+
+    fn error(&mut self, err: ImportError) {
+        self.errors.push(err.into());
+    }
+}
@@ -0,0 +1,277 @@
+use std::io::{self, BufRead, Write};
+
+use base::{file_cache::FileCache, located::Located, source_id::SourceId};
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label},
+    term::{
+        self,
+        termcolor::{BufferedStandardStream, ColorChoice},
+    },
+};
+use interp::{interpreter::Interpreter, value::Value};
+use messages::{message::Message, parser::ParserError};
+use resolve::resolver::Resolver;
+use syntax::{
+    ast::{
+        expr::Identifier,
+        stmt::{Block, Declaration, FunctionDeclaration, Return, Stmt, StmtExpr},
+        ty::{Type, TypedParam},
+        DeclS, Module, Package, Program, ReplInput, StmtS,
+    },
+    parser::Parser,
+};
+use typeck::{checker::TypeChecker, table::TypeTable};
+
+use crate::on_interpreter_stack;
+
+/// An interactive session that runs declarations and statements as they are entered, and
+/// prints the value of expressions.
+///
+/// Every input runs as a function of its own, whose parameters are the variables of the earlier
+/// inputs. Its statements are in a block of their own, so a `let` can define a variable of an
+/// earlier input again. The interpreter keeps the declarations and the objects of the session,
+/// and the variables the input leaves in scope are passed to the next one. Inputs with errors
+/// are left out of the session.
+pub struct Repl {
+    files: FileCache,
+    decls: Vec<DeclS>,
+    variables: Vec<Variable>,
+    interpreter: Interpreter<'static>,
+    /// The number of inputs so far, which is part of the name of their source.
+    inputs: usize,
+}
+
+/// A variable of the session.
+struct Variable {
+    name: String,
+    ty: Type,
+    value: Value,
+}
+
+impl Repl {
+    /// Reads inputs from stdin until it is closed. The session runs on the stack of the
+    /// interpreter, since its objects can't be sent between threads.
+    pub fn run() {
+        on_interpreter_stack(|| Self::new().read_inputs());
+    }
+
+    fn new() -> Self {
+        let mut files = FileCache::empty();
+        // the generated function of every input points here
+        files.insert(Self::session_source(), String::new());
+        Self {
+            files,
+            decls: Vec::new(),
+            variables: Vec::new(),
+            interpreter: Interpreter::new(Box::new(io::stdout())),
+            inputs: 0,
+        }
+    }
+
+    /// Inputs continue on the next line until they are complete.
+    fn read_inputs(&mut self) {
+        let mut lines = io::stdin().lock().lines();
+        let mut input = String::new();
+        loop {
+            let prompt = if input.is_empty() { ">> " } else { ".. " };
+            print!("{}", prompt);
+            io::stdout().flush().expect("Failed to write the prompt");
+            let Some(Ok(line)) = lines.next() else {
+                break;
+            };
+            input.push_str(&line);
+            input.push('\n');
+            if input.trim().is_empty() || self.eval(&input) {
+                input.clear();
+            }
+        }
+        println!();
+    }
+
+    /// Runs an input. Returns `false` if the input is incomplete.
+    fn eval(&mut self, input: &str) -> bool {
+        let source = SourceId::from_path(format!("<repl:{}>", self.inputs + 1));
+        let (parsed, errors) = Parser::parse_repl_input(source, input);
+        let is_incomplete =
+            |msg: &Message| matches!(msg, Message::Parse(ParserError::UnrecognizedEOF { .. }));
+        if !errors.is_empty() && errors.iter().all(is_incomplete) {
+            return false;
+        }
+
+        self.inputs += 1;
+        self.files.insert(source, input.to_string());
+        if self.report(errors) {
+            return true;
+        }
+        if let Some(parsed) = parsed {
+            self.eval_parsed(parsed);
+        }
+        true
+    }
+
+    fn eval_parsed(&mut self, parsed: ReplInput) {
+        let mut decls = self.decls.clone();
+        let mut stmts = Vec::new();
+        let mut is_expr = false;
+        match parsed {
+            ReplInput::Declaration(decl) if matches!(decl.value, Declaration::Import(_)) => {
+                let diagnostic = Diagnostic::error()
+                    .with_message("imports are not supported in the REPL")
+                    .with_labels(vec![Label::primary(decl.source, decl.span)]);
+                self.emit(&diagnostic);
+                return;
+            }
+            ReplInput::Declaration(decl) => decls.push(decl),
+            ReplInput::Stmt(stmt) => stmts.push(stmt),
+            ReplInput::Expr(expr) => {
+                is_expr = true;
+                let stmt = Stmt::Expr(StmtExpr {
+                    value: expr.clone(),
+                });
+                stmts.push(Located::new(expr.source, expr.span, stmt));
+            }
+        }
+
+        let mut package = self.package(decls.clone(), stmts);
+        if self.report(Resolver::resolve(&mut package)) {
+            return;
+        }
+        let (types, errors) = TypeChecker::check(&package);
+        if self.report(errors) {
+            return;
+        }
+
+        // the declarations are loaded, the function of the input runs on its own
+        let module = &mut package.modules[0];
+        let input = module
+            .program
+            .stmts
+            .pop()
+            .expect("The input was just added");
+        let Declaration::Fun(input) = input.value else {
+            unreachable!("Inputs run as functions")
+        };
+        let Some(Stmt::Block(Block { mut stmts })) =
+            (input.body.stmts.into_iter().next()).map(|stmt| stmt.value)
+        else {
+            unreachable!("The statements of an input are in a block")
+        };
+        let defined = Self::defined_variables(&stmts, &types);
+
+        // the function returns the value of the expression. The type checker already accepted
+        // it as a statement, and the types of the expressions are the same either way.
+        let mut show_value = false;
+        if let Some(Stmt::Expr(StmtExpr { value })) = stmts.last().map(|stmt| &stmt.value) {
+            if is_expr && types.expr_ty(value) != Some(&Type::Unit) {
+                show_value = true;
+                let value = Some(value.clone());
+                stmts.last_mut().expect("The expression is the last").value =
+                    Stmt::Return(Return { value });
+            }
+        }
+
+        self.interpreter.load(&package, &types);
+        let args = self.variables.iter().map(|v| v.value.clone()).collect();
+        let module = &package.modules[0];
+        let result =
+            (self.interpreter).run_input(module, &input.params, &stmts, &types, &self.files, args);
+        match result {
+            Ok((value, mut values)) => {
+                if show_value {
+                    println!("{}", self.interpreter.display(&value));
+                }
+                // variables that are defined again keep their place
+                for variable in &mut self.variables {
+                    variable.value = values
+                        .remove(&variable.name)
+                        .expect("Variables stay in scope");
+                }
+                for (name, ty) in defined {
+                    match self.variables.iter_mut().find(|v| v.name == name) {
+                        Some(variable) => variable.ty = ty,
+                        None => {
+                            let value = values.remove(&name).expect("Variables stay in scope");
+                            self.variables.push(Variable { name, ty, value });
+                        }
+                    }
+                }
+                self.decls = decls;
+            }
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    /// The variables the statements define at their top level with their types, in the order
+    /// they are defined.
+    fn defined_variables(stmts: &[StmtS], types: &TypeTable) -> Vec<(String, Type)> {
+        let mut defined: Vec<(String, Type)> = Vec::new();
+        for stmt in stmts {
+            if let Stmt::VariableDeclaration(v) = &stmt.value {
+                let ty = match &v.ty {
+                    Some(ty) => ty.value.clone(),
+                    None => (types.expr_ty(&v.value).cloned())
+                        .expect("Variables have the type of their value"),
+                };
+                defined.retain(|(name, _)| *name != v.var.name);
+                defined.push((v.var.name.clone(), ty));
+            }
+        }
+        defined
+    }
+
+    /// The session as a package with the input as its last function. The variables of the
+    /// session are its parameters and the statements are in a block.
+    fn package(&self, mut decls: Vec<DeclS>, stmts: Vec<StmtS>) -> Package {
+        let source = Self::session_source();
+        let params = (self.variables.iter())
+            .map(|v| TypedParam {
+                name: Located::new(
+                    source,
+                    0..0,
+                    Identifier {
+                        name: v.name.clone(),
+                    },
+                ),
+                ty: Located::new(source, 0..0, v.ty.clone()),
+            })
+            .collect();
+        let block = Located::new(source, 0..0, Stmt::Block(Block { stmts }));
+        let input = FunctionDeclaration {
+            name: Identifier {
+                name: "main".to_string(),
+            },
+            type_params: Vec::new(),
+            params,
+            ret_ty: Located::new(source, 0..0, Type::Unit),
+            body: Block { stmts: vec![block] },
+        };
+        decls.push(Located::new(source, 0..0, Declaration::Fun(input)));
+        let module = Module {
+            path: Vec::new(),
+            source,
+            program: Program { stmts: decls },
+        };
+        Package {
+            modules: vec![module],
+        }
+    }
+
+    fn session_source() -> SourceId {
+        SourceId::from_path("repl.pine")
+    }
+
+    /// Prints the messages. Returns `true` if there were any.
+    fn report(&self, messages: Vec<Message>) -> bool {
+        for msg in &messages {
+            self.emit(&msg.as_diagnostic());
+        }
+        !messages.is_empty()
+    }
+
+    fn emit(&self, diagnostic: &Diagnostic<SourceId>) {
+        let mut writer = BufferedStandardStream::stderr(ColorChoice::Always);
+        let config = term::Config::default();
+        term::emit(&mut writer, &config, &self.files, diagnostic).unwrap();
+        writer.flush().unwrap();
+    }
+}
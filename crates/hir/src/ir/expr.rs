@@ -0,0 +1,104 @@
+use syntax::{
+    ast::op::{OpInfix, OpPrefix},
+    builtins::{BuiltinFunction, Intrinsic},
+};
+
+use super::{ty::Ty, EnumId, ExprS, FunctionId, LocalId, StructId};
+
+#[derive(Clone, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Ty,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExprKind {
+    Literal(Literal),
+    Local(LocalId),
+    /// A variable of an enclosing function, by its index in the captures of the lambda.
+    Captured(usize),
+    /// A function used as a value.
+    FunctionRef(FunctionId),
+    BuiltinRef(&'static BuiltinFunction),
+    /// Calls a function, method or extern function. Methods get the object as their first
+    /// argument.
+    Call(FunctionId, Vec<ExprS>),
+    CallBuiltin(&'static BuiltinFunction, Vec<ExprS>),
+    /// Calls the closure the first expression evaluates to.
+    CallClosure(Box<ExprS>, Vec<ExprS>),
+    Intrinsic(Intrinsic, Vec<ExprS>),
+    /// A field of a type object, by its index.
+    Field(Box<ExprS>, usize),
+    Prefix(OpPrefix, Box<ExprS>),
+    Infix(Box<ExprS>, OpInfix, Box<ExprS>),
+    /// A new type object with the values of its fields in declaration order.
+    Struct(StructId, Vec<ExprS>),
+    /// A variant of an enum, by its index, with its payload.
+    Variant(EnumId, usize, Vec<ExprS>),
+    Index(Box<ExprS>, Box<ExprS>),
+    /// An array or list literal, depending on the type.
+    Array(Vec<ExprS>),
+    Unwrap(Box<ExprS>),
+    /// A closure of a lifted lambda, with the values of the variables it captures.
+    Closure(FunctionId, Vec<ExprS>),
+}
+
+impl ExprKind {
+    /// The expressions the expression is computed from, in evaluation order.
+    pub fn operands(&self) -> Vec<&ExprS> {
+        match self {
+            ExprKind::Literal(_)
+            | ExprKind::Local(_)
+            | ExprKind::Captured(_)
+            | ExprKind::FunctionRef(_)
+            | ExprKind::BuiltinRef(_) => vec![],
+            ExprKind::Call(_, args)
+            | ExprKind::CallBuiltin(_, args)
+            | ExprKind::Intrinsic(_, args)
+            | ExprKind::Struct(_, args)
+            | ExprKind::Variant(_, _, args)
+            | ExprKind::Array(args)
+            | ExprKind::Closure(_, args) => args.iter().collect(),
+            ExprKind::CallClosure(callee, args) => {
+                std::iter::once(callee.as_ref()).chain(args).collect()
+            }
+            ExprKind::Field(object, _) | ExprKind::Prefix(_, object) | ExprKind::Unwrap(object) => {
+                vec![object]
+            }
+            ExprKind::Infix(lt, _, rt) | ExprKind::Index(lt, rt) => vec![lt, rt],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut ExprS> {
+        match self {
+            ExprKind::Literal(_)
+            | ExprKind::Local(_)
+            | ExprKind::Captured(_)
+            | ExprKind::FunctionRef(_)
+            | ExprKind::BuiltinRef(_) => vec![],
+            ExprKind::Call(_, args)
+            | ExprKind::CallBuiltin(_, args)
+            | ExprKind::Intrinsic(_, args)
+            | ExprKind::Struct(_, args)
+            | ExprKind::Variant(_, _, args)
+            | ExprKind::Array(args)
+            | ExprKind::Closure(_, args) => args.iter_mut().collect(),
+            ExprKind::CallClosure(callee, args) => {
+                std::iter::once(callee.as_mut()).chain(args).collect()
+            }
+            ExprKind::Field(object, _) | ExprKind::Prefix(_, object) | ExprKind::Unwrap(object) => {
+                vec![object]
+            }
+            ExprKind::Infix(lt, _, rt) | ExprKind::Index(lt, rt) => vec![lt, rt],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Nil,
+}
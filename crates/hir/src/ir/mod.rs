@@ -0,0 +1,194 @@
+pub mod expr;
+pub mod stmt;
+pub mod ty;
+
+use base::{located::Located, source_id::SourceId};
+use expr::Expr;
+use stmt::{Block, Stmt};
+use ty::Ty;
+
+pub type ExprS = Located<Expr>;
+pub type StmtS = Located<Stmt>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ModuleId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FunctionId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StructId(pub usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EnumId(pub usize);
+
+/// A variable of a function, by its index in the function's locals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LocalId(pub usize);
+
+/// A package after type checking. Names are resolved to the declarations they refer to, and
+/// generic declarations only exist as instances for the type arguments they are used with.
+#[derive(Debug, Default)]
+pub struct Package {
+    /// In the order of the modules of the parsed package, so the root module is last.
+    pub modules: Vec<Module>,
+    pub functions: Vec<Function>,
+    pub structs: Vec<Struct>,
+    pub enums: Vec<Enum>,
+}
+
+impl Package {
+    pub fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id.0]
+    }
+
+    pub fn function(&self, id: FunctionId) -> &Function {
+        &self.functions[id.0]
+    }
+
+    pub fn struct_(&self, id: StructId) -> &Struct {
+        &self.structs[id.0]
+    }
+
+    pub fn enum_(&self, id: EnumId) -> &Enum {
+        &self.enums[id.0]
+    }
+
+    pub fn module_ids(&self) -> impl Iterator<Item = ModuleId> {
+        (0..self.modules.len()).map(ModuleId)
+    }
+
+    pub fn function_ids(&self) -> impl Iterator<Item = FunctionId> {
+        (0..self.functions.len()).map(FunctionId)
+    }
+}
+
+#[derive(Debug)]
+pub struct Module {
+    /// The path the module is imported by. Empty for the root module.
+    pub path: Vec<String>,
+    pub source: SourceId,
+    /// The modules it imports directly.
+    pub imports: Vec<ModuleId>,
+    /// The declarations of the module in source order. Instances of generic declarations and
+    /// lifted lambdas are not part of any module.
+    pub items: Vec<Item>,
+}
+
+impl Module {
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Item {
+    Function(FunctionId),
+    Struct(StructId),
+    Enum(EnumId),
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    /// The module that declares the function, or the generic function it is an instance of.
+    pub module: ModuleId,
+    pub kind: FunctionKind,
+    /// Where the function, or the generic function it is an instance of, is declared. Lambdas
+    /// are located at their expression.
+    pub location: Located<()>,
+    pub params: Vec<LocalId>,
+    pub ret_ty: Ty,
+    /// The parameters and variables of the function, including the ones lowering introduces.
+    pub locals: Vec<Local>,
+    /// `None` for extern functions.
+    pub body: Option<Block>,
+}
+
+impl Function {
+    pub fn local(&self, id: LocalId) -> &Local {
+        &self.locals[id.0]
+    }
+
+    pub fn param_tys(&self) -> Vec<Ty> {
+        self.params
+            .iter()
+            .map(|p| self.local(*p).ty.clone())
+            .collect()
+    }
+
+    /// Whether the function is only used by the modules that refer to it, instead of being
+    /// declared by its module.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self.kind,
+            FunctionKind::Instance { .. } | FunctionKind::Lambda { .. }
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum FunctionKind {
+    Function,
+    /// A method of a type object or enum. Its first parameter is `self`.
+    Method {
+        of: Ty,
+    },
+    /// A generic function with its type parameters replaced by `type_args`.
+    Instance {
+        type_args: Vec<Ty>,
+    },
+    /// A lambda lifted out of the function it was written in. It reads the variables it
+    /// captures from its environment.
+    Lambda {
+        captures: Vec<Local>,
+    },
+    /// A function implemented in C, declared by `header` if it has one.
+    Extern {
+        header: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Local {
+    pub name: String,
+    pub ty: Ty,
+}
+
+#[derive(Debug)]
+pub struct Struct {
+    /// The name it is declared with. Types of different modules can share it.
+    pub name: String,
+    pub module: ModuleId,
+    /// The type arguments of an instance of a generic type object, otherwise empty.
+    pub type_args: Vec<Ty>,
+    pub fields: Vec<Field>,
+    /// Whether the program creates objects of the type, so it needs a constructor.
+    pub constructed: bool,
+}
+
+impl Struct {
+    pub fn is_instance(&self) -> bool {
+        !self.type_args.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub ty: Ty,
+}
+
+#[derive(Debug)]
+pub struct Enum {
+    /// The name it is declared with. Types of different modules can share it.
+    pub name: String,
+    pub module: ModuleId,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug)]
+pub struct Variant {
+    pub name: String,
+    pub payload: Vec<Ty>,
+}
@@ -0,0 +1,52 @@
+use super::{EnumId, ExprS, LocalId, StmtS};
+
+#[derive(Clone, Debug)]
+pub enum Stmt {
+    Block(Block),
+    Expr(ExprS),
+    /// Defines a variable. The variable is only used after its definition.
+    Let(LocalId, ExprS),
+    /// Assigns to a variable, captured variable, field or element.
+    Assign(ExprS, ExprS),
+    If(ExprS, Block, Option<Block>),
+    While(While),
+    Match(Match),
+    Return(Option<ExprS>),
+    Break,
+    Continue,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Block {
+    pub stmts: Vec<StmtS>,
+}
+
+/// `loop` is a `while (true)`, and `for` counts its variable up in `step`.
+#[derive(Clone, Debug)]
+pub struct While {
+    pub cond: ExprS,
+    pub body: Block,
+    /// Runs after the body and on `continue`.
+    pub step: Option<Box<StmtS>>,
+}
+
+/// Runs the first arm whose pattern matches the variant of the enum in `scrutinee`.
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub scrutinee: LocalId,
+    pub enum_: EnumId,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Block,
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Wildcard,
+    /// The index of the variant, and the variables its payload is bound to.
+    Variant(usize, Vec<LocalId>),
+}
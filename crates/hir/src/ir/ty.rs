@@ -0,0 +1,37 @@
+use super::{EnumId, StructId};
+
+/// The type of a value. Type objects and enums are referred to by their id, and every instance
+/// of a generic type object has an id of its own.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Ty {
+    Unit,
+    Bool,
+    I8,
+    I32,
+    I64,
+    U8,
+    U32,
+    U64,
+    F32,
+    F64,
+    Str,
+    Struct(StructId),
+    Enum(EnumId),
+    Array(Box<Ty>, usize),
+    List(Box<Ty>),
+    Optional(Box<Ty>),
+    Function(Vec<Ty>, Box<Ty>),
+}
+
+impl Ty {
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Ty::I8 | Ty::I32 | Ty::I64 | Ty::U8 | Ty::U32 | Ty::U64
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Ty::F32 | Ty::F64)
+    }
+}
@@ -0,0 +1,822 @@
+use std::collections::HashMap;
+
+use base::located::Located;
+use syntax::ast::{
+    self,
+    expr::{Expr as AstExpr, *},
+    stmt::{Block as AstBlock, Match as AstMatch, Stmt as AstStmt, While as AstWhile, *},
+    ty::{qualified_type_name, unqualified_type_name, Type, TypedParam},
+};
+use syntax::builtins::{find_builtin, find_intrinsic};
+use typeck::table::TypeTable;
+
+use crate::ir::{
+    expr::{Expr, ExprKind, Literal},
+    stmt::{Block, Match, MatchArm, Pattern, Stmt, While},
+    ty::Ty,
+    Enum, EnumId, ExprS, Field, Function, FunctionId, FunctionKind, Item, Local, LocalId, Module,
+    ModuleId, Package, StmtS, Struct, StructId, Variant,
+};
+
+/// The function or lambda whose body is lowered.
+#[derive(Default)]
+struct Body {
+    locals: Vec<Local>,
+    scopes: Vec<HashMap<String, LocalId>>,
+    /// The names of the variables a lambda captures, in the order of its environment.
+    captures: Vec<String>,
+}
+
+/// A module path and a name declared in that module.
+type QualifiedName = (Vec<String>, String);
+
+/// Lowers a type checked package to the HIR.
+///
+/// Generic declarations are lowered once for every combination of type arguments they are
+/// used with, and lambdas are lifted to functions of their own.
+pub struct AstToHir<'a> {
+    package: &'a ast::Package,
+    types: &'a TypeTable,
+    hir: Package,
+    /// Functions, extern functions and the instances of generic functions, keyed by module
+    /// path and name.
+    functions: HashMap<QualifiedName, FunctionId>,
+    generic_functions: HashMap<QualifiedName, (ModuleId, Located<&'a FunctionDeclaration>)>,
+    function_instances: HashMap<(Vec<String>, String, Vec<Ty>), FunctionId>,
+    /// Methods keyed by the name of their type and their own name. Type names are qualified,
+    /// like in the type checked package.
+    methods: HashMap<(String, String), FunctionId>,
+    structs: HashMap<String, StructId>,
+    generic_structs: HashMap<String, (ModuleId, &'a TypeObject)>,
+    struct_instances: HashMap<(String, Vec<Ty>), StructId>,
+    enums: HashMap<String, EnumId>,
+    /// Functions whose body still has to be lowered, with the type arguments of instances
+    /// keyed by their type parameter.
+    pending: Vec<(FunctionId, &'a FunctionDeclaration, HashMap<String, Ty>)>,
+    /// The number of lambdas lifted out of every module, used to name them.
+    lambda_counts: Vec<usize>,
+    module: ModuleId,
+    /// The paths of the modules imported by the current module, keyed by their alias.
+    imports: HashMap<String, Vec<String>>,
+    /// The type arguments of the instance that is lowered, keyed by the type parameter.
+    substitution: HashMap<String, Ty>,
+    /// The bodies that are lowered, from the function to the innermost lambda.
+    bodies: Vec<Body>,
+}
+
+impl<'a> AstToHir<'a> {
+    pub fn lower(package: &'a ast::Package, types: &'a TypeTable) -> Package {
+        let mut lower = Self {
+            package,
+            types,
+            hir: Package::default(),
+            functions: HashMap::new(),
+            generic_functions: HashMap::new(),
+            function_instances: HashMap::new(),
+            methods: HashMap::new(),
+            structs: HashMap::new(),
+            generic_structs: HashMap::new(),
+            struct_instances: HashMap::new(),
+            enums: HashMap::new(),
+            pending: Vec::new(),
+            lambda_counts: vec![0; package.modules.len()],
+            module: ModuleId(0),
+            imports: HashMap::new(),
+            substitution: HashMap::new(),
+            bodies: Vec::new(),
+        };
+        for module in &package.modules {
+            lower.declare_module(module);
+        }
+        // the declarations can refer to the types of every module
+        for (id, module) in package.modules.iter().enumerate() {
+            lower.enter_module(ModuleId(id));
+            lower.lower_declarations(module);
+        }
+        // instances of generic functions are found while lowering bodies
+        while !lower.pending.is_empty() {
+            for (id, decl, substitution) in std::mem::take(&mut lower.pending) {
+                lower.enter_module(lower.hir.function(id).module);
+                lower.substitution = substitution;
+                lower.lower_function(id, decl);
+            }
+        }
+        lower.hir
+    }
+
+    /// Gives every declaration of the module an id, so they can be referred to before they are
+    /// lowered.
+    fn declare_module(&mut self, module: &'a ast::Module) {
+        let id = ModuleId(self.hir.modules.len());
+        let imports = module
+            .program
+            .imports()
+            .filter_map(|i| {
+                self.package
+                    .modules
+                    .iter()
+                    .position(|m| m.path == i.module_path())
+            })
+            .map(ModuleId)
+            .collect();
+        let mut items = Vec::new();
+        for decl in &module.program.stmts {
+            let location = Located::empty(decl.source, decl.span.clone());
+            match &decl.value {
+                Declaration::Fun(f) if f.is_generic() => {
+                    let key = (module.path.clone(), f.name.to_string());
+                    let f = Located::new(decl.source, decl.span.clone(), f);
+                    self.generic_functions.insert(key, (id, f));
+                }
+                Declaration::Fun(f) => {
+                    let kind = FunctionKind::Function;
+                    let function = self.declare_function(f.name.as_ref(), id, kind, location);
+                    self.functions
+                        .insert((module.path.clone(), f.name.to_string()), function);
+                    self.pending.push((function, f, HashMap::new()));
+                    items.push(Item::Function(function));
+                }
+                Declaration::Extern(e) => {
+                    let kind = FunctionKind::Extern {
+                        header: e.header.clone(),
+                    };
+                    let function = self.declare_function(e.name.as_ref(), id, kind, location);
+                    self.functions
+                        .insert((module.path.clone(), e.name.to_string()), function);
+                    items.push(Item::Function(function));
+                }
+                Declaration::Impl(i) => {
+                    for method in &i.methods {
+                        // the type of `self` is known once every type has an id
+                        let kind = FunctionKind::Method { of: Ty::Unit };
+                        let location = Located::empty(method.source, method.span.clone());
+                        let name = method.value.name.as_ref();
+                        let function = self.declare_function(name, id, kind, location);
+                        let key = (i.name.value.to_string(), method.value.name.to_string());
+                        self.methods.insert(key, function);
+                        self.pending.push((function, &method.value, HashMap::new()));
+                        items.push(Item::Function(function));
+                    }
+                }
+                Declaration::TypeObject(t) if t.is_generic() => {
+                    self.generic_structs.insert(t.name.to_string(), (id, t));
+                }
+                Declaration::TypeObject(t) => {
+                    let struct_ = StructId(self.hir.structs.len());
+                    self.hir.structs.push(Struct {
+                        name: unqualified_type_name(t.name.as_ref()).to_string(),
+                        module: id,
+                        type_args: Vec::new(),
+                        fields: Vec::new(),
+                        constructed: true,
+                    });
+                    self.structs.insert(t.name.to_string(), struct_);
+                    items.push(Item::Struct(struct_));
+                }
+                Declaration::Enum(e) => {
+                    let enum_ = EnumId(self.hir.enums.len());
+                    self.hir.enums.push(Enum {
+                        name: unqualified_type_name(e.name.as_ref()).to_string(),
+                        module: id,
+                        variants: Vec::new(),
+                    });
+                    self.enums.insert(e.name.to_string(), enum_);
+                    items.push(Item::Enum(enum_));
+                }
+                Declaration::Import(_) => {}
+                Declaration::Error => unreachable!("Programs with syntax errors are not lowered"),
+            }
+        }
+        self.hir.modules.push(Module {
+            path: module.path.clone(),
+            source: module.source,
+            imports,
+            items,
+        });
+    }
+
+    fn declare_function(
+        &mut self,
+        name: &str,
+        module: ModuleId,
+        kind: FunctionKind,
+        location: Located<()>,
+    ) -> FunctionId {
+        let id = FunctionId(self.hir.functions.len());
+        self.hir.functions.push(Function {
+            name: name.to_string(),
+            module,
+            kind,
+            location,
+            params: Vec::new(),
+            ret_ty: Ty::Unit,
+            locals: Vec::new(),
+            body: None,
+        });
+        id
+    }
+
+    fn enter_module(&mut self, module: ModuleId) {
+        self.module = module;
+        self.imports = self.package.modules[module.0]
+            .program
+            .imports()
+            .map(|i| (i.alias().to_string(), i.module_path()))
+            .collect();
+        self.substitution.clear();
+    }
+
+    /// Lowers the types of the fields, payloads and signatures that are declared by the
+    /// module.
+    fn lower_declarations(&mut self, module: &ast::Module) {
+        for decl in &module.program.stmts {
+            match &decl.value {
+                Declaration::TypeObject(t) if !t.is_generic() => {
+                    let fields = t.members.iter().map(|m| self.lower_field(m)).collect();
+                    self.hir.structs[self.structs[t.name.as_ref()].0].fields = fields;
+                }
+                Declaration::Enum(e) => {
+                    let variants = e
+                        .variants
+                        .iter()
+                        .map(|v| Variant {
+                            name: v.name.value.to_string(),
+                            payload: v
+                                .payload
+                                .iter()
+                                .map(|ty| self.lower_ty(&ty.value))
+                                .collect(),
+                        })
+                        .collect();
+                    self.hir.enums[self.enums[e.name.as_ref()].0].variants = variants;
+                }
+                Declaration::Extern(e) => {
+                    let id = self.functions[&(module.path.clone(), e.name.to_string())];
+                    self.bodies.push(Body::default());
+                    let params = e.params.iter().map(|p| self.define_param(p)).collect();
+                    let locals = self.bodies.pop().expect("Body was pushed").locals;
+                    let ret_ty = self.lower_ty(&e.ret_ty.value);
+                    let function = &mut self.hir.functions[id.0];
+                    function.params = params;
+                    function.locals = locals;
+                    function.ret_ty = ret_ty;
+                }
+                Declaration::Impl(i) => {
+                    let of = self.lower_ty(&Type::Struct(i.name.value.to_string()));
+                    for method in &i.methods {
+                        let key = (i.name.value.to_string(), method.value.name.to_string());
+                        let id = self.methods[&key];
+                        self.hir.functions[id.0].kind = FunctionKind::Method { of: of.clone() };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn lower_field(&mut self, member: &TypedParam) -> Field {
+        Field {
+            name: member.name.value.to_string(),
+            ty: self.lower_ty(&member.ty.value),
+        }
+    }
+
+    fn lower_function(&mut self, id: FunctionId, decl: &FunctionDeclaration) {
+        self.bodies.push(Body::default());
+        self.push_scope();
+        let params = decl.params.iter().map(|p| self.define_param(p)).collect();
+        let body = self.lower_block(&decl.body);
+        let locals = self.bodies.pop().expect("Body was pushed").locals;
+        let ret_ty = self.lower_ty(&decl.ret_ty.value);
+
+        let function = &mut self.hir.functions[id.0];
+        function.params = params;
+        function.ret_ty = ret_ty;
+        function.locals = locals;
+        function.body = Some(body);
+    }
+
+    fn body(&mut self) -> &mut Body {
+        self.bodies
+            .last_mut()
+            .expect("Only bodies contain variables")
+    }
+
+    fn push_scope(&mut self) {
+        self.body().scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        self.body().scopes.pop();
+    }
+
+    fn define_param(&mut self, param: &TypedParam) -> LocalId {
+        let ty = self.lower_ty(&param.ty.value);
+        self.define(param.name.value.as_ref(), ty)
+    }
+
+    fn define(&mut self, name: &str, ty: Ty) -> LocalId {
+        let id = self.add_local(name.to_string(), ty);
+        if let Some(scope) = self.body().scopes.last_mut() {
+            scope.insert(name.to_string(), id);
+        }
+        id
+    }
+
+    /// A variable that is not visible to the program, like the end of a range.
+    fn temporary(&mut self, name: &str, ty: Ty) -> LocalId {
+        let index = self.body().locals.len();
+        self.add_local(format!("_{}_{}", name, index), ty)
+    }
+
+    fn add_local(&mut self, name: String, ty: Ty) -> LocalId {
+        let body = self.body();
+        body.locals.push(Local { name, ty });
+        LocalId(body.locals.len() - 1)
+    }
+
+    fn lookup(&self, name: &str) -> LocalId {
+        let body = self.bodies.last().expect("Only bodies contain variables");
+        *body
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .expect("Variables are checked by the resolver")
+    }
+
+    fn lower_block(&mut self, block: &AstBlock) -> Block {
+        self.push_scope();
+        let stmts = block
+            .stmts
+            .iter()
+            .filter_map(|stmt| self.lower_stmt(stmt))
+            .collect();
+        self.pop_scope();
+        Block { stmts }
+    }
+
+    /// The bodies of branches and loops are always blocks.
+    fn lower_body(&mut self, stmt: &ast::StmtS) -> Block {
+        match &stmt.value {
+            AstStmt::Block(b) => self.lower_block(b),
+            _ => unreachable!("Bodies are blocks"),
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt: &ast::StmtS) -> Option<StmtS> {
+        let lowered = match &stmt.value {
+            AstStmt::Block(b) => Stmt::Block(self.lower_block(b)),
+            AstStmt::Expr(e) => Stmt::Expr(self.lower_expr(&e.value)),
+            AstStmt::If(i) => {
+                let cond = self.lower_expr(&i.cond);
+                let then = self.lower_body(&i.then);
+                let else_ = i.else_.as_ref().map(|e| self.lower_body(e));
+                Stmt::If(cond, then, else_)
+            }
+            AstStmt::Return(r) => Stmt::Return(r.value.as_ref().map(|v| self.lower_expr(v))),
+            AstStmt::Break(_) => Stmt::Break,
+            AstStmt::Continue(_) => Stmt::Continue,
+            AstStmt::VariableDeclaration(v) => {
+                // the value can refer to a variable of the same name in an outer scope
+                let value = self.lower_expr(&v.value);
+                let ty = match &v.ty {
+                    Some(ty) => self.lower_ty(&ty.value),
+                    None => value.value.ty.clone(),
+                };
+                Stmt::Let(self.define(v.var.as_ref(), ty), value)
+            }
+            AstStmt::Loop(l) => {
+                let cond = Self::located(stmt, ExprKind::Literal(Literal::Bool(true)), Ty::Bool);
+                let body = self.lower_body(&l.body);
+                Stmt::While(While {
+                    cond,
+                    body,
+                    step: None,
+                })
+            }
+            AstStmt::While(w) => self.lower_while(w),
+            AstStmt::For(f) => self.lower_for(stmt, f),
+            AstStmt::Match(m) => self.lower_match(stmt, m),
+            AstStmt::Assign(a) => Stmt::Assign(self.lower_expr(&a.var), self.lower_expr(&a.value)),
+            AstStmt::Empty(_) => return None,
+            AstStmt::Error => unreachable!("Programs with syntax errors are not lowered"),
+        };
+        Some(Located::new(stmt.source, stmt.span.clone(), lowered))
+    }
+
+    fn lower_while(&mut self, w: &AstWhile) -> Stmt {
+        Stmt::While(While {
+            cond: self.lower_expr(&w.cond),
+            body: self.lower_body(&w.body),
+            step: None,
+        })
+    }
+
+    /// Input:
+    ///     for i in start..end { ... }
+    /// Output:
+    ///     {
+    ///         let i = start;
+    ///         let _for_end_1 = end;
+    ///         while (i < _for_end_1) { ... } step { i = i + 1; }
+    ///     }
+    fn lower_for(&mut self, stmt: &ast::StmtS, f: &For) -> Stmt {
+        let start = self.lower_expr(&f.start);
+        let end = self.lower_expr(&f.end);
+        let ty = start.value.ty.clone();
+
+        self.push_scope();
+        let var = self.define(f.var.value.as_ref(), ty.clone());
+        let end_var = self.temporary("for_end", ty.clone());
+        let body = self.lower_body(&f.body);
+        self.pop_scope();
+
+        let local = |id| Self::located(stmt, ExprKind::Local(id), ty.clone());
+        let cond = ExprKind::Infix(
+            Box::new(local(var)),
+            ast::op::OpInfix::Less,
+            Box::new(local(end_var)),
+        );
+        let one = Self::located(stmt, ExprKind::Literal(Literal::Int(1)), ty.clone());
+        let increment = ExprKind::Infix(Box::new(local(var)), ast::op::OpInfix::Add, Box::new(one));
+        let step = Stmt::Assign(local(var), Self::located(stmt, increment, ty.clone()));
+        let while_ = Stmt::While(While {
+            cond: Self::located(stmt, cond, Ty::Bool),
+            body,
+            step: Some(Box::new(Located::new(stmt.source, stmt.span.clone(), step))),
+        });
+
+        let stmts = vec![Stmt::Let(var, start), Stmt::Let(end_var, end), while_];
+        Stmt::Block(Block {
+            stmts: stmts
+                .into_iter()
+                .map(|s| Located::new(stmt.source, stmt.span.clone(), s))
+                .collect(),
+        })
+    }
+
+    /// The matched value is stored in a variable, whose variant the arms are compared with.
+    fn lower_match(&mut self, stmt: &ast::StmtS, m: &AstMatch) -> Stmt {
+        let value = self.lower_expr(&m.value);
+        let Ty::Enum(enum_) = value.value.ty.clone() else {
+            unreachable!("Only enums are matched")
+        };
+        let scrutinee = self.temporary("match", value.value.ty.clone());
+
+        let mut arms = Vec::new();
+        for arm in &m.arms {
+            self.push_scope();
+            let pattern = match &arm.pattern.value {
+                ast::stmt::Pattern::Wildcard => Pattern::Wildcard,
+                ast::stmt::Pattern::Variant(p) => {
+                    let index = self.variant(enum_, p.variant.as_ref());
+                    let payload = self.hir.enum_(enum_).variants[index].payload.clone();
+                    let bindings = (p.bindings.iter().zip(payload))
+                        .map(|(binding, ty)| self.define(binding.value.as_ref(), ty))
+                        .collect();
+                    Pattern::Variant(index, bindings)
+                }
+            };
+            let body = self.lower_body(&arm.body);
+            self.pop_scope();
+            arms.push(MatchArm { pattern, body });
+        }
+
+        let match_ = Stmt::Match(Match {
+            scrutinee,
+            enum_,
+            arms,
+        });
+        let stmts = vec![Stmt::Let(scrutinee, value), match_];
+        Stmt::Block(Block {
+            stmts: stmts
+                .into_iter()
+                .map(|s| Located::new(stmt.source, stmt.span.clone(), s))
+                .collect(),
+        })
+    }
+
+    fn variant(&self, enum_: EnumId, name: &str) -> usize {
+        self.hir
+            .enum_(enum_)
+            .variants
+            .iter()
+            .position(|v| v.name == name)
+            .expect("Variants are checked by the resolver")
+    }
+
+    fn lower_expr(&mut self, expr: &ast::ExprS) -> ExprS {
+        let types = self.types;
+        let ty = self.expr_ty(expr);
+        let kind = match &expr.value {
+            AstExpr::Var(v) => self.lower_var(expr, v),
+            AstExpr::Literal(l) => ExprKind::Literal(match l {
+                ExprLiteral::Bool(b) => Literal::Bool(*b),
+                ExprLiteral::Integer(i) => Literal::Int(*i),
+                ExprLiteral::Float(f) => Literal::Float(*f),
+                ExprLiteral::String(s) => Literal::Str(s.clone()),
+                ExprLiteral::Nil => Literal::Nil,
+            }),
+            AstExpr::Call(c) => self.lower_call(expr, c),
+            AstExpr::MemberAccess(m) => match types.function_ref(expr) {
+                Some(path) => self.function_ref(expr, path, m.member_name.as_ref()),
+                None => {
+                    let object = self.lower_expr(&m.object);
+                    let Ty::Struct(id) = &object.value.ty else {
+                        unreachable!("Only type objects have fields")
+                    };
+                    let field = self.field(*id, m.member_name.as_ref());
+                    ExprKind::Field(Box::new(object), field)
+                }
+            },
+            AstExpr::Prefix(p) => ExprKind::Prefix(p.op, Box::new(self.lower_expr(&p.rt))),
+            AstExpr::Infix(i) => {
+                let lt = self.lower_expr(&i.lt);
+                let rt = self.lower_expr(&i.rt);
+                ExprKind::Infix(Box::new(lt), i.op, Box::new(rt))
+            }
+            AstExpr::StructureInit(s) => {
+                let Ty::Struct(id) = &ty else {
+                    unreachable!("Structure inits create type objects")
+                };
+                let fields = self.hir.struct_(*id).fields.clone();
+                let values = fields
+                    .iter()
+                    .map(|field| {
+                        let (_, value) = (s.members.iter())
+                            .find(|(name, _)| name.as_ref() == field.name)
+                            .expect("Every field is initialized");
+                        self.lower_expr(value)
+                    })
+                    .collect();
+                ExprKind::Struct(*id, values)
+            }
+            AstExpr::EnumVariant(v) => self.lower_variant(v, vec![]),
+            AstExpr::Index(i) => {
+                let object = self.lower_expr(&i.object);
+                let index = self.lower_expr(&i.index);
+                ExprKind::Index(Box::new(object), Box::new(index))
+            }
+            AstExpr::Array(a) => {
+                ExprKind::Array(a.elements.iter().map(|e| self.lower_expr(e)).collect())
+            }
+            AstExpr::Unwrap(u) => ExprKind::Unwrap(Box::new(self.lower_expr(&u.value))),
+            AstExpr::Lambda(l) => self.lower_lambda(expr, l),
+        };
+        Located::new(expr.source, expr.span.clone(), Expr { kind, ty })
+    }
+
+    /// Variables, captured variables, and functions that are used as values.
+    fn lower_var(&mut self, expr: &ast::ExprS, var: &ExprVar) -> ExprKind {
+        let types = self.types;
+        let name = var.var.as_ref();
+        if types.is_captured(expr) {
+            return ExprKind::Captured(self.capture_index(name));
+        }
+        if let Some(path) = types.function_ref(expr) {
+            return match find_builtin(name) {
+                Some(builtin) => ExprKind::BuiltinRef(builtin),
+                None => self.function_ref(expr, path, name),
+            };
+        }
+        ExprKind::Local(self.lookup(name))
+    }
+
+    fn capture_index(&self, name: &str) -> usize {
+        let body = self.bodies.last().expect("Only bodies contain variables");
+        body.captures
+            .iter()
+            .position(|c| c == name)
+            .expect("Captured variables are captured by the lambda")
+    }
+
+    fn function_ref(&mut self, expr: &ast::ExprS, path: &[String], name: &str) -> ExprKind {
+        ExprKind::FunctionRef(self.resolve_function(expr, path, name))
+    }
+
+    /// The function `name` of the module at `path`, or its instance for the type arguments of
+    /// the call or reference `expr`.
+    fn resolve_function(&mut self, expr: &ast::ExprS, path: &[String], name: &str) -> FunctionId {
+        match self.types.type_args(expr) {
+            Some(type_args) => {
+                let type_args = type_args.iter().map(|ty| self.lower_ty(ty)).collect();
+                self.function_instance(path, name, type_args)
+            }
+            None => self.functions[&(path.to_vec(), name.to_string())],
+        }
+    }
+
+    fn lower_call(&mut self, expr: &ast::ExprS, call: &ExprCall) -> ExprKind {
+        // only callees that are values have a type, named functions and methods don't
+        if self.types.expr_ty(&call.callee).is_some() {
+            let callee = self.lower_expr(&call.callee);
+            let args = self.lower_args(&call.args);
+            return ExprKind::CallClosure(Box::new(callee), args);
+        }
+        match &call.callee.value {
+            AstExpr::EnumVariant(v) => {
+                let args = self.lower_args(&call.args);
+                self.lower_variant(v, args)
+            }
+            AstExpr::Var(callee) => {
+                let name = callee.var.as_ref();
+                if let Some(intrinsic) = find_intrinsic(name) {
+                    return ExprKind::Intrinsic(intrinsic, self.lower_args(&call.args));
+                }
+                if let Some(builtin) = find_builtin(name) {
+                    return ExprKind::CallBuiltin(builtin, self.lower_args(&call.args));
+                }
+                let path = self.package.modules[self.module.0].path.clone();
+                let function = self.resolve_function(expr, &path, name);
+                ExprKind::Call(function, self.lower_args(&call.args))
+            }
+            AstExpr::MemberAccess(m) => {
+                if let AstExpr::Var(module) = &m.object.value {
+                    if let Some(path) = self.imports.get(module.var.as_ref()).cloned() {
+                        let function = self.resolve_function(expr, &path, m.member_name.as_ref());
+                        return ExprKind::Call(function, self.lower_args(&call.args));
+                    }
+                }
+                // `object.method(args)` passes the object as `self`
+                let object = self.lower_expr(&m.object);
+                let (module, name) = match &object.value.ty {
+                    Ty::Struct(id) => (self.hir.struct_(*id).module, &self.hir.struct_(*id).name),
+                    Ty::Enum(id) => (self.hir.enum_(*id).module, &self.hir.enum_(*id).name),
+                    _ => unreachable!("Methods are only called on type objects and enums"),
+                };
+                let ty_name = qualified_type_name(&self.hir.module(module).path, name);
+                let method = self.methods[&(ty_name, m.member_name.to_string())];
+                let args = std::iter::once(object)
+                    .chain(self.lower_args(&call.args))
+                    .collect();
+                ExprKind::Call(method, args)
+            }
+            _ => unreachable!("Only functions and values of function types are called"),
+        }
+    }
+
+    fn lower_args(&mut self, args: &[ast::ExprS]) -> Vec<ExprS> {
+        args.iter().map(|arg| self.lower_expr(arg)).collect()
+    }
+
+    fn lower_variant(&mut self, variant: &ExprEnumVariant, payload: Vec<ExprS>) -> ExprKind {
+        let enum_ = self.enums[variant.enum_name.as_ref()];
+        let index = self.variant(enum_, variant.variant.as_ref());
+        ExprKind::Variant(enum_, index, payload)
+    }
+
+    fn field(&self, struct_: StructId, name: &str) -> usize {
+        self.hir
+            .struct_(struct_)
+            .fields
+            .iter()
+            .position(|f| f.name == name)
+            .expect("Fields are checked by the type checker")
+    }
+
+    /// Lifts the lambda to a function of the module. The closure gets the values of the
+    /// variables the lambda captures, which lambdas nested in lambdas take from their own
+    /// environment.
+    fn lower_lambda(&mut self, expr: &ast::ExprS, lambda: &ExprLambda) -> ExprKind {
+        let types = self.types;
+        let captures = types.captures(expr);
+        let mut values = Vec::new();
+        let mut env = Vec::new();
+        for capture in captures {
+            let ty = self.lower_ty(&capture.ty);
+            let kind = match capture.from_env {
+                true => ExprKind::Captured(self.capture_index(&capture.name)),
+                false => ExprKind::Local(self.lookup(&capture.name)),
+            };
+            values.push(Self::located(expr, kind, ty.clone()));
+            env.push(Local {
+                name: capture.name.clone(),
+                ty,
+            });
+        }
+
+        let index = self.lambda_counts[self.module.0];
+        self.lambda_counts[self.module.0] += 1;
+        let kind = FunctionKind::Lambda { captures: env };
+        let name = format!("lambda_{}", index);
+        let location = Located::empty(expr.source, expr.span.clone());
+        let id = self.declare_function(&name, self.module, kind, location);
+
+        self.bodies.push(Body {
+            captures: captures.iter().map(|c| c.name.clone()).collect(),
+            ..Body::default()
+        });
+        self.push_scope();
+        let params = lambda.params.iter().map(|p| self.define_param(p)).collect();
+        let body = self.lower_block(&lambda.body);
+        let locals = self.bodies.pop().expect("Body was pushed").locals;
+        let ret_ty = self.lower_ty(&lambda.ret_ty.value);
+
+        let function = &mut self.hir.functions[id.0];
+        function.params = params;
+        function.ret_ty = ret_ty;
+        function.locals = locals;
+        function.body = Some(body);
+        ExprKind::Closure(id, values)
+    }
+
+    /// The instance of a generic function for the given type arguments. Its body is lowered
+    /// later, in the context of the module that declares it.
+    fn function_instance(&mut self, path: &[String], name: &str, type_args: Vec<Ty>) -> FunctionId {
+        let key = (path.to_vec(), name.to_string(), type_args);
+        if let Some(id) = self.function_instances.get(&key) {
+            return *id;
+        }
+        let (module, decl) = self.generic_functions[&(path.to_vec(), name.to_string())].clone();
+        let location = Located::empty(decl.source, decl.span.clone());
+        let decl = decl.value;
+        let type_args = key.2.clone();
+        let substitution = (decl.type_params.iter())
+            .map(|p| p.value.to_string())
+            .zip(type_args.iter().cloned())
+            .collect();
+        let kind = FunctionKind::Instance { type_args };
+        let id = self.declare_function(name, module, kind, location);
+        self.function_instances.insert(key, id);
+        self.pending.push((id, decl, substitution));
+        id
+    }
+
+    /// The instance of a generic type object for the given type arguments.
+    fn struct_instance(&mut self, name: &str, type_args: Vec<Ty>) -> StructId {
+        let key = (name.to_string(), type_args);
+        if let Some(id) = self.struct_instances.get(&key) {
+            return *id;
+        }
+        let (module, decl) = self.generic_structs[name];
+        let id = StructId(self.hir.structs.len());
+        self.hir.structs.push(Struct {
+            name: unqualified_type_name(name).to_string(),
+            module,
+            type_args: key.1.clone(),
+            fields: Vec::new(),
+            constructed: true,
+        });
+        // the fields can refer to the instance itself
+        let substitution = (decl.type_params.iter())
+            .map(|p| p.value.to_string())
+            .zip(key.1.iter().cloned())
+            .collect();
+        self.struct_instances.insert(key, id);
+        let substitution = std::mem::replace(&mut self.substitution, substitution);
+        let fields = decl.members.iter().map(|m| self.lower_field(m)).collect();
+        self.substitution = substitution;
+        self.hir.structs[id.0].fields = fields;
+        id
+    }
+
+    fn lower_ty(&mut self, ty: &Type) -> Ty {
+        match ty {
+            Type::Unit => Ty::Unit,
+            Type::Bool => Ty::Bool,
+            Type::I8 => Ty::I8,
+            Type::I32 => Ty::I32,
+            Type::I64 => Ty::I64,
+            Type::U8 => Ty::U8,
+            Type::U32 => Ty::U32,
+            Type::U64 => Ty::U64,
+            Type::F32 => Ty::F32,
+            Type::F64 => Ty::F64,
+            Type::String => Ty::Str,
+            // type parameters are written like type objects
+            Type::Struct(name) => match self.substitution.get(name) {
+                Some(arg) => arg.clone(),
+                None => match self.structs.get(name) {
+                    Some(id) => Ty::Struct(*id),
+                    None => Ty::Enum(self.enums[name]),
+                },
+            },
+            Type::Array(elem, len) => Ty::Array(Box::new(self.lower_ty(elem)), *len),
+            Type::List(elem) => Ty::List(Box::new(self.lower_ty(elem))),
+            Type::Optional(inner) => Ty::Optional(Box::new(self.lower_ty(inner))),
+            Type::Generic(name, args) => {
+                let args = args.iter().map(|arg| self.lower_ty(arg)).collect();
+                Ty::Struct(self.struct_instance(name, args))
+            }
+            Type::Function(params, ret) => Ty::Function(
+                params.iter().map(|p| self.lower_ty(p)).collect(),
+                Box::new(self.lower_ty(ret)),
+            ),
+        }
+    }
+
+    /// The type the type checker inferred for `expr`, in the instance that is lowered.
+    fn expr_ty(&mut self, expr: &ast::ExprS) -> Ty {
+        let ty = self
+            .types
+            .expr_ty(expr)
+            .expect("Expression was not typechecked");
+        self.lower_ty(ty)
+    }
+
+    /// An expression that lowering introduces, located at `at`.
+    fn located<T>(at: &Located<T>, kind: ExprKind, ty: Ty) -> ExprS {
+        Located::new(at.source, at.span.clone(), Expr { kind, ty })
+    }
+}
@@ -0,0 +1,139 @@
+use std::rc::Rc;
+
+use base::source_id::SourceId;
+use syntax::{
+    ast::{
+        op::{OpInfix, OpPrefix},
+        ty::Type,
+    },
+    builtins::{BuiltinFunction, Intrinsic},
+};
+
+use crate::value::{EnumInfo, StructInfo, Value};
+
+/// A function of the interpreted program, by its index in the functions of the interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FunctionId(pub usize);
+
+/// The position in the Pine source a runtime error points at. The line and column are only
+/// looked up once an error happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub source: SourceId,
+    pub offset: usize,
+}
+
+/// The code the interpreter runs, lowered from the AST of a function.
+///
+/// Variables are resolved to their slot in the frame of the function, and everything the type
+/// checker found out about an expression is part of its code, so running it looks nothing up
+/// by name or by location.
+#[derive(Debug)]
+pub enum Function {
+    Pine {
+        /// The arguments are the first variables.
+        variables: usize,
+        body: Vec<Stmt>,
+    },
+    /// Extern functions are implemented in C, so the interpreter can't call them.
+    Extern(String),
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Block(Vec<Stmt>),
+    Expr(Expr),
+    /// Defines a variable, which is also how variables are assigned.
+    Let(usize, Expr),
+    Assign(Box<Place>, Expr),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    Return(Option<Expr>),
+    Break,
+    Continue,
+    Loop(Box<Stmt>),
+    While(Expr, Box<Stmt>),
+    For(Box<For>),
+    Match(Box<Match>),
+}
+
+/// The end of the range is evaluated once, the variable can be assigned in the body.
+#[derive(Debug)]
+pub struct For {
+    pub var: usize,
+    /// The integer type of the variable.
+    pub ty: Type,
+    pub start: Expr,
+    pub end: Expr,
+    pub body: Stmt,
+}
+
+#[derive(Debug)]
+pub struct Match {
+    pub value: Expr,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Debug)]
+pub struct MatchArm {
+    /// The index of the variant the arm matches, `None` for the wildcard.
+    pub variant: Option<usize>,
+    /// The variables the payload is bound to.
+    pub bindings: Vec<usize>,
+    pub body: Stmt,
+}
+
+/// What an assignment assigns to. The operands are evaluated before the value.
+#[derive(Debug)]
+pub enum Place {
+    Local(usize),
+    Captured(usize),
+    Field(Expr, usize),
+    Index(Expr, Expr, Location),
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Literal(Value),
+    Local(usize),
+    /// A variable of an enclosing function, by its index in the closure of the running lambda.
+    Captured(usize),
+    /// A function used as a value.
+    Function(FunctionId),
+    Builtin(&'static BuiltinFunction),
+    Call(FunctionId, Vec<Expr>, Location),
+    /// Calls a method with the object as its first argument. The object is evaluated after the
+    /// arguments.
+    CallMethod(FunctionId, Box<Expr>, Vec<Expr>, Location),
+    CallBuiltin(&'static BuiltinFunction, Vec<Expr>),
+    /// Calls the closure the first expression evaluates to.
+    CallClosure(Box<Expr>, Vec<Expr>, Location),
+    Intrinsic(Intrinsic, Vec<Expr>, Location),
+    /// A field of a type object, by its index.
+    Field(Box<Expr>, usize),
+    /// The type is the type of the result.
+    Prefix(OpPrefix, Type, Box<Expr>),
+    Infix(Box<Infix>),
+    /// `and` and `or` only evaluate their right side if they have to.
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    /// A new type object, with the index of the field every value is for. The values are
+    /// evaluated in the order they are written in.
+    Struct(Rc<StructInfo>, Vec<(usize, Expr)>),
+    /// A variant of an enum, by its index, with its payload.
+    Variant(Rc<EnumInfo>, usize, Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>, Location),
+    Array(Vec<Expr>),
+    Unwrap(Box<Expr>, Location),
+    /// A lambda, with the values of the variables it captures.
+    Closure(FunctionId, Vec<Expr>),
+}
+
+#[derive(Debug)]
+pub struct Infix {
+    pub lt: Expr,
+    pub op: OpInfix,
+    pub rt: Expr,
+    /// The type of the operands.
+    pub ty: Type,
+    pub location: Location,
+}
@@ -0,0 +1,543 @@
+use std::{collections::HashMap, rc::Rc};
+
+use syntax::{
+    ast::{
+        expr::*,
+        op::OpInfix,
+        stmt::*,
+        ty::{Type, TypedParam},
+        ExprS, Module, Package, StmtS,
+    },
+    builtins::{find_builtin, find_intrinsic, BuiltinFunction},
+};
+use typeck::table::TypeTable;
+
+use crate::{
+    code::{self, FunctionId, Location, Place},
+    value::{EnumInfo, StructInfo, Value},
+};
+
+/// The functions and types of the packages the interpreter loaded.
+#[derive(Debug, Default)]
+pub struct Program {
+    pub functions: Vec<Rc<code::Function>>,
+    /// Functions keyed by module path and name.
+    function_ids: HashMap<(Vec<String>, String), FunctionId>,
+    /// Methods keyed by the name of their type and their name.
+    methods: HashMap<(String, String), FunctionId>,
+    structs: HashMap<String, Rc<StructInfo>>,
+    enums: HashMap<String, Rc<EnumInfo>>,
+    /// The paths of the modules every module imports, keyed by module path and alias.
+    imports: HashMap<Vec<String>, HashMap<String, Vec<String>>>,
+}
+
+impl Program {
+    /// Compiles the declarations of the package that are not loaded yet. Declarations that are
+    /// already loaded keep their code.
+    pub fn load(&mut self, package: &Package, types: &TypeTable) {
+        let mut pending = Vec::new();
+        for module in &package.modules {
+            self.register(module, &mut pending);
+        }
+        for (id, module, fun) in pending {
+            let compiled = FunctionCompiler::new(self, module, types, Vec::new())
+                .compile(&fun.params, &fun.body);
+            self.functions[id.0] = Rc::new(compiled);
+        }
+    }
+
+    /// Compiles the statements of a REPL input to a function of its own. Its parameters are the
+    /// variables of the earlier inputs, which the statements can shadow. Returns the function
+    /// with the slots of the variables that are in scope after the statements, by name.
+    pub fn load_input(
+        &mut self,
+        module: &Module,
+        params: &[TypedParam],
+        stmts: &[StmtS],
+        types: &TypeTable,
+    ) -> (FunctionId, HashMap<String, usize>) {
+        let id = self.add(Self::placeholder());
+        let (compiled, variables) =
+            FunctionCompiler::new(self, module, types, Vec::new()).compile_input(params, stmts);
+        self.functions[id.0] = Rc::new(compiled);
+        (id, variables)
+    }
+
+    /// The function `name` of the module at `path`.
+    pub fn function(&self, path: &[String], name: &str) -> Option<FunctionId> {
+        let key = (path.to_vec(), name.to_string());
+        self.function_ids.get(&key).copied()
+    }
+
+    fn register<'p>(
+        &mut self,
+        module: &'p Module,
+        pending: &mut Vec<(FunctionId, &'p Module, &'p FunctionDeclaration)>,
+    ) {
+        let imports = (module.program.imports())
+            .map(|i| (i.alias().to_string(), i.module_path()))
+            .collect();
+        self.imports.insert(module.path.clone(), imports);
+
+        for decl in &module.program.stmts {
+            match &decl.value {
+                Declaration::Fun(f) => {
+                    let key = (module.path.clone(), f.name.to_string());
+                    if !self.function_ids.contains_key(&key) {
+                        let id = self.add(Self::placeholder());
+                        self.function_ids.insert(key, id);
+                        pending.push((id, module, f));
+                    }
+                }
+                Declaration::Extern(e) => {
+                    let key = (module.path.clone(), e.name.to_string());
+                    if !self.function_ids.contains_key(&key) {
+                        let id = self.add(code::Function::Extern(e.name.to_string()));
+                        self.function_ids.insert(key, id);
+                    }
+                }
+                Declaration::TypeObject(t) => {
+                    let fields = t.members.iter().map(|m| m.name.value.to_string());
+                    let info = StructInfo {
+                        name: t.name.to_string(),
+                        fields: fields.collect(),
+                    };
+                    (self.structs)
+                        .entry(info.name.clone())
+                        .or_insert_with(|| Rc::new(info));
+                }
+                Declaration::Enum(e) => {
+                    let variants = e.variants.iter().map(|v| v.name.value.to_string());
+                    let info = EnumInfo {
+                        name: e.name.to_string(),
+                        variants: variants.collect(),
+                    };
+                    (self.enums)
+                        .entry(info.name.clone())
+                        .or_insert_with(|| Rc::new(info));
+                }
+                Declaration::Impl(i) => {
+                    for method in &i.methods {
+                        let key = (i.name.value.to_string(), method.value.name.to_string());
+                        if !self.methods.contains_key(&key) {
+                            let id = self.add(Self::placeholder());
+                            self.methods.insert(key, id);
+                            pending.push((id, module, &method.value));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add(&mut self, function: code::Function) -> FunctionId {
+        self.functions.push(Rc::new(function));
+        FunctionId(self.functions.len() - 1)
+    }
+
+    /// Stands in for a function until it is compiled, so that functions can call each other.
+    fn placeholder() -> code::Function {
+        code::Function::Pine {
+            variables: 0,
+            body: Vec::new(),
+        }
+    }
+
+    /// The type object a value of type `ty` is, also after it was narrowed from an optional.
+    fn struct_info(&self, ty: &Type) -> &Rc<StructInfo> {
+        &self.structs[Self::type_name(ty)]
+    }
+
+    fn type_name(ty: &Type) -> &str {
+        match ty {
+            Type::Struct(name) | Type::Generic(name, _) => name,
+            Type::Optional(ty) => Self::type_name(ty),
+            _ => unreachable!("Only type objects and enums have fields and methods"),
+        }
+    }
+}
+
+/// Compiles a function or lambda. Every variable gets a slot of its own in the frame of the
+/// function, also the ones that shadow another variable.
+struct FunctionCompiler<'c, 'p> {
+    program: &'c mut Program,
+    module: &'p Module,
+    types: &'p TypeTable,
+    scopes: Vec<HashMap<&'p str, usize>>,
+    variables: usize,
+    /// The names of the variables the lambda captures, in the order of its closure.
+    captures: Vec<&'p str>,
+}
+
+impl<'c, 'p> FunctionCompiler<'c, 'p> {
+    fn new(
+        program: &'c mut Program,
+        module: &'p Module,
+        types: &'p TypeTable,
+        captures: Vec<&'p str>,
+    ) -> Self {
+        Self {
+            program,
+            module,
+            types,
+            scopes: Vec::new(),
+            variables: 0,
+            captures,
+        }
+    }
+
+    fn compile(mut self, params: &'p [TypedParam], body: &'p Block) -> code::Function {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.define(param.name.value.as_ref());
+        }
+        let body = self.block(body);
+        code::Function::Pine {
+            variables: self.variables,
+            body,
+        }
+    }
+
+    /// Like `compile`, but the statements are in a scope of their own, whose variables are
+    /// returned with the parameters they don't shadow.
+    fn compile_input(
+        mut self,
+        params: &'p [TypedParam],
+        stmts: &'p [StmtS],
+    ) -> (code::Function, HashMap<String, usize>) {
+        self.scopes.push(HashMap::new());
+        for param in params {
+            self.define(param.name.value.as_ref());
+        }
+        self.scopes.push(HashMap::new());
+        let body = stmts.iter().map(|stmt| self.stmt(stmt)).collect();
+        // the inner scope comes last, so its variables replace the parameters
+        let variables = (self.scopes.iter().flatten())
+            .map(|(name, slot)| (name.to_string(), *slot))
+            .collect();
+        let function = code::Function::Pine {
+            variables: self.variables,
+            body,
+        };
+        (function, variables)
+    }
+
+    fn block(&mut self, block: &'p Block) -> Vec<code::Stmt> {
+        self.scopes.push(HashMap::new());
+        let stmts = block.stmts.iter().map(|stmt| self.stmt(stmt)).collect();
+        self.scopes.pop();
+        stmts
+    }
+
+    /// A statement in a scope of its own, like the branches of an if.
+    fn body(&mut self, stmt: &'p StmtS) -> code::Stmt {
+        self.scopes.push(HashMap::new());
+        let stmt = self.stmt(stmt);
+        self.scopes.pop();
+        stmt
+    }
+
+    fn stmt(&mut self, stmt: &'p StmtS) -> code::Stmt {
+        match &stmt.value {
+            Stmt::Block(block) => code::Stmt::Block(self.block(block)),
+            Stmt::Expr(e) => code::Stmt::Expr(self.expr(&e.value)),
+            Stmt::If(if_stmt) => code::Stmt::If(
+                self.expr(&if_stmt.cond),
+                Box::new(self.body(&if_stmt.then)),
+                (if_stmt.else_.as_ref()).map(|else_| Box::new(self.body(else_))),
+            ),
+            Stmt::Return(r) => code::Stmt::Return(r.value.as_ref().map(|value| self.expr(value))),
+            Stmt::Break(_) => code::Stmt::Break,
+            Stmt::Continue(_) => code::Stmt::Continue,
+            Stmt::VariableDeclaration(let_stmt) => {
+                // the value can refer to the variable it shadows
+                let value = self.expr(&let_stmt.value);
+                code::Stmt::Let(self.define(let_stmt.var.as_ref()), value)
+            }
+            Stmt::Loop(loop_stmt) => code::Stmt::Loop(Box::new(self.body(&loop_stmt.body))),
+            Stmt::While(while_stmt) => code::Stmt::While(
+                self.expr(&while_stmt.cond),
+                Box::new(self.body(&while_stmt.body)),
+            ),
+            Stmt::For(for_stmt) => {
+                let ty = self.ty(&for_stmt.start).clone();
+                let start = self.expr(&for_stmt.start);
+                let end = self.expr(&for_stmt.end);
+                self.scopes.push(HashMap::new());
+                let var = self.define(for_stmt.var.value.as_ref());
+                let body = self.body(&for_stmt.body);
+                self.scopes.pop();
+                code::Stmt::For(Box::new(code::For {
+                    var,
+                    ty,
+                    start,
+                    end,
+                    body,
+                }))
+            }
+            Stmt::Match(match_stmt) => {
+                let value = self.expr(&match_stmt.value);
+                let arms = (match_stmt.arms.iter())
+                    .map(|arm| self.match_arm(arm))
+                    .collect();
+                code::Stmt::Match(Box::new(code::Match { value, arms }))
+            }
+            Stmt::Empty(_) => code::Stmt::Block(Vec::new()),
+            Stmt::Assign(assign) => {
+                let place = self.place(&assign.var);
+                code::Stmt::Assign(Box::new(place), self.expr(&assign.value))
+            }
+            Stmt::Error => unreachable!("Programs with syntax errors are not run"),
+        }
+    }
+
+    fn match_arm(&mut self, arm: &'p MatchArm) -> code::MatchArm {
+        self.scopes.push(HashMap::new());
+        let (variant, bindings) = match &arm.pattern.value {
+            Pattern::Wildcard => (None, Vec::new()),
+            Pattern::Variant(p) => {
+                let variants = &self.program.enums[p.enum_name.as_ref()].variants;
+                let variant = variants.iter().position(|v| *v == p.variant.as_ref());
+                let bindings = p.bindings.iter().map(|b| self.define(b.value.as_ref()));
+                (variant, bindings.collect())
+            }
+        };
+        let body = self.body(&arm.body);
+        self.scopes.pop();
+        code::MatchArm {
+            variant,
+            bindings,
+            body,
+        }
+    }
+
+    fn place(&mut self, target: &'p ExprS) -> Place {
+        match &target.value {
+            Expr::Var(var) => match self.types.is_captured(target) {
+                true => Place::Captured(self.captured(var.var.as_ref())),
+                false => Place::Local(self.lookup(var.var.as_ref())),
+            },
+            Expr::MemberAccess(access) => {
+                let field = self.field(&access.object, access.member_name.as_ref());
+                Place::Field(self.expr(&access.object), field)
+            }
+            Expr::Index(index) => Place::Index(
+                self.expr(&index.object),
+                self.expr(&index.index),
+                Self::location(&index.index),
+            ),
+            _ => unreachable!("Only variables, members and elements are assigned"),
+        }
+    }
+
+    fn expr(&mut self, expr: &'p ExprS) -> code::Expr {
+        match &expr.value {
+            Expr::Var(var) => self.var(expr, var.var.as_ref()),
+            Expr::Literal(literal) => code::Expr::Literal(match literal {
+                ExprLiteral::Bool(b) => Value::Bool(*b),
+                ExprLiteral::Nil => Value::Nil,
+                ExprLiteral::Integer(i) => Value::Int(*i),
+                ExprLiteral::Float(f) => Value::Float(*f),
+                ExprLiteral::String(s) => Value::Str(s.as_str().into()),
+            }),
+            Expr::Call(call) => self.call(expr, call),
+            Expr::MemberAccess(access) => match self.types.function_ref(expr) {
+                Some(path) => self.function_value(path, access.member_name.as_ref()),
+                None => {
+                    let field = self.field(&access.object, access.member_name.as_ref());
+                    code::Expr::Field(Box::new(self.expr(&access.object)), field)
+                }
+            },
+            Expr::Prefix(prefix) => {
+                let ty = self.ty(expr).clone();
+                code::Expr::Prefix(prefix.op, ty, Box::new(self.expr(&prefix.rt)))
+            }
+            Expr::Infix(infix) => {
+                let lt = self.expr(&infix.lt);
+                let rt = self.expr(&infix.rt);
+                match infix.op {
+                    OpInfix::LogicAnd => code::Expr::And(Box::new(lt), Box::new(rt)),
+                    OpInfix::LogicOr => code::Expr::Or(Box::new(lt), Box::new(rt)),
+                    op => code::Expr::Infix(Box::new(code::Infix {
+                        lt,
+                        op,
+                        rt,
+                        ty: self.ty(&infix.lt).clone(),
+                        location: Self::location(expr),
+                    })),
+                }
+            }
+            Expr::StructureInit(si) => {
+                let info = self.program.structs[si.name.as_ref()].clone();
+                let values = (si.members.iter())
+                    .map(|(name, value)| {
+                        let field = (info.fields.iter())
+                            .position(|f| f == name.as_ref())
+                            .expect("Type objects are created with their fields");
+                        (field, self.expr(value))
+                    })
+                    .collect();
+                code::Expr::Struct(info, values)
+            }
+            Expr::EnumVariant(variant) => self.variant(variant, Vec::new()),
+            Expr::Index(index) => code::Expr::Index(
+                Box::new(self.expr(&index.object)),
+                Box::new(self.expr(&index.index)),
+                Self::location(&index.index),
+            ),
+            Expr::Array(array) => code::Expr::Array(self.exprs(&array.elements)),
+            Expr::Unwrap(unwrap) => {
+                code::Expr::Unwrap(Box::new(self.expr(&unwrap.value)), Self::location(expr))
+            }
+            Expr::Lambda(lambda) => self.lambda(expr, lambda),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &'p [ExprS]) -> Vec<code::Expr> {
+        exprs.iter().map(|expr| self.expr(expr)).collect()
+    }
+
+    /// Captured variables are read from the closure of the running lambda, and functions used
+    /// as values become closures.
+    fn var(&mut self, expr: &'p ExprS, name: &'p str) -> code::Expr {
+        if self.types.is_captured(expr) {
+            return code::Expr::Captured(self.captured(name));
+        }
+        if let Some(path) = self.types.function_ref(expr) {
+            return self.function_value(path, name);
+        }
+        code::Expr::Local(self.lookup(name))
+    }
+
+    /// The lambda becomes a function of its own, which reads the variables it captures from its
+    /// closure. They are copied into the closure when it is created.
+    fn lambda(&mut self, expr: &'p ExprS, lambda: &'p ExprLambda) -> code::Expr {
+        let captures = self.types.captures(expr);
+        let values = (captures.iter())
+            .map(|c| match c.from_env {
+                true => code::Expr::Captured(self.captured(&c.name)),
+                false => code::Expr::Local(self.lookup(&c.name)),
+            })
+            .collect();
+        let names = captures.iter().map(|c| c.name.as_str()).collect();
+
+        let id = self.program.add(Program::placeholder());
+        let compiled = FunctionCompiler::new(self.program, self.module, self.types, names)
+            .compile(&lambda.params, &lambda.body);
+        self.program.functions[id.0] = Rc::new(compiled);
+        code::Expr::Closure(id, values)
+    }
+
+    fn call(&mut self, expr: &'p ExprS, call: &'p ExprCall) -> code::Expr {
+        // only callees that are values have a type, named functions and methods don't
+        if self.types.expr_ty(&call.callee).is_some() {
+            let callee = Box::new(self.expr(&call.callee));
+            return code::Expr::CallClosure(callee, self.exprs(&call.args), Self::location(expr));
+        }
+        if let Expr::Var(callee) = &call.callee.value {
+            if let Some(intrinsic) = find_intrinsic(callee.var.as_ref()) {
+                let args = self.exprs(&call.args);
+                return code::Expr::Intrinsic(intrinsic, args, Self::location(expr));
+            }
+        }
+        let args = self.exprs(&call.args);
+        match &call.callee.value {
+            Expr::EnumVariant(variant) => self.variant(variant, args),
+            Expr::MemberAccess(access) => {
+                if let Expr::Var(module) = &access.object.value {
+                    let imports = &self.program.imports[&self.module.path];
+                    if let Some(path) = imports.get(module.var.as_ref()).cloned() {
+                        return self.named_call(expr, &path, access.member_name.as_ref(), args);
+                    }
+                }
+                let ty = Program::type_name(self.ty(&access.object));
+                let key = (ty.to_string(), access.member_name.to_string());
+                let method = self.program.methods[&key];
+                let object = Box::new(self.expr(&access.object));
+                code::Expr::CallMethod(method, object, args, Self::location(expr))
+            }
+            Expr::Var(f) => {
+                let path = &self.module.path;
+                self.named_call(expr, path, f.var.as_ref(), args)
+            }
+            _ => unreachable!("Other callees are values"),
+        }
+    }
+
+    /// Calls the function `name` of the module at `path`, or the builtin `name`.
+    fn named_call(
+        &self,
+        expr: &ExprS,
+        path: &[String],
+        name: &str,
+        args: Vec<code::Expr>,
+    ) -> code::Expr {
+        match self.program.function(path, name) {
+            Some(id) => code::Expr::Call(id, args, Self::location(expr)),
+            None => code::Expr::CallBuiltin(Self::builtin(name), args),
+        }
+    }
+
+    /// A closure that calls the function `name` of the module at `path`.
+    fn function_value(&self, path: &[String], name: &str) -> code::Expr {
+        match self.program.function(path, name) {
+            Some(id) => code::Expr::Function(id),
+            None => code::Expr::Builtin(Self::builtin(name)),
+        }
+    }
+
+    fn builtin(name: &str) -> &'static BuiltinFunction {
+        find_builtin(name).expect("Functions are declared or builtins")
+    }
+
+    fn variant(&self, variant: &ExprEnumVariant, payload: Vec<code::Expr>) -> code::Expr {
+        let info = self.program.enums[variant.enum_name.as_ref()].clone();
+        let index = (info.variants.iter())
+            .position(|v| *v == variant.variant.as_ref())
+            .expect("Variants are declared by their enum");
+        code::Expr::Variant(info, index, payload)
+    }
+
+    /// The index of the field `name` of the type object `object` evaluates to.
+    fn field(&self, object: &ExprS, name: &str) -> usize {
+        let info = self.program.struct_info(self.ty(object));
+        (info.fields.iter())
+            .position(|f| f == name)
+            .expect("Fields are declared by their type object")
+    }
+
+    fn define(&mut self, name: &'p str) -> usize {
+        let slot = self.variables;
+        self.variables += 1;
+        let scope = self.scopes.last_mut().expect("No scope to define in");
+        scope.insert(name, slot);
+        slot
+    }
+
+    fn lookup(&self, name: &str) -> usize {
+        (self.scopes.iter().rev())
+            .find_map(|scope| scope.get(name))
+            .copied()
+            .expect("Variables are defined before they are used")
+    }
+
+    fn captured(&self, name: &str) -> usize {
+        (self.captures.iter())
+            .position(|c| *c == name)
+            .expect("Lambdas capture the variables they use")
+    }
+
+    fn ty(&self, expr: &ExprS) -> &'p Type {
+        self.types
+            .expr_ty(expr)
+            .expect("Expression was not typechecked")
+    }
+
+    fn location(expr: &ExprS) -> Location {
+        Location {
+            source: expr.source,
+            offset: expr.span.start,
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt::{self, Display, Formatter};
+
+use base::file_cache::FileCache;
+
+use crate::code::Location;
+
+/// An error that aborts the interpreted program, like unwrapping `nil` or indexing out of
+/// bounds. It is reported in the same format as the errors of the C runtime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    /// The position in the Pine source, as `path:line:column`.
+    pub location: String,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub fn new<S: Into<String>>(location: String, message: S) -> Self {
+        Self {
+            location,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// A runtime error while the program runs. Its location is looked up in the source once it
+/// reaches the caller of the interpreter.
+#[derive(Debug)]
+pub(crate) struct Abort {
+    location: Location,
+    message: String,
+}
+
+impl Abort {
+    pub fn new<S: Into<String>>(location: Location, message: S) -> Self {
+        Self {
+            location,
+            message: message.into(),
+        }
+    }
+
+    pub fn locate(self, files: &FileCache) -> RuntimeError {
+        let Location { source, offset } = self.location;
+        let (line, column) = files
+            .line_column(source, offset)
+            .expect("Source files are loaded before they are run");
+        let location = format!("{}:{}:{}", source.to_path().display(), line, column);
+        RuntimeError::new(location, self.message)
+    }
+}
@@ -0,0 +1,762 @@
+use crate::{
+    code::{self, Expr, Function, FunctionId, Location, Place, Stmt},
+    compile::Program,
+    error::{Abort, RuntimeError},
+    value::{Callable, Closure, Heap, Object, ObjectId, Value},
+};
+use base::file_cache::FileCache;
+use std::{cmp::Ordering, collections::HashMap, io::Write, rc::Rc};
+use syntax::ast::{
+    op::{OpInfix, OpPrefix},
+    ty::{Type, TypedParam},
+    Module, Package, StmtS,
+};
+use syntax::builtins::{BuiltinFunction, Intrinsic};
+use typeck::table::TypeTable;
+
+type Result<T> = std::result::Result<T, Abort>;
+
+/// How a statement completes.
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+/// A running function.
+struct Frame {
+    /// The index of its first variable in the variables of the interpreter.
+    base: usize,
+    /// The closure of the running lambda, which holds the captured variables.
+    closure: Option<ObjectId>,
+}
+
+/// Runs a type checked package without compiling it to C.
+///
+/// The functions of the package are compiled from their AST to code whose variables are slots
+/// of a frame, see [`code`]. The interpreter follows the semantics of the C backend, so that
+/// both print the same output for the same program. Extern functions can't be called, since
+/// there is no C code to call.
+pub struct Interpreter<'a> {
+    // The output of the print builtins.
+    out: Box<dyn Write + 'a>,
+    program: Program,
+    heap: Heap,
+    // The variables of every running function, each frame after the one of its caller.
+    variables: Vec<Value>,
+    frames: Vec<Frame>,
+    // The values that are held while further expressions are evaluated, like the arguments of
+    // a call. The collector sees them next to the variables of the frames.
+    temps: Vec<Value>,
+}
+
+impl<'a> Interpreter<'a> {
+    /// Runs the `main` function of the root module.
+    pub fn run(
+        package: &Package,
+        types: &TypeTable,
+        files: &FileCache,
+        out: &'a mut dyn Write,
+    ) -> std::result::Result<(), RuntimeError> {
+        let mut interpreter = Self::new(Box::new(out));
+        interpreter.load(package, types);
+        interpreter.run_main(package, files)?;
+        Ok(())
+    }
+
+    pub fn new(out: Box<dyn Write + 'a>) -> Self {
+        Self {
+            out,
+            program: Program::default(),
+            heap: Heap::new(),
+            variables: Vec::new(),
+            frames: Vec::new(),
+            temps: Vec::new(),
+        }
+    }
+
+    /// Compiles the functions of the package that are not loaded yet.
+    pub fn load(&mut self, package: &Package, types: &TypeTable) {
+        self.program.load(package, types);
+    }
+
+    /// Runs the `main` function of the root module of the loaded package and returns what it
+    /// returns.
+    pub fn run_main(
+        &mut self,
+        package: &Package,
+        files: &FileCache,
+    ) -> std::result::Result<Value, RuntimeError> {
+        let root = (package.modules.last()).expect("The root module is always loaded");
+        let Some(main) = self.program.function(&root.path, "main") else {
+            let location = root.source.to_path().display().to_string();
+            return Err(RuntimeError::new(location, "no `main` function to run"));
+        };
+        let location = Location {
+            source: root.source,
+            offset: 0,
+        };
+        let base = self.variables.len();
+        (self.call(main, base, None, location)).map_err(|abort| abort.locate(files))
+    }
+
+    /// Runs the statements of a REPL input with `args` as the values of the variables of the
+    /// earlier inputs, see [`Program::load_input`]. Returns what the statements return and the
+    /// values of the variables that are in scope after them, by name.
+    pub fn run_input(
+        &mut self,
+        module: &Module,
+        params: &[TypedParam],
+        stmts: &[StmtS],
+        types: &TypeTable,
+        files: &FileCache,
+        args: Vec<Value>,
+    ) -> std::result::Result<(Value, HashMap<String, Value>), RuntimeError> {
+        let (id, slots) = self.program.load_input(module, params, stmts, types);
+        let function = self.program.functions[id.0].clone();
+        let Function::Pine { variables, body } = function.as_ref() else {
+            unreachable!("Inputs are compiled to Pine functions")
+        };
+        let base = self.variables.len();
+        self.variables.extend(args);
+        let flow = self.run_frame(body, base, *variables, None);
+        let value = match flow {
+            Ok(Flow::Return(value)) => Ok(value),
+            Ok(_) => Ok(Value::Unit),
+            Err(abort) => Err(abort.locate(files)),
+        };
+        let values = (slots.into_iter())
+            .map(|(name, slot)| (name, self.variables[base + slot].clone()))
+            .collect();
+        self.variables.truncate(base);
+        Ok((value?, values))
+    }
+
+    /// Formats a value the way it is written in Pine, like `Point { x: 1, y: 2 }`. Objects
+    /// that contain themselves are cut short with `..`.
+    pub fn display(&self, value: &Value) -> String {
+        let mut out = String::new();
+        self.write_value(&mut out, value, &mut Vec::new());
+        out
+    }
+
+    fn write_value(&self, out: &mut String, value: &Value, parents: &mut Vec<ObjectId>) {
+        match value {
+            Value::Unit => out.push_str("()"),
+            Value::Bool(b) => out.push_str(&b.to_string()),
+            Value::Int(i) => out.push_str(&i.to_string()),
+            Value::Float(f) => out.push_str(&f.to_string()),
+            Value::Str(s) => out.push_str(&format!("{:?}", s)),
+            Value::Nil => out.push_str("nil"),
+            Value::Object(id) if parents.contains(id) => out.push_str(".."),
+            Value::Object(id) => {
+                parents.push(*id);
+                match self.heap.get(*id) {
+                    Object::Struct { ty, fields } => {
+                        out.push_str(&format!("{} {{ ", ty.name));
+                        for (i, (name, value)) in ty.fields.iter().zip(fields).enumerate() {
+                            if i > 0 {
+                                out.push_str(", ");
+                            }
+                            out.push_str(&format!("{}: ", name));
+                            self.write_value(out, value, parents);
+                        }
+                        out.push_str(" }");
+                    }
+                    Object::Variant {
+                        ty,
+                        variant,
+                        payload,
+                    } => {
+                        out.push_str(&format!("{}::{}", ty.name, ty.variants[*variant]));
+                        if !payload.is_empty() {
+                            out.push('(');
+                            self.write_values(out, payload, parents);
+                            out.push(')');
+                        }
+                    }
+                    Object::Array(elements) => {
+                        out.push('[');
+                        self.write_values(out, elements, parents);
+                        out.push(']');
+                    }
+                    Object::Closure(_) => out.push_str("<fun>"),
+                }
+                parents.pop();
+            }
+        }
+    }
+
+    fn write_values(&self, out: &mut String, values: &[Value], parents: &mut Vec<ObjectId>) {
+        for (i, value) in values.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            self.write_value(out, value, parents);
+        }
+    }
+
+    /// Calls a function whose arguments were pushed to the variables from `base` on, so they
+    /// are its first variables. `location` is where an extern function is called, which fails.
+    fn call(
+        &mut self,
+        id: FunctionId,
+        base: usize,
+        closure: Option<ObjectId>,
+        location: Location,
+    ) -> Result<Value> {
+        let function = self.program.functions[id.0].clone();
+        let (variables, body) = match function.as_ref() {
+            Function::Pine { variables, body } => (*variables, body),
+            Function::Extern(name) => {
+                self.variables.truncate(base);
+                let message = format!("extern function `{}` can't be interpreted", name);
+                return Err(Abort::new(location, message));
+            }
+        };
+        let flow = self.run_frame(body, base, variables, closure);
+        self.variables.truncate(base);
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Unit),
+        }
+    }
+
+    /// Runs a function body in a new frame whose arguments were pushed from `base` on. The
+    /// variables of the frame are left for the caller to remove.
+    fn run_frame(
+        &mut self,
+        body: &[Stmt],
+        base: usize,
+        variables: usize,
+        closure: Option<ObjectId>,
+    ) -> Result<Flow> {
+        self.variables.resize(base + variables, Value::Unit);
+        self.frames.push(Frame { base, closure });
+        let flow = self.exec_stmts(body);
+        self.frames.pop();
+        flow
+    }
+
+    /// Evaluates the arguments of a call onto the variables, where the frame of the called
+    /// function starts. Returns where they start.
+    fn push_args(&mut self, args: &[Expr]) -> Result<usize> {
+        let base = self.variables.len();
+        for arg in args {
+            match self.eval(arg) {
+                Ok(value) => self.variables.push(value),
+                Err(err) => {
+                    self.variables.truncate(base);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(base)
+    }
+
+    fn call_closure(&mut self, callee: &Expr, args: &[Expr], location: Location) -> Result<Value> {
+        let closure = self.eval(callee)?;
+        let held = self.temps.len();
+        self.temps.push(closure);
+        let base = self.push_args(args);
+        let closure = self.temps.swap_remove(held).as_object();
+        let base = base?;
+
+        let Object::Closure(c) = self.heap.get(closure) else {
+            unreachable!("Only functions are called")
+        };
+        match c.callable {
+            Callable::Function(id) => self.call(id, base, Some(closure), location),
+            Callable::Builtin(builtin) => {
+                let args = self.variables.split_off(base);
+                Ok(self.call_builtin(builtin, args))
+            }
+        }
+    }
+
+    fn call_builtin(&mut self, builtin: &BuiltinFunction, args: Vec<Value>) -> Value {
+        let written = match builtin.name {
+            "print_int" => writeln!(self.out, "{}", args[0].as_int()),
+            "print_bool" => writeln!(self.out, "{}", args[0].as_bool()),
+            "print_str" => writeln!(self.out, "{}", args[0].as_str()),
+            "str_len" => return Value::Int(args[0].as_str().len() as i64),
+            name => unreachable!("Unknown builtin `{}`", name),
+        };
+        written.expect("Failed to write the output of the program");
+        Value::Unit
+    }
+
+    fn exec_stmts(&mut self, stmts: &[Stmt]) -> Result<Flow> {
+        for stmt in stmts {
+            match self.exec_stmt(stmt)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Runs the body of a loop. Returns how the loop completes if the body leaves it.
+    fn exec_loop_body(&mut self, body: &Stmt) -> Result<Option<Flow>> {
+        Ok(match self.exec_stmt(body)? {
+            Flow::Next | Flow::Continue => None,
+            Flow::Break => Some(Flow::Next),
+            flow @ Flow::Return(_) => Some(flow),
+        })
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<Flow> {
+        match stmt {
+            Stmt::Block(stmts) => self.exec_stmts(stmts),
+            Stmt::Expr(e) => {
+                self.eval(e)?;
+                Ok(Flow::Next)
+            }
+            Stmt::If(cond, then, else_) => {
+                if self.eval(cond)?.as_bool() {
+                    self.exec_stmt(then)
+                } else if let Some(else_) = else_ {
+                    self.exec_stmt(else_)
+                } else {
+                    Ok(Flow::Next)
+                }
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => Value::Unit,
+                };
+                Ok(Flow::Return(value))
+            }
+            Stmt::Break => Ok(Flow::Break),
+            Stmt::Continue => Ok(Flow::Continue),
+            Stmt::Let(var, value) => {
+                let value = self.eval(value)?;
+                self.set(*var, value);
+                Ok(Flow::Next)
+            }
+            Stmt::Loop(body) => loop {
+                if let Some(flow) = self.exec_loop_body(body)? {
+                    return Ok(flow);
+                }
+            },
+            Stmt::While(cond, body) => {
+                while self.eval(cond)?.as_bool() {
+                    if let Some(flow) = self.exec_loop_body(body)? {
+                        return Ok(flow);
+                    }
+                }
+                Ok(Flow::Next)
+            }
+            Stmt::For(for_stmt) => self.exec_for(for_stmt),
+            Stmt::Match(match_stmt) => self.exec_match(match_stmt),
+            Stmt::Assign(place, value) => {
+                self.assign(place, value)?;
+                Ok(Flow::Next)
+            }
+        }
+    }
+
+    fn exec_for(&mut self, for_stmt: &code::For) -> Result<Flow> {
+        let ty = &for_stmt.ty;
+        let start = self.eval(&for_stmt.start)?;
+        let end = self.eval(&for_stmt.end)?.as_int();
+        self.set(for_stmt.var, start);
+        loop {
+            let i = self.get(for_stmt.var).as_int();
+            if !Self::compare_ints(ty, i, end).is_lt() {
+                return Ok(Flow::Next);
+            }
+            if let Some(flow) = self.exec_loop_body(&for_stmt.body)? {
+                return Ok(flow);
+            }
+            let i = self.get(for_stmt.var).as_int();
+            self.set(for_stmt.var, Value::Int(wrap(ty, i.wrapping_add(1))));
+        }
+    }
+
+    fn exec_match(&mut self, match_stmt: &code::Match) -> Result<Flow> {
+        let value = self.eval(&match_stmt.value)?.as_object();
+        let Object::Variant {
+            variant, payload, ..
+        } = self.heap.get(value)
+        else {
+            unreachable!("Only enums are matched")
+        };
+        let (variant, payload) = (*variant, payload.clone());
+
+        for arm in &match_stmt.arms {
+            if arm.variant.is_some_and(|v| v != variant) {
+                continue;
+            }
+            for (var, value) in arm.bindings.iter().zip(payload) {
+                self.set(*var, value);
+            }
+            return self.exec_stmt(&arm.body);
+        }
+        Ok(Flow::Next)
+    }
+
+    fn assign(&mut self, place: &Place, value: &Expr) -> Result<()> {
+        match place {
+            Place::Local(var) => {
+                let value = self.eval(value)?;
+                self.set(*var, value);
+            }
+            Place::Captured(index) => {
+                let value = self.eval(value)?;
+                *self.captured_mut(*index) = value;
+            }
+            Place::Field(object, field) => {
+                let [object, value] = self.eval_values([object, value])?;
+                self.fields_mut(object.as_object())[*field] = value;
+            }
+            Place::Index(object, index, location) => {
+                let [object, i, value] = self.eval_values([object, index, value])?;
+                let object = object.as_object();
+                let i = self.check_index(*location, object, i.as_int())?;
+                self.elements_mut(object)[i] = value;
+            }
+        }
+        Ok(())
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        match expr {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Local(var) => Ok(self.get(*var)),
+            Expr::Captured(index) => Ok(self.captured_mut(*index).clone()),
+            Expr::Function(id) => Ok(self.closure(Callable::Function(*id), Vec::new())),
+            Expr::Builtin(builtin) => Ok(self.closure(Callable::Builtin(builtin), Vec::new())),
+            Expr::Call(id, args, location) => {
+                let base = self.push_args(args)?;
+                self.call(*id, base, None, *location)
+            }
+            Expr::CallMethod(id, object, args, location) => {
+                let base = self.push_args(args)?;
+                let object = match self.eval(object) {
+                    Ok(object) => object,
+                    Err(err) => {
+                        self.variables.truncate(base);
+                        return Err(err);
+                    }
+                };
+                // the object is the first argument
+                self.variables.insert(base, object);
+                self.call(*id, base, None, *location)
+            }
+            Expr::CallBuiltin(builtin, args) => {
+                let args = self.eval_args(args)?;
+                Ok(self.call_builtin(builtin, args))
+            }
+            Expr::CallClosure(callee, args, location) => self.call_closure(callee, args, *location),
+            Expr::Intrinsic(intrinsic, args, location) => {
+                self.eval_intrinsic(*intrinsic, args, *location)
+            }
+            Expr::Field(object, field) => {
+                let object = self.eval(object)?.as_object();
+                Ok(self.fields(object)[*field].clone())
+            }
+            Expr::Prefix(op, ty, rt) => {
+                let value = self.eval(rt)?;
+                Ok(match (op, value) {
+                    (OpPrefix::Negate, Value::Int(i)) => Value::Int(wrap(ty, i.wrapping_neg())),
+                    (OpPrefix::Negate, Value::Float(f)) => Value::Float(-f),
+                    (OpPrefix::Not, Value::Bool(b)) => Value::Bool(!b),
+                    (op, _) => unreachable!("The type checker does not allow `{}` here", op),
+                })
+            }
+            Expr::Infix(infix) => self.eval_infix(infix),
+            Expr::And(lt, rt) => match self.eval(lt)?.as_bool() {
+                false => Ok(Value::Bool(false)),
+                true => self.eval(rt),
+            },
+            Expr::Or(lt, rt) => match self.eval(lt)?.as_bool() {
+                true => Ok(Value::Bool(true)),
+                false => self.eval(rt),
+            },
+            Expr::Struct(ty, values) => {
+                let evaluated = self.eval_args(values.iter().map(|(_, value)| value))?;
+                let mut fields = vec![Value::Unit; ty.fields.len()];
+                for ((field, _), value) in values.iter().zip(evaluated) {
+                    fields[*field] = value;
+                }
+                let ty = ty.clone();
+                Ok(self.alloc(Object::Struct { ty, fields }))
+            }
+            Expr::Variant(ty, variant, payload) => {
+                let payload = self.eval_args(payload)?;
+                Ok(self.alloc(Object::Variant {
+                    ty: ty.clone(),
+                    variant: *variant,
+                    payload,
+                }))
+            }
+            Expr::Index(object, index, location) => {
+                let [object, i] = self.eval_values([object, index])?;
+                let object = object.as_object();
+                let i = self.check_index(*location, object, i.as_int())?;
+                Ok(self.elements(object)[i].clone())
+            }
+            Expr::Array(elements) => {
+                let elements = self.eval_args(elements)?;
+                Ok(self.alloc(Object::Array(elements)))
+            }
+            Expr::Unwrap(value, location) => match self.eval(value)? {
+                Value::Nil => Err(Abort::new(*location, "unwrapped a nil value")),
+                value => Ok(value),
+            },
+            Expr::Closure(id, captures) => {
+                let env = self.eval_args(captures)?;
+                Ok(self.closure(Callable::Function(*id), env))
+            }
+        }
+    }
+
+    /// Evaluates the expressions in order. The values of the evaluated ones are held, so they
+    /// survive a collection while the others are evaluated.
+    fn eval_args<'e>(&mut self, args: impl IntoIterator<Item = &'e Expr>) -> Result<Vec<Value>> {
+        let held = self.temps.len();
+        for arg in args {
+            match self.eval(arg) {
+                Ok(value) => self.temps.push(value),
+                Err(err) => {
+                    self.temps.truncate(held);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(self.temps.split_off(held))
+    }
+
+    /// Like `eval_args`, for a few expressions whose values don't need a `Vec`.
+    fn eval_values<const N: usize>(&mut self, exprs: [&Expr; N]) -> Result<[Value; N]> {
+        let held = self.temps.len();
+        for expr in exprs {
+            match self.eval(expr) {
+                Ok(value) => self.temps.push(value),
+                Err(err) => {
+                    self.temps.truncate(held);
+                    return Err(err);
+                }
+            }
+        }
+        let values =
+            std::array::from_fn(|i| std::mem::replace(&mut self.temps[held + i], Value::Unit));
+        self.temps.truncate(held);
+        Ok(values)
+    }
+
+    fn eval_intrinsic(
+        &mut self,
+        intrinsic: Intrinsic,
+        args: &[Expr],
+        location: Location,
+    ) -> Result<Value> {
+        match intrinsic {
+            Intrinsic::Len => {
+                let collection = self.eval(&args[0])?.as_object();
+                Ok(Value::Int(self.elements(collection).len() as i64))
+            }
+            Intrinsic::Push => {
+                let [collection, value] = self.eval_values([&args[0], &args[1]])?;
+                self.elements_mut(collection.as_object()).push(value);
+                Ok(Value::Unit)
+            }
+            Intrinsic::Pop => {
+                let collection = self.eval(&args[0])?.as_object();
+                match self.elements_mut(collection).pop() {
+                    Some(value) => Ok(value),
+                    None => Err(Abort::new(location, "pop from an empty list")),
+                }
+            }
+        }
+    }
+
+    fn eval_infix(&mut self, infix: &code::Infix) -> Result<Value> {
+        let [left, right] = self.eval_values([&infix.lt, &infix.rt])?;
+        let ty = &infix.ty;
+        match (left, right) {
+            (Value::Int(a), Value::Int(b)) => Self::int_infix(infix, a, b),
+            (Value::Float(a), Value::Float(b)) => Ok(Self::float_infix(infix.op, ty, a, b)),
+            // optional strings are compared like pointers
+            (Value::Str(a), Value::Str(b)) if !matches!(ty, Type::Optional(_)) => {
+                Ok(Self::str_infix(infix.op, &a, &b))
+            }
+            (left, right) => match infix.op {
+                OpInfix::Equal => Ok(Value::Bool(left.is_identical(&right))),
+                OpInfix::NotEqual => Ok(Value::Bool(!left.is_identical(&right))),
+                op => unreachable!("The type checker does not allow `{}` here", op),
+            },
+        }
+    }
+
+    fn int_infix(infix: &code::Infix, a: i64, b: i64) -> Result<Value> {
+        let ty = &infix.ty;
+        let unsigned = *ty == Type::U64;
+        let value = match infix.op {
+            OpInfix::Add => a.wrapping_add(b),
+            OpInfix::Subtract => a.wrapping_sub(b),
+            OpInfix::Multiply => a.wrapping_mul(b),
+            OpInfix::Divide | OpInfix::Modulo if b == 0 => {
+                return Err(Abort::new(infix.location, "division by zero"));
+            }
+            OpInfix::Divide if unsigned => ((a as u64) / (b as u64)) as i64,
+            OpInfix::Divide => a.wrapping_div(b),
+            OpInfix::Modulo if unsigned => ((a as u64) % (b as u64)) as i64,
+            OpInfix::Modulo => a.wrapping_rem(b),
+            op => return Ok(Value::Bool(Self::compare(op, Self::compare_ints(ty, a, b)))),
+        };
+        Ok(Value::Int(wrap(ty, value)))
+    }
+
+    fn float_infix(op: OpInfix, ty: &Type, a: f64, b: f64) -> Value {
+        let value = match op {
+            OpInfix::Add => a + b,
+            OpInfix::Subtract => a - b,
+            OpInfix::Multiply => a * b,
+            OpInfix::Divide => a / b,
+            OpInfix::Modulo => a % b,
+            // NaN is neither less, greater nor equal to anything
+            OpInfix::Less => return Value::Bool(a < b),
+            OpInfix::LessEqual => return Value::Bool(a <= b),
+            OpInfix::Greater => return Value::Bool(a > b),
+            OpInfix::GreaterEqual => return Value::Bool(a >= b),
+            OpInfix::Equal => return Value::Bool(a == b),
+            OpInfix::NotEqual => return Value::Bool(a != b),
+            OpInfix::LogicAnd | OpInfix::LogicOr => unreachable!("Floats are not bools"),
+        };
+        match ty {
+            Type::F32 => Value::Float(value as f32 as f64),
+            _ => Value::Float(value),
+        }
+    }
+
+    /// Strings are concatenated and compared byte by byte.
+    fn str_infix(op: OpInfix, a: &Rc<str>, b: &Rc<str>) -> Value {
+        match op {
+            OpInfix::Add => Value::Str(format!("{}{}", a, b).into()),
+            op => Value::Bool(Self::compare(op, a.as_bytes().cmp(b.as_bytes()))),
+        }
+    }
+
+    fn compare(op: OpInfix, ordering: Ordering) -> bool {
+        match op {
+            OpInfix::Less => ordering.is_lt(),
+            OpInfix::LessEqual => ordering.is_le(),
+            OpInfix::Greater => ordering.is_gt(),
+            OpInfix::GreaterEqual => ordering.is_ge(),
+            OpInfix::Equal => ordering.is_eq(),
+            OpInfix::NotEqual => ordering.is_ne(),
+            op => unreachable!("`{}` is not a comparison", op),
+        }
+    }
+
+    fn compare_ints(ty: &Type, a: i64, b: i64) -> Ordering {
+        match ty {
+            Type::U64 => (a as u64).cmp(&(b as u64)),
+            _ => a.cmp(&b),
+        }
+    }
+
+    fn closure(&mut self, callable: Callable, env: Vec<Value>) -> Value {
+        self.alloc(Object::Closure(Closure { callable, env }))
+    }
+
+    fn alloc(&mut self, object: Object) -> Value {
+        if self.heap.should_collect() {
+            self.collect(&object);
+        }
+        Value::Object(self.heap.alloc(object))
+    }
+
+    /// Frees the objects the program can no longer reach. The roots are the variables of every
+    /// frame, the closures of the running lambdas, the held values and the values of the
+    /// `pending` object, which is about to be allocated.
+    fn collect(&mut self, pending: &Object) {
+        let values = (self.variables.iter())
+            .chain(&self.temps)
+            .chain(pending.values());
+        let closures = self.frames.iter().filter_map(|frame| frame.closure);
+        let roots = values.filter_map(Value::object).chain(closures);
+        self.heap.collect(roots);
+    }
+
+    fn fields(&self, object: ObjectId) -> &Vec<Value> {
+        let Object::Struct { fields, .. } = self.heap.get(object) else {
+            unreachable!("Only type objects have fields")
+        };
+        fields
+    }
+
+    fn fields_mut(&mut self, object: ObjectId) -> &mut Vec<Value> {
+        let Object::Struct { fields, .. } = self.heap.get_mut(object) else {
+            unreachable!("Only type objects have fields")
+        };
+        fields
+    }
+
+    fn elements(&self, object: ObjectId) -> &Vec<Value> {
+        let Object::Array(elements) = self.heap.get(object) else {
+            unreachable!("Only arrays and lists have elements")
+        };
+        elements
+    }
+
+    fn elements_mut(&mut self, object: ObjectId) -> &mut Vec<Value> {
+        let Object::Array(elements) = self.heap.get_mut(object) else {
+            unreachable!("Only arrays and lists have elements")
+        };
+        elements
+    }
+
+    /// The index as a `usize`, or an error if it is out of bounds.
+    fn check_index(&self, location: Location, object: ObjectId, i: i64) -> Result<usize> {
+        let len = self.elements(object).len();
+        if i < 0 || i as usize >= len {
+            let message = format!("index {} is out of bounds for length {}", i, len);
+            return Err(Abort::new(location, message));
+        }
+        Ok(i as usize)
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames
+            .last()
+            .expect("Code only runs inside of functions")
+    }
+
+    fn get(&self, var: usize) -> Value {
+        self.variables[self.frame().base + var].clone()
+    }
+
+    fn set(&mut self, var: usize, value: Value) {
+        let base = self.frame().base;
+        self.variables[base + var] = value;
+    }
+
+    /// A variable captured by the running lambda.
+    fn captured_mut(&mut self, index: usize) -> &mut Value {
+        let closure = self
+            .frame()
+            .closure
+            .expect("Only lambdas capture variables");
+        let Object::Closure(closure) = self.heap.get_mut(closure) else {
+            unreachable!("Lambdas run with their closure")
+        };
+        &mut closure.env[index]
+    }
+}
+
+/// Wraps an integer to the width of its type, like C does when it stores the result of an
+/// operation.
+fn wrap(ty: &Type, value: i64) -> i64 {
+    match ty {
+        Type::I8 => value as i8 as i64,
+        Type::I32 => value as i32 as i64,
+        Type::U8 => value as u8 as i64,
+        Type::U32 => value as u32 as i64,
+        _ => value,
+    }
+}
@@ -0,0 +1,5 @@
+pub mod code;
+mod compile;
+pub mod error;
+pub mod interpreter;
+pub mod value;
@@ -0,0 +1,219 @@
+use std::rc::Rc;
+use syntax::builtins::BuiltinFunction;
+
+use crate::code::FunctionId;
+
+/// A value of the interpreted program.
+///
+/// Everything the C backend allocates on the gc heap lives on the `Heap` and is referred to by
+/// its `ObjectId`, so that it is shared the same way. Strings are immutable and shared by `Rc`.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    /// Integers of every width. They are wrapped to the width of their type after every
+    /// operation.
+    Int(i64),
+    Float(f64),
+    Str(Rc<str>),
+    Nil,
+    Object(ObjectId),
+}
+
+impl Value {
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            _ => unreachable!("Expected a bool, the type checker disagrees"),
+        }
+    }
+
+    pub fn as_int(&self) -> i64 {
+        match self {
+            Value::Int(i) => *i,
+            _ => unreachable!("Expected an integer, the type checker disagrees"),
+        }
+    }
+
+    pub fn as_str(&self) -> &Rc<str> {
+        match self {
+            Value::Str(s) => s,
+            _ => unreachable!("Expected a string, the type checker disagrees"),
+        }
+    }
+
+    /// The object a value of a type object, enum, array, list or function refers to. `nil`
+    /// never gets here, since optionals have to be narrowed or unwrapped before they are used.
+    pub fn as_object(&self) -> ObjectId {
+        match self {
+            Value::Object(id) => *id,
+            _ => unreachable!("Expected an object, the type checker disagrees"),
+        }
+    }
+
+    /// The object the value refers to, if it is one.
+    pub fn object(&self) -> Option<ObjectId> {
+        match self {
+            Value::Object(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Whether two values are the same. Like pointers in C, objects and strings are only the
+    /// same if they are the same allocation.
+    pub fn is_identical(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Unit, Value::Unit) | (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectId(usize);
+
+#[derive(Debug)]
+pub enum Object {
+    /// The values of the fields are in the order they are declared in.
+    Struct {
+        ty: Rc<StructInfo>,
+        fields: Vec<Value>,
+    },
+    Variant {
+        ty: Rc<EnumInfo>,
+        variant: usize,
+        payload: Vec<Value>,
+    },
+    /// Arrays and lists. Only lists ever change their length.
+    Array(Vec<Value>),
+    Closure(Closure),
+}
+
+/// The names of a type object and its fields, used to print its objects.
+#[derive(Debug)]
+pub struct StructInfo {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+/// The names of an enum and its variants.
+#[derive(Debug)]
+pub struct EnumInfo {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+/// A function value, with the values of the variables captured by its lambda.
+#[derive(Debug)]
+pub struct Closure {
+    pub callable: Callable,
+    pub env: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Callable {
+    /// A function or lambda.
+    Function(FunctionId),
+    Builtin(&'static BuiltinFunction),
+}
+
+impl Object {
+    /// The values the object refers to.
+    pub fn values(&self) -> &[Value] {
+        match self {
+            Object::Struct { fields, .. } => fields,
+            Object::Variant { payload, .. } => payload,
+            Object::Array(elements) => elements,
+            Object::Closure(closure) => &closure.env,
+        }
+    }
+}
+
+/// The number of objects the first collection waits for.
+const MIN_COLLECTION_THRESHOLD: usize = 1 << 16;
+
+/// The objects allocated by the interpreted program.
+///
+/// Like the gc of the C runtime, the heap frees the objects the program can no longer reach with
+/// a mark and sweep. It collects once the number of objects doubled since the last collection,
+/// which `should_collect` tells the interpreter, since only it knows the roots. Freed slots are
+/// reused by later allocations.
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    free: Vec<usize>,
+    threshold: usize,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            free: Vec::new(),
+            threshold: MIN_COLLECTION_THRESHOLD,
+        }
+    }
+
+    pub fn alloc(&mut self, object: Object) -> ObjectId {
+        match self.free.pop() {
+            Some(slot) => {
+                self.objects[slot] = Some(object);
+                ObjectId(slot)
+            }
+            None => {
+                self.objects.push(Some(object));
+                ObjectId(self.objects.len() - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, id: ObjectId) -> &Object {
+        self.objects[id.0]
+            .as_ref()
+            .expect("Reachable objects are not freed")
+    }
+
+    pub fn get_mut(&mut self, id: ObjectId) -> &mut Object {
+        self.objects[id.0]
+            .as_mut()
+            .expect("Reachable objects are not freed")
+    }
+
+    fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.len() >= self.threshold
+    }
+
+    /// Frees every object that cannot be reached from the `roots`.
+    pub fn collect(&mut self, roots: impl Iterator<Item = ObjectId>) {
+        let mut marked = vec![false; self.objects.len()];
+        let mut pending = roots.collect::<Vec<_>>();
+        while let Some(id) = pending.pop() {
+            if std::mem::replace(&mut marked[id.0], true) {
+                continue;
+            }
+            pending.extend(self.get(id).values().iter().filter_map(Value::object));
+        }
+
+        for (slot, marked) in marked.into_iter().enumerate() {
+            if !marked && self.objects[slot].take().is_some() {
+                self.free.push(slot);
+            }
+        }
+        self.threshold = MIN_COLLECTION_THRESHOLD.max(2 * self.len());
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
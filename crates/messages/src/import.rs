@@ -0,0 +1,65 @@
+use base::{located::Located, source_id::SourceId};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ImportError {
+    /// There is no file for the imported module relative to the package root.
+    ModuleNotFound { name: Located<String>, file: String },
+    /// A module imports itself, directly or through other modules.
+    ImportCycle {
+        name: Located<String>,
+        cycle: Vec<String>,
+    },
+}
+
+impl ImportError {
+    pub fn as_diagnostic(&self) -> Diagnostic<SourceId> {
+        let code = self.code();
+        let message = self.message();
+        let labels = self.labels();
+        let notes = self.notes();
+        Diagnostic::error()
+            .with_code(format!("IMP::{:04}", code).as_str())
+            .with_message(message)
+            .with_labels(labels)
+            .with_notes(notes)
+    }
+
+    pub fn code(&self) -> usize {
+        match self {
+            ImportError::ModuleNotFound { .. } => 0,
+            ImportError::ImportCycle { .. } => 1,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ImportError::ModuleNotFound { .. } => "module not found",
+            ImportError::ImportCycle { .. } => "import cycle",
+        }
+    }
+
+    pub fn labels(&self) -> Vec<Label<SourceId>> {
+        match self {
+            ImportError::ModuleNotFound { name, .. } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("`{}` is not a module", name.value))]
+            }
+            ImportError::ImportCycle { name, .. } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("importing `{}` creates a cycle", name.value))]
+            }
+        }
+    }
+
+    pub fn notes(&self) -> Vec<String> {
+        match self {
+            ImportError::ModuleNotFound { file, .. } => {
+                vec![format!("no file found at `{}`", file)]
+            }
+            ImportError::ImportCycle { cycle, .. } => {
+                vec![format!("the import cycle is {}", cycle.join(" -> "))]
+            }
+        }
+    }
+}
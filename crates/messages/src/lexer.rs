@@ -1,10 +1,15 @@
 use base::{located::Located, source_id::SourceId};
 use codespan_reporting::diagnostic::{Diagnostic, Label};
+use std::ops::Range;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum LexerError {
     UnexpectedInput { token: Located<String> },
     UnterminatedString { location: Located<()> },
+    UnknownEscape { location: Located<()>, escape: char },
+    MalformedUnicodeEscape { location: Located<()> },
+    InvalidCodepoint { location: Located<()>, value: u32 },
+    MalformedHexEscape { location: Located<()> },
 }
 
 impl LexerError {
@@ -24,6 +29,10 @@ impl LexerError {
         match self {
             LexerError::UnexpectedInput { .. } => 0,
             LexerError::UnterminatedString { .. } => 1,
+            LexerError::UnknownEscape { .. } => 2,
+            LexerError::MalformedUnicodeEscape { .. } => 3,
+            LexerError::InvalidCodepoint { .. } => 4,
+            LexerError::MalformedHexEscape { .. } => 5,
         }
     }
 
@@ -31,6 +40,21 @@ impl LexerError {
         match self {
             LexerError::UnexpectedInput { .. } => "unexpected input",
             LexerError::UnterminatedString { .. } => "unterminated string",
+            LexerError::UnknownEscape { .. } => "unknown escape sequence",
+            LexerError::MalformedUnicodeEscape { .. } => "malformed unicode escape",
+            LexerError::InvalidCodepoint { .. } => "invalid unicode escape",
+            LexerError::MalformedHexEscape { .. } => "malformed hex escape",
+        }
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            LexerError::UnexpectedInput { token } => token.span.clone(),
+            LexerError::UnterminatedString { location }
+            | LexerError::UnknownEscape { location, .. }
+            | LexerError::MalformedUnicodeEscape { location }
+            | LexerError::InvalidCodepoint { location, .. }
+            | LexerError::MalformedHexEscape { location } => location.span.clone(),
         }
     }
 
@@ -42,10 +66,36 @@ impl LexerError {
             LexerError::UnterminatedString { location } => {
                 vec![Label::primary(location.source, location.span.clone())]
             }
+            LexerError::UnknownEscape { location, escape } => {
+                vec![
+                    Label::primary(location.source, location.span.clone()).with_message(format!(
+                        "`\\{}` is not a valid escape",
+                        escape.escape_debug()
+                    )),
+                ]
+            }
+            LexerError::MalformedUnicodeEscape { location } => {
+                vec![Label::primary(location.source, location.span.clone())
+                    .with_message("expected 1 to 6 hex digits in braces, like `\\u{1F332}`")]
+            }
+            LexerError::InvalidCodepoint { location, value } => {
+                vec![Label::primary(location.source, location.span.clone())
+                    .with_message(format!("`{value:X}` is not a unicode scalar value"))]
+            }
+            LexerError::MalformedHexEscape { location } => {
+                vec![Label::primary(location.source, location.span.clone())
+                    .with_message("expected two hex digits up to `7F`")]
+            }
         }
     }
 
     pub fn notes(&self) -> Vec<String> {
-        vec![]
+        match self {
+            LexerError::UnknownEscape { .. } => vec![
+                "valid escapes are `\\n`, `\\r`, `\\t`, `\\0`, `\\\\`, `\\\"`, `\\'`, `\\x7F` and `\\u{7FFF}`"
+                    .to_string(),
+            ],
+            _ => vec![],
+        }
     }
 }
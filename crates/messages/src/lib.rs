@@ -1,3 +1,6 @@
+pub mod import;
 pub mod lexer;
 pub mod message;
 pub mod parser;
+pub mod resolver;
+pub mod typeck;
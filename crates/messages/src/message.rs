@@ -1,10 +1,16 @@
-use crate::{lexer::LexerError, parser::ParserError};
+use crate::{
+    import::ImportError, lexer::LexerError, parser::ParserError, resolver::ResolverError,
+    typeck::TypeError,
+};
 use base::source_id::SourceId;
 use codespan_reporting::diagnostic::Diagnostic;
 
 pub enum Message {
     Lexer(LexerError),
     Parse(ParserError),
+    Import(ImportError),
+    Resolve(ResolverError),
+    Type(TypeError),
 }
 
 impl Message {
@@ -12,6 +18,9 @@ impl Message {
         match self {
             Message::Lexer(err) => err.as_diagnostic(),
             Message::Parse(err) => err.as_diagnostic(),
+            Message::Import(err) => err.as_diagnostic(),
+            Message::Resolve(err) => err.as_diagnostic(),
+            Message::Type(err) => err.as_diagnostic(),
         }
     }
 }
@@ -27,3 +36,21 @@ impl From<ParserError> for Message {
         Message::Parse(err)
     }
 }
+
+impl From<ImportError> for Message {
+    fn from(err: ImportError) -> Self {
+        Message::Import(err)
+    }
+}
+
+impl From<ResolverError> for Message {
+    fn from(err: ResolverError) -> Self {
+        Message::Resolve(err)
+    }
+}
+
+impl From<TypeError> for Message {
+    fn from(err: TypeError) -> Self {
+        Message::Type(err)
+    }
+}
@@ -0,0 +1,135 @@
+use base::{located::Located, source_id::SourceId};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResolverError {
+    UndefinedVariable {
+        name: Located<String>,
+    },
+    UndefinedFunction {
+        name: Located<String>,
+    },
+    UndefinedType {
+        name: Located<String>,
+    },
+    DuplicateDefinition {
+        name: Located<String>,
+        previous: Located<()>,
+    },
+    UndefinedVariant {
+        name: Located<String>,
+        enum_name: String,
+    },
+    BuiltinRedefinition {
+        name: Located<String>,
+    },
+    /// A `break` or `continue` that is not inside a loop.
+    OutsideOfLoop {
+        name: Located<String>,
+    },
+    /// Methods can only be added in the module that declares the type.
+    ForeignImpl {
+        name: Located<String>,
+    },
+    /// A type name that more than one imported module declares.
+    AmbiguousType {
+        name: Located<String>,
+        /// The qualified names of the types it could refer to.
+        candidates: Vec<String>,
+    },
+}
+
+impl ResolverError {
+    pub fn as_diagnostic(&self) -> Diagnostic<SourceId> {
+        let code = self.code();
+        let message = self.message();
+        let labels = self.labels();
+        let notes = self.notes();
+        Diagnostic::error()
+            .with_code(format!("RES::{:04}", code).as_str())
+            .with_message(message)
+            .with_labels(labels)
+            .with_notes(notes)
+    }
+
+    pub fn code(&self) -> usize {
+        match self {
+            ResolverError::UndefinedVariable { .. } => 0,
+            ResolverError::UndefinedFunction { .. } => 1,
+            ResolverError::UndefinedType { .. } => 2,
+            ResolverError::DuplicateDefinition { .. } => 3,
+            ResolverError::UndefinedVariant { .. } => 4,
+            ResolverError::BuiltinRedefinition { .. } => 5,
+            ResolverError::OutsideOfLoop { .. } => 6,
+            ResolverError::ForeignImpl { .. } => 7,
+            ResolverError::AmbiguousType { .. } => 8,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ResolverError::UndefinedVariable { .. } => "undefined variable",
+            ResolverError::UndefinedFunction { .. } => "undefined function",
+            ResolverError::UndefinedType { .. } => "undefined type",
+            ResolverError::DuplicateDefinition { .. } => "duplicate definition",
+            ResolverError::UndefinedVariant { .. } => "undefined variant",
+            ResolverError::BuiltinRedefinition { .. } => "redefinition of a builtin",
+            ResolverError::OutsideOfLoop { .. } => "loop control outside of a loop",
+            ResolverError::ForeignImpl { .. } => "impl for a type of another module",
+            ResolverError::AmbiguousType { .. } => "ambiguous type",
+        }
+    }
+
+    pub fn labels(&self) -> Vec<Label<SourceId>> {
+        match self {
+            ResolverError::UndefinedVariable { name }
+            | ResolverError::UndefinedFunction { name }
+            | ResolverError::UndefinedType { name } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("`{}` is not defined", name.value))]
+            }
+            ResolverError::DuplicateDefinition { name, previous } => {
+                vec![
+                    Label::primary(name.source, name.span.clone())
+                        .with_message(format!("`{}` is defined again here", name.value)),
+                    Label::secondary(previous.source, previous.span.clone())
+                        .with_message("previous definition"),
+                ]
+            }
+            ResolverError::UndefinedVariant { name, enum_name } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("`{}` has no variant `{}`", enum_name, name.value))]
+            }
+            ResolverError::BuiltinRedefinition { name } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("`{}` is a builtin function", name.value))]
+            }
+            ResolverError::OutsideOfLoop { name } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("`{}` can only be used inside a loop", name.value))]
+            }
+            ResolverError::ForeignImpl { name } => {
+                vec![Label::primary(name.source, name.span.clone())
+                    .with_message(format!("`{}` is declared in another module", name.value))]
+            }
+            ResolverError::AmbiguousType { name, .. } => {
+                vec![
+                    Label::primary(name.source, name.span.clone()).with_message(format!(
+                        "`{}` is declared by more than one imported module",
+                        name.value
+                    )),
+                ]
+            }
+        }
+    }
+
+    pub fn notes(&self) -> Vec<String> {
+        match self {
+            ResolverError::AmbiguousType { candidates, .. } => {
+                let candidates: Vec<_> = candidates.iter().map(|c| format!("`{}`", c)).collect();
+                vec![format!("write one of {} instead", candidates.join(", "))]
+            }
+            _ => vec![],
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use base::{located::Located, source_id::SourceId};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+/// Types are passed in their printed form since `messages` cannot depend on the AST.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeError {
+    Mismatch {
+        location: Located<()>,
+        expected: String,
+        found: String,
+    },
+    InvalidInfixOperands {
+        location: Located<()>,
+        op: String,
+        lt: String,
+        rt: String,
+    },
+    InvalidPrefixOperand {
+        location: Located<()>,
+        op: String,
+        rt: String,
+    },
+    ArgumentCount {
+        location: Located<()>,
+        expected: usize,
+        found: usize,
+    },
+    NotCallable {
+        location: Located<()>,
+    },
+    UnknownField {
+        location: Located<()>,
+        ty: String,
+        field: String,
+    },
+    MissingFields {
+        location: Located<()>,
+        ty: String,
+        fields: Vec<String>,
+    },
+    DuplicateField {
+        location: Located<()>,
+        field: String,
+    },
+    InvalidAssignTarget {
+        location: Located<()>,
+    },
+    MissingReturn {
+        location: Located<()>,
+        expected: String,
+    },
+    UnitVariable {
+        location: Located<()>,
+    },
+    NotAnEnum {
+        location: Located<()>,
+        ty: String,
+    },
+    NonExhaustiveMatch {
+        location: Located<()>,
+        missing: Vec<String>,
+    },
+    UnreachableArm {
+        location: Located<()>,
+    },
+    PatternArity {
+        location: Located<()>,
+        expected: usize,
+        found: usize,
+    },
+    NotAStruct {
+        location: Located<()>,
+        ty: String,
+    },
+    UnknownElementType {
+        location: Located<()>,
+    },
+    NotIndexable {
+        location: Located<()>,
+        ty: String,
+    },
+    UnknownMethod {
+        location: Located<()>,
+        ty: String,
+        method: String,
+    },
+    MaybeNil {
+        location: Located<()>,
+        ty: String,
+    },
+    NotOptional {
+        location: Located<()>,
+        ty: String,
+    },
+    NotNullable {
+        location: Located<()>,
+        ty: String,
+    },
+    TypeArgumentCount {
+        location: Located<()>,
+        ty: String,
+        expected: usize,
+        found: usize,
+    },
+    UnknownTypeArgument {
+        location: Located<()>,
+        param: String,
+    },
+    GenericImpl {
+        location: Located<()>,
+        ty: String,
+    },
+    IntegerOutOfRange {
+        location: Located<()>,
+        value: i128,
+        ty: String,
+        min: i128,
+        max: i128,
+    },
+    AssignToCaptured {
+        location: Located<()>,
+        name: String,
+    },
+    InfiniteInstantiation {
+        location: Located<()>,
+        function: String,
+        ty: String,
+    },
+}
+
+impl TypeError {
+    pub fn as_diagnostic(&self) -> Diagnostic<SourceId> {
+        let code = self.code();
+        let message = self.message();
+        let labels = self.labels();
+        let notes = self.notes();
+        Diagnostic::error()
+            .with_code(format!("TYP::{:04}", code).as_str())
+            .with_message(message)
+            .with_labels(labels)
+            .with_notes(notes)
+    }
+
+    pub fn code(&self) -> usize {
+        match self {
+            TypeError::Mismatch { .. } => 0,
+            TypeError::InvalidInfixOperands { .. } => 1,
+            TypeError::InvalidPrefixOperand { .. } => 2,
+            TypeError::ArgumentCount { .. } => 3,
+            TypeError::NotCallable { .. } => 4,
+            TypeError::UnknownField { .. } => 5,
+            TypeError::MissingFields { .. } => 6,
+            TypeError::DuplicateField { .. } => 7,
+            TypeError::InvalidAssignTarget { .. } => 8,
+            TypeError::MissingReturn { .. } => 9,
+            TypeError::UnitVariable { .. } => 10,
+            TypeError::NotAnEnum { .. } => 11,
+            TypeError::NonExhaustiveMatch { .. } => 12,
+            TypeError::UnreachableArm { .. } => 13,
+            TypeError::PatternArity { .. } => 14,
+            TypeError::NotAStruct { .. } => 15,
+            TypeError::UnknownElementType { .. } => 16,
+            TypeError::NotIndexable { .. } => 17,
+            TypeError::UnknownMethod { .. } => 18,
+            TypeError::MaybeNil { .. } => 19,
+            TypeError::NotOptional { .. } => 20,
+            TypeError::NotNullable { .. } => 21,
+            TypeError::TypeArgumentCount { .. } => 22,
+            TypeError::UnknownTypeArgument { .. } => 23,
+            TypeError::GenericImpl { .. } => 24,
+            TypeError::IntegerOutOfRange { .. } => 25,
+            TypeError::AssignToCaptured { .. } => 26,
+            TypeError::InfiniteInstantiation { .. } => 27,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            TypeError::Mismatch { .. } => "mismatched types",
+            TypeError::InvalidInfixOperands { .. } => "invalid operands for infix operator",
+            TypeError::InvalidPrefixOperand { .. } => "invalid operand for prefix operator",
+            TypeError::ArgumentCount { .. } => "wrong number of arguments",
+            TypeError::NotCallable { .. } => "expression is not callable",
+            TypeError::UnknownField { .. } => "unknown field",
+            TypeError::MissingFields { .. } => "missing fields in initializer",
+            TypeError::DuplicateField { .. } => "field initialized more than once",
+            TypeError::InvalidAssignTarget { .. } => "invalid assignment target",
+            TypeError::MissingReturn { .. } => "missing return",
+            TypeError::UnitVariable { .. } => "variable of unit type",
+            TypeError::NotAnEnum { .. } => "match on a value that is not an enum",
+            TypeError::NonExhaustiveMatch { .. } => "non-exhaustive match",
+            TypeError::UnreachableArm { .. } => "unreachable match arm",
+            TypeError::PatternArity { .. } => "wrong number of bindings in pattern",
+            TypeError::NotAStruct { .. } => "initializer for a type that is not a struct",
+            TypeError::UnknownElementType { .. } => "cannot infer the element type",
+            TypeError::NotIndexable { .. } => "value cannot be indexed",
+            TypeError::UnknownMethod { .. } => "unknown method",
+            TypeError::MaybeNil { .. } => "value may be `nil`",
+            TypeError::NotOptional { .. } => "unwrap of a value that is not optional",
+            TypeError::NotNullable { .. } => "type cannot be optional",
+            TypeError::TypeArgumentCount { .. } => "wrong number of type arguments",
+            TypeError::UnknownTypeArgument { .. } => "cannot infer type argument",
+            TypeError::GenericImpl { .. } => "methods on a generic type",
+            TypeError::IntegerOutOfRange { .. } => "integer literal out of range",
+            TypeError::AssignToCaptured { .. } => "assignment to a captured variable",
+            TypeError::InfiniteInstantiation { .. } => "generic function is instantiated forever",
+        }
+    }
+
+    pub fn labels(&self) -> Vec<Label<SourceId>> {
+        let (location, message) = match self {
+            TypeError::Mismatch {
+                location,
+                expected,
+                found,
+            } => (location, format!("expected `{expected}`, found `{found}`")),
+            TypeError::InvalidInfixOperands {
+                location,
+                op,
+                lt,
+                rt,
+            } => (
+                location,
+                format!("cannot apply `{op}` to `{lt}` and `{rt}`"),
+            ),
+            TypeError::InvalidPrefixOperand { location, op, rt } => {
+                (location, format!("cannot apply `{op}` to `{rt}`"))
+            }
+            TypeError::ArgumentCount {
+                location,
+                expected,
+                found,
+            } => (
+                location,
+                format!("expected {expected} arguments, found {found}"),
+            ),
+            TypeError::NotCallable { location } => {
+                (location, "only functions can be called".to_string())
+            }
+            TypeError::UnknownField {
+                location,
+                ty,
+                field,
+            } => (location, format!("`{ty}` has no field `{field}`")),
+            TypeError::MissingFields {
+                location,
+                ty,
+                fields,
+            } => (
+                location,
+                format!("missing {} of `{ty}`", quoted_list(fields)),
+            ),
+            TypeError::DuplicateField { location, field } => {
+                (location, format!("`{field}` is already initialized"))
+            }
+            TypeError::InvalidAssignTarget { location } => (
+                location,
+                "only variables and fields can be assigned to".to_string(),
+            ),
+            TypeError::MissingReturn { location, expected } => (
+                location,
+                format!("not every path returns a value of type `{expected}`"),
+            ),
+            TypeError::UnitVariable { location } => (
+                location,
+                "variables cannot hold a value of type `()`".to_string(),
+            ),
+            TypeError::NotAnEnum { location, ty } => (location, format!("`{ty}` is not an enum")),
+            TypeError::NonExhaustiveMatch { location, missing } => {
+                (location, format!("{} not covered", quoted_list(missing)))
+            }
+            TypeError::UnreachableArm { location } => (
+                location,
+                "this pattern is already covered by an earlier arm".to_string(),
+            ),
+            TypeError::PatternArity {
+                location,
+                expected,
+                found,
+            } => (
+                location,
+                format!("expected {expected} bindings, found {found}"),
+            ),
+            TypeError::NotAStruct { location, ty } => (location, format!("`{ty}` is not a struct")),
+            TypeError::UnknownElementType { location } => {
+                (location, "empty arrays need a type annotation".to_string())
+            }
+            TypeError::NotIndexable { location, ty } => {
+                (location, format!("`{ty}` is not an array or a list"))
+            }
+            TypeError::UnknownMethod {
+                location,
+                ty,
+                method,
+            } => (location, format!("`{ty}` has no method `{method}`")),
+            TypeError::MaybeNil { location, ty } => {
+                (location, format!("this value of type `{ty}` may be `nil`"))
+            }
+            TypeError::NotOptional { location, ty } => {
+                (location, format!("`{ty}` is not an optional type"))
+            }
+            TypeError::NotNullable { location, ty } => {
+                (location, format!("`{ty}` values cannot be `nil`"))
+            }
+            TypeError::TypeArgumentCount {
+                location,
+                ty,
+                expected,
+                found,
+            } => (
+                location,
+                format!("`{ty}` takes {expected} type arguments, found {found}"),
+            ),
+            TypeError::UnknownTypeArgument { location, param } => (
+                location,
+                format!("the type of `{param}` is not known from the values"),
+            ),
+            TypeError::GenericImpl { location, ty } => (
+                location,
+                format!("`{ty}` is generic and cannot have methods"),
+            ),
+            TypeError::IntegerOutOfRange {
+                location,
+                value,
+                ty,
+                ..
+            } => (location, format!("`{value}` does not fit into `{ty}`")),
+            TypeError::AssignToCaptured { location, name } => (
+                location,
+                format!("`{name}` belongs to the enclosing function"),
+            ),
+            TypeError::InfiniteInstantiation {
+                location,
+                function,
+                ty,
+            } => (
+                location,
+                format!("`{function}` is used with `{ty}`, which leads back to this call"),
+            ),
+        };
+        vec![Label::primary(location.source, location.span.clone()).with_message(message)]
+    }
+
+    pub fn notes(&self) -> Vec<String> {
+        match self {
+            TypeError::MaybeNil { .. } => {
+                vec!["compare the value with `nil` first or unwrap it with `!`".to_string()]
+            }
+            TypeError::NotNullable { .. } => vec![
+                "only structs, enums, strings, arrays and lists can be optional".to_string(),
+                "a type parameter can stand for any type, so it cannot be optional".to_string(),
+            ],
+            TypeError::IntegerOutOfRange { ty, min, max, .. } => {
+                vec![format!("`{ty}` holds values from {min} to {max}")]
+            }
+            TypeError::AssignToCaptured { .. } => vec![
+                "a lambda captures a copy of the variable, which the assignment would change"
+                    .to_string(),
+                "store the value in a field of a type object to share it".to_string(),
+            ],
+            TypeError::InfiniteInstantiation { .. } => vec![
+                "generic functions are compiled for every type argument they are used with"
+                    .to_string(),
+                "each round of these calls wraps the type argument once more".to_string(),
+            ],
+            _ => vec![],
+        }
+    }
+}
+
+fn quoted_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{item}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use hir::ir::{ty::Ty, FunctionId, LocalId, Package as Hir};
+
+use crate::ir::{
+    Body, Operand, Package, Place, PlaceBase, Projection, Rvalue, Statement, Terminator,
+};
+
+/// Finds the locals holding type objects that never leave the function creating them, and
+/// marks them to be stored on the stack.
+///
+/// An object escapes as soon as the local itself is used as a value: returned, passed to a
+/// function, stored in a field, element or another variable, captured by a closure or compared.
+/// Reading and writing its fields does not let it escape. Only locals that are always assigned
+/// a new object are candidates, so parameters and locals assigned an existing object never are.
+pub struct EscapeAnalysis {
+    escaped: HashSet<LocalId>,
+}
+
+impl EscapeAnalysis {
+    pub fn run(hir: &Hir, mir: &mut Package) {
+        for (id, body) in mir.bodies.iter_mut().enumerate() {
+            let Some(body) = body else {
+                continue;
+            };
+            let function = hir.function(FunctionId(id));
+            let mut analysis = Self {
+                escaped: function.params.iter().copied().collect(),
+            };
+            analysis.analyze(body);
+            for (id, local) in body.locals.iter_mut().enumerate() {
+                local.on_stack = match local.ty {
+                    // C has no empty structs to store
+                    Ty::Struct(s) => {
+                        !analysis.escaped.contains(&LocalId(id))
+                            && !hir.struct_(s).fields.is_empty()
+                    }
+                    _ => false,
+                };
+            }
+        }
+    }
+
+    fn analyze(&mut self, body: &Body) {
+        for block in &body.blocks {
+            for stmt in &block.stmts {
+                match &stmt.value {
+                    Statement::Assign(place, rvalue) => {
+                        match (place.base, place.projections.is_empty(), rvalue) {
+                            (PlaceBase::Local(_), true, Rvalue::Struct(..)) => {}
+                            (PlaceBase::Local(id), true, _) => {
+                                self.escaped.insert(id);
+                            }
+                            _ => self.visit_projections(place),
+                        }
+                        self.visit_rvalue(rvalue);
+                    }
+                    Statement::Eval(rvalue) => self.visit_rvalue(rvalue),
+                }
+            }
+            match &block.terminator.value {
+                Terminator::Branch(cond, ..) => self.visit_operand(cond),
+                Terminator::Return(Some(value)) => self.visit_operand(value),
+                Terminator::Goto(_)
+                | Terminator::Switch { .. }
+                | Terminator::Return(None)
+                | Terminator::Unreachable => {}
+            }
+        }
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &Rvalue) {
+        for operand in rvalue.operands() {
+            self.visit_operand(operand);
+        }
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) {
+        let Operand::Copy(place) = operand else {
+            return;
+        };
+        match (place.base, place.projections.is_empty()) {
+            (PlaceBase::Local(id), true) => {
+                self.escaped.insert(id);
+            }
+            _ => self.visit_projections(place),
+        }
+    }
+
+    fn visit_projections(&mut self, place: &Place) {
+        for projection in &place.projections {
+            if let Projection::Index(index, _) = projection {
+                self.visit_operand(index);
+            }
+        }
+    }
+}
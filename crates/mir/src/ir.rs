@@ -0,0 +1,247 @@
+use base::located::Located;
+use hir::ir::{
+    expr::Literal, ty::Ty, EnumId, Function, FunctionId, FunctionKind, LocalId, Package as Hir,
+    StructId,
+};
+use syntax::{
+    ast::op::{OpInfix, OpPrefix},
+    builtins::{BuiltinFunction, Intrinsic},
+};
+
+pub type StatementS = Located<Statement>;
+pub type TerminatorS = Located<Terminator>;
+
+/// The bodies of the functions of a HIR package, indexed by their `FunctionId`.
+#[derive(Debug, Default)]
+pub struct Package {
+    /// `None` for extern functions.
+    pub bodies: Vec<Option<Body>>,
+}
+
+impl Package {
+    pub fn body(&self, id: FunctionId) -> Option<&Body> {
+        self.bodies[id.0].as_ref()
+    }
+}
+
+/// A function as a graph of basic blocks. The first block is the entry.
+#[derive(Debug)]
+pub struct Body {
+    /// The variables of the HIR function under the same ids, followed by the temporaries.
+    /// Every local has a name of its own, even where the program shadows a variable.
+    pub locals: Vec<LocalDecl>,
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Body {
+    pub fn local(&self, id: LocalId) -> &LocalDecl {
+        &self.locals[id.0]
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id.0]
+    }
+
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len()).map(BlockId)
+    }
+
+    /// The type of the value stored at `place`, in the body of `function`.
+    pub fn place_ty(&self, hir: &Hir, function: &Function, place: &Place) -> Ty {
+        let mut ty = match place.base {
+            PlaceBase::Local(id) => self.local(id).ty.clone(),
+            PlaceBase::Captured(index) => match &function.kind {
+                FunctionKind::Lambda { captures } => captures[index].ty.clone(),
+                _ => unreachable!("Only lambdas capture variables"),
+            },
+        };
+        for projection in &place.projections {
+            ty = match (projection, ty) {
+                (Projection::Field(field), Ty::Struct(id)) => {
+                    hir.struct_(id).fields[*field].ty.clone()
+                }
+                (Projection::Index(..), Ty::Array(elem, _) | Ty::List(elem)) => *elem,
+                (Projection::Payload(variant, index), Ty::Enum(id)) => {
+                    hir.enum_(id).variants[*variant].payload[*index].clone()
+                }
+                _ => unreachable!("Projections match the type they are applied to"),
+            };
+        }
+        ty
+    }
+
+    pub fn operand_ty(&self, hir: &Hir, function: &Function, operand: &Operand) -> Ty {
+        match operand {
+            Operand::Copy(place) => self.place_ty(hir, function, place),
+            Operand::Constant(_, ty) => ty.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LocalDecl {
+    pub name: String,
+    pub ty: Ty,
+    /// Whether the local holds a type object that never leaves the function, so the object can
+    /// live in the local itself instead of on the heap. Set by the escape analysis.
+    pub on_stack: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+/// Statements that run in order, followed by the terminator that decides which block runs next.
+#[derive(Clone, Debug)]
+pub struct BasicBlock {
+    pub stmts: Vec<StatementS>,
+    pub terminator: TerminatorS,
+}
+
+/// Statements are located at the expression whose value they compute.
+#[derive(Clone, Debug)]
+pub enum Statement {
+    Assign(Place, Rvalue),
+    /// Computes a value only for its side effects, like a call returning nothing.
+    Eval(Rvalue),
+}
+
+/// Terminators are located at the statement whose control flow they implement. The end of a
+/// function is located at its declaration.
+#[derive(Clone, Debug)]
+pub enum Terminator {
+    Goto(BlockId),
+    /// Continues with the first block if the condition holds, otherwise with the second.
+    Branch(Operand, BlockId, BlockId),
+    /// Continues with the block of the variant of the enum in the local, or with `otherwise` if
+    /// the variant has none.
+    Switch {
+        scrutinee: LocalId,
+        enum_: EnumId,
+        targets: Vec<(usize, BlockId)>,
+        otherwise: BlockId,
+    },
+    Return(Option<Operand>),
+    /// The end of a function that returns a value, which the program never reaches.
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Goto(target) => vec![*target],
+            Terminator::Branch(_, then, else_) => vec![*then, *else_],
+            Terminator::Switch {
+                targets, otherwise, ..
+            } => targets
+                .iter()
+                .map(|(_, target)| *target)
+                .chain(std::iter::once(*otherwise))
+                .collect(),
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Goto(target) => vec![target],
+            Terminator::Branch(_, then, else_) => vec![then, else_],
+            Terminator::Switch {
+                targets, otherwise, ..
+            } => targets
+                .iter_mut()
+                .map(|(_, target)| target)
+                .chain(std::iter::once(otherwise))
+                .collect(),
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+}
+
+/// A memory location: a variable, optionally followed by fields, elements and payloads.
+#[derive(Clone, Debug)]
+pub struct Place {
+    pub base: PlaceBase,
+    pub projections: Vec<Projection>,
+}
+
+impl Place {
+    pub fn local(id: LocalId) -> Self {
+        Self {
+            base: PlaceBase::Local(id),
+            projections: Vec::new(),
+        }
+    }
+
+    pub fn project(mut self, projection: Projection) -> Self {
+        self.projections.push(projection);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaceBase {
+    Local(LocalId),
+    /// A variable of an enclosing function, by its index in the captures of the lambda.
+    Captured(usize),
+}
+
+#[derive(Clone, Debug)]
+pub enum Projection {
+    /// A field of a type object, by its index.
+    Field(usize),
+    /// An element of an array or list. The index is checked at runtime, and an index out of
+    /// bounds is reported at the location.
+    Index(Operand, Located<()>),
+    /// A value of the payload of an enum, which has to be of the variant.
+    Payload(usize, usize),
+}
+
+#[derive(Clone, Debug)]
+pub enum Operand {
+    Copy(Place),
+    Constant(Literal, Ty),
+}
+
+#[derive(Clone, Debug)]
+pub enum Rvalue {
+    Use(Operand),
+    /// A function used as a value.
+    FunctionRef(FunctionId),
+    BuiltinRef(&'static BuiltinFunction),
+    /// Calls a function, method or extern function. Methods get the object as their first
+    /// argument.
+    Call(FunctionId, Vec<Operand>),
+    CallBuiltin(&'static BuiltinFunction, Vec<Operand>),
+    CallClosure(Operand, Vec<Operand>),
+    Intrinsic(Intrinsic, Vec<Operand>),
+    Prefix(OpPrefix, Operand),
+    /// Any operator but `&&` and `||`, which are lowered to branches.
+    Infix(Operand, OpInfix, Operand),
+    /// A new type object with the values of its fields in declaration order.
+    Struct(StructId, Vec<Operand>),
+    /// A variant of an enum, by its index, with its payload.
+    Variant(EnumId, usize, Vec<Operand>),
+    /// An array or list literal, depending on the type of the place it is assigned to.
+    Array(Vec<Operand>),
+    Unwrap(Operand),
+    /// A closure of a lifted lambda, with the values of the variables it captures.
+    Closure(FunctionId, Vec<Operand>),
+}
+
+impl Rvalue {
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Rvalue::FunctionRef(_) | Rvalue::BuiltinRef(_) => vec![],
+            Rvalue::Use(op) | Rvalue::Prefix(_, op) | Rvalue::Unwrap(op) => vec![op],
+            Rvalue::Call(_, ops)
+            | Rvalue::CallBuiltin(_, ops)
+            | Rvalue::Intrinsic(_, ops)
+            | Rvalue::Struct(_, ops)
+            | Rvalue::Variant(_, _, ops)
+            | Rvalue::Array(ops)
+            | Rvalue::Closure(_, ops) => ops.iter().collect(),
+            Rvalue::CallClosure(callee, args) => std::iter::once(callee).chain(args).collect(),
+            Rvalue::Infix(lt, _, rt) => vec![lt, rt],
+        }
+    }
+}
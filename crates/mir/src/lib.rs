@@ -0,0 +1,4 @@
+pub mod escape;
+pub mod ir;
+pub mod lower;
+pub mod print;
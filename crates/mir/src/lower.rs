@@ -0,0 +1,456 @@
+use std::collections::{HashMap, HashSet};
+
+use base::located::Located;
+use hir::ir::{
+    expr::ExprKind,
+    stmt::{Block, Match, Pattern, Stmt, While},
+    ty::Ty,
+    ExprS, Function, FunctionKind, LocalId, Package as Hir, StmtS,
+};
+use syntax::ast::op::OpInfix;
+
+use crate::ir::{
+    BasicBlock, BlockId, Body, LocalDecl, Operand, Package, Place, PlaceBase, Projection, Rvalue,
+    Statement, StatementS, Terminator, TerminatorS,
+};
+
+/// A block whose terminator is only known once the statements after it are lowered.
+struct PartialBlock {
+    stmts: Vec<StatementS>,
+    terminator: Option<TerminatorS>,
+}
+
+/// Where `continue` and `break` jump to in a loop.
+struct LoopTargets {
+    continue_: BlockId,
+    break_: BlockId,
+}
+
+/// Lowers the bodies of a HIR package to basic blocks.
+///
+/// Every intermediate value gets a temporary, so statements only work on operands, and
+/// control flow like loops, `match` and the short circuiting of `&&` and `||` becomes explicit
+/// jumps between blocks.
+pub struct HirToMir<'a> {
+    function: &'a Function,
+    locals: Vec<LocalDecl>,
+    blocks: Vec<PartialBlock>,
+    /// The block statements are added to.
+    current: BlockId,
+    loops: Vec<LoopTargets>,
+}
+
+impl<'a> HirToMir<'a> {
+    pub fn lower(hir: &'a Hir) -> Package {
+        let bodies = hir
+            .functions
+            .iter()
+            .map(|function| {
+                let block = function.body.as_ref()?;
+                Some(HirToMir::new(function).lower_body(block))
+            })
+            .collect();
+        Package { bodies }
+    }
+
+    fn new(function: &'a Function) -> Self {
+        // C has no shadowing within a function, so repeated names get a number, starting with
+        // the id of their local
+        let mut names = HashSet::new();
+        let locals = (function.locals.iter().enumerate())
+            .map(|(id, local)| {
+                let mut name = local.name.clone();
+                let mut number = id;
+                while !names.insert(name.clone()) {
+                    name = format!("{}__{}", local.name, number);
+                    number += 1;
+                }
+                LocalDecl {
+                    name,
+                    ty: local.ty.clone(),
+                    on_stack: false,
+                }
+            })
+            .collect();
+        Self {
+            function,
+            locals,
+            blocks: Vec::new(),
+            current: BlockId(0),
+            loops: Vec::new(),
+        }
+    }
+
+    fn lower_body(mut self, block: &Block) -> Body {
+        self.current = self.new_block();
+        self.lower_block(block);
+        // the end of a function returning a value is only reached if it loops forever
+        let end = match self.function.ret_ty {
+            Ty::Unit => Terminator::Return(None),
+            _ => Terminator::Unreachable,
+        };
+        self.set_terminator(&self.function.location, end);
+        let blocks = self.blocks.into_iter().map(|block| BasicBlock {
+            stmts: block.stmts,
+            terminator: block.terminator.expect("Every block is terminated"),
+        });
+        let mut body = Body {
+            locals: self.locals,
+            blocks: blocks.collect(),
+        };
+        Self::remove_unreachable_blocks(&mut body);
+        body
+    }
+
+    /// Statements after a `return`, `break` or `continue` end up in blocks no jump leads to.
+    fn remove_unreachable_blocks(body: &mut Body) {
+        let mut reachable = vec![false; body.blocks.len()];
+        let mut work = vec![BlockId(0)];
+        while let Some(id) = work.pop() {
+            if !std::mem::replace(&mut reachable[id.0], true) {
+                work.extend(body.block(id).terminator.value.successors());
+            }
+        }
+        let mut renamed = HashMap::new();
+        let blocks = std::mem::take(&mut body.blocks);
+        for (id, block) in blocks.into_iter().enumerate() {
+            if reachable[id] {
+                renamed.insert(BlockId(id), BlockId(body.blocks.len()));
+                body.blocks.push(block);
+            }
+        }
+        for block in &mut body.blocks {
+            for target in block.terminator.value.successors_mut() {
+                *target = renamed[target];
+            }
+        }
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PartialBlock {
+            stmts: Vec::new(),
+            terminator: None,
+        });
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn set_terminator<T>(&mut self, at: &Located<T>, terminator: Terminator) {
+        let terminator = Located::new(at.source, at.span.clone(), terminator);
+        self.blocks[self.current.0].terminator = Some(terminator);
+    }
+
+    /// Ends the current block. The statements that follow go to a new block, which only jumps
+    /// can lead to.
+    fn terminate<T>(&mut self, at: &Located<T>, terminator: Terminator) {
+        self.set_terminator(at, terminator);
+        self.current = self.new_block();
+    }
+
+    /// Ends the current block with a jump to `target` and continues in it.
+    fn goto<T>(&mut self, at: &Located<T>, target: BlockId) {
+        self.set_terminator(at, Terminator::Goto(target));
+        self.current = target;
+    }
+
+    fn push<T>(&mut self, at: &Located<T>, stmt: Statement) {
+        let stmt = Located::new(at.source, at.span.clone(), stmt);
+        self.blocks[self.current.0].stmts.push(stmt);
+    }
+
+    fn temporary(&mut self, ty: Ty) -> LocalId {
+        let id = self.locals.len();
+        self.locals.push(LocalDecl {
+            name: format!("_t{}", id),
+            ty,
+            on_stack: false,
+        });
+        LocalId(id)
+    }
+
+    fn lower_block(&mut self, block: &Block) {
+        for stmt in &block.stmts {
+            self.lower_stmt(stmt);
+        }
+    }
+
+    fn lower_stmt(&mut self, stmt: &StmtS) {
+        match &stmt.value {
+            Stmt::Block(block) => self.lower_block(block),
+            // values that are not used are still stored, since their type decides how they
+            // are computed
+            Stmt::Expr(expr) if expr.value.ty == Ty::Unit => {
+                let rvalue = self.lower_rvalue(expr);
+                self.push(expr, Statement::Eval(rvalue));
+            }
+            Stmt::Expr(expr) => {
+                self.lower_operand(expr);
+            }
+            Stmt::Let(local, value) => {
+                let rvalue = self.lower_rvalue(value);
+                self.push(value, Statement::Assign(Place::local(*local), rvalue));
+            }
+            Stmt::Assign(target, value) => {
+                let place = self.lower_place(target);
+                let rvalue = self.lower_rvalue(value);
+                self.push(value, Statement::Assign(place, rvalue));
+            }
+            Stmt::If(cond, then, else_) => {
+                let cond = self.lower_operand(cond);
+                let then_block = self.new_block();
+                let else_block = self.new_block();
+                let join = match else_ {
+                    Some(_) => self.new_block(),
+                    None => else_block,
+                };
+                self.set_terminator(stmt, Terminator::Branch(cond, then_block, else_block));
+                self.current = then_block;
+                self.lower_block(then);
+                self.goto(stmt, join);
+                if let Some(else_) = else_ {
+                    self.current = else_block;
+                    self.lower_block(else_);
+                    self.goto(stmt, join);
+                }
+            }
+            Stmt::While(w) => self.lower_while(stmt, w),
+            Stmt::Match(m) => self.lower_match(stmt, m),
+            Stmt::Return(Some(value)) if value.value.ty == Ty::Unit => {
+                let rvalue = self.lower_rvalue(value);
+                self.push(value, Statement::Eval(rvalue));
+                self.terminate(stmt, Terminator::Return(None));
+            }
+            Stmt::Return(value) => {
+                let value = value.as_ref().map(|v| self.lower_operand(v));
+                self.terminate(stmt, Terminator::Return(value));
+            }
+            Stmt::Break => {
+                let target = self
+                    .loops
+                    .last()
+                    .expect("break is only used in loops")
+                    .break_;
+                self.terminate(stmt, Terminator::Goto(target));
+            }
+            Stmt::Continue => {
+                let loop_ = self.loops.last().expect("continue is only used in loops");
+                self.terminate(stmt, Terminator::Goto(loop_.continue_));
+            }
+        }
+    }
+
+    /// Input:
+    ///     while (cond) { body } step { step }
+    /// Output:
+    ///     bb0: goto bb1
+    ///     bb1: _t0 = cond; branch _t0, bb2, bb4
+    ///     bb2: body; goto bb3
+    ///     bb3: step; goto bb1
+    ///     bb4: ...
+    fn lower_while(&mut self, stmt: &StmtS, w: &While) {
+        let cond_block = self.new_block();
+        self.goto(stmt, cond_block);
+        let cond = self.lower_operand(&w.cond);
+        let body_block = self.new_block();
+        let step_block = match w.step {
+            Some(_) => self.new_block(),
+            None => cond_block,
+        };
+        let exit = self.new_block();
+        self.set_terminator(&w.cond, Terminator::Branch(cond, body_block, exit));
+
+        self.current = body_block;
+        self.loops.push(LoopTargets {
+            continue_: step_block,
+            break_: exit,
+        });
+        self.lower_block(&w.body);
+        self.loops.pop();
+        self.goto(stmt, step_block);
+        if let Some(step) = &w.step {
+            self.lower_stmt(step);
+            self.goto(step, cond_block);
+        }
+        self.current = exit;
+    }
+
+    /// Every arm gets a block that binds the payload of its variant and jumps to the end of the
+    /// match once its body is done.
+    fn lower_match(&mut self, stmt: &StmtS, m: &Match) {
+        let join = self.new_block();
+        let mut targets = Vec::new();
+        let mut otherwise = join;
+        let switch_block = self.current;
+        for arm in &m.arms {
+            let arm_block = self.new_block();
+            self.current = arm_block;
+            match &arm.pattern {
+                Pattern::Wildcard => otherwise = arm_block,
+                Pattern::Variant(variant, bindings) => {
+                    targets.push((*variant, arm_block));
+                    // the payload is bound where the body starts, an empty body needs none of it
+                    if let Some(at) = arm.body.stmts.first() {
+                        for (index, binding) in bindings.iter().enumerate() {
+                            let payload = Place::local(m.scrutinee)
+                                .project(Projection::Payload(*variant, index));
+                            let rvalue = Rvalue::Use(Operand::Copy(payload));
+                            self.push(at, Statement::Assign(Place::local(*binding), rvalue));
+                        }
+                    }
+                }
+            }
+            self.lower_block(&arm.body);
+            self.goto(stmt, join);
+        }
+        self.current = switch_block;
+        let switch = Terminator::Switch {
+            scrutinee: m.scrutinee,
+            enum_: m.enum_,
+            targets,
+            otherwise,
+        };
+        self.set_terminator(stmt, switch);
+        self.current = join;
+    }
+
+    /// Lowers an expression to a value that can be used without computing anything.
+    fn lower_operand(&mut self, expr: &ExprS) -> Operand {
+        match &expr.value.kind {
+            ExprKind::Literal(literal) => Operand::Constant(literal.clone(), expr.value.ty.clone()),
+            // Variables can only be changed by the function itself, so they can be read
+            // directly. Fields and elements are read right away, since calls can change them.
+            ExprKind::Local(_) | ExprKind::Captured(_) => Operand::Copy(self.lower_variable(expr)),
+            ExprKind::Infix(lt, op @ (OpInfix::LogicAnd | OpInfix::LogicOr), rt) => {
+                self.lower_short_circuit(expr, lt, *op, rt)
+            }
+            _ => {
+                let rvalue = self.lower_rvalue(expr);
+                let temp = self.temporary(expr.value.ty.clone());
+                self.push(expr, Statement::Assign(Place::local(temp), rvalue));
+                Operand::Copy(Place::local(temp))
+            }
+        }
+    }
+
+    /// Input:
+    ///     a && b
+    /// Output:
+    ///     bb0: _t0 = a; branch _t0, bb1, bb2
+    ///     bb1: _t0 = b; goto bb2
+    ///     bb2: ...
+    fn lower_short_circuit(
+        &mut self,
+        expr: &ExprS,
+        lt: &ExprS,
+        op: OpInfix,
+        rt: &ExprS,
+    ) -> Operand {
+        let result = self.temporary(Ty::Bool);
+        let left = self.lower_rvalue(lt);
+        self.push(lt, Statement::Assign(Place::local(result), left));
+        let rt_block = self.new_block();
+        let join = self.new_block();
+        let cond = Operand::Copy(Place::local(result));
+        let terminator = match op {
+            OpInfix::LogicAnd => Terminator::Branch(cond, rt_block, join),
+            _ => Terminator::Branch(cond, join, rt_block),
+        };
+        self.set_terminator(expr, terminator);
+        self.current = rt_block;
+        let right = self.lower_rvalue(rt);
+        self.push(expr, Statement::Assign(Place::local(result), right));
+        self.goto(expr, join);
+        Operand::Copy(Place::local(result))
+    }
+
+    fn lower_operands(&mut self, exprs: &[ExprS]) -> Vec<Operand> {
+        exprs.iter().map(|e| self.lower_operand(e)).collect()
+    }
+
+    fn lower_rvalue(&mut self, expr: &ExprS) -> Rvalue {
+        match &expr.value.kind {
+            ExprKind::Literal(_) | ExprKind::Local(_) | ExprKind::Captured(_) => {
+                Rvalue::Use(self.lower_operand(expr))
+            }
+            ExprKind::FunctionRef(id) => Rvalue::FunctionRef(*id),
+            ExprKind::BuiltinRef(builtin) => Rvalue::BuiltinRef(builtin),
+            ExprKind::Call(id, args) => Rvalue::Call(*id, self.lower_operands(args)),
+            ExprKind::CallBuiltin(builtin, args) => {
+                Rvalue::CallBuiltin(builtin, self.lower_operands(args))
+            }
+            ExprKind::CallClosure(callee, args) => {
+                let callee = self.lower_operand(callee);
+                Rvalue::CallClosure(callee, self.lower_operands(args))
+            }
+            ExprKind::Intrinsic(intrinsic, args) => {
+                Rvalue::Intrinsic(*intrinsic, self.lower_operands(args))
+            }
+            ExprKind::Field(..) | ExprKind::Index(..) => {
+                Rvalue::Use(Operand::Copy(self.lower_place(expr)))
+            }
+            ExprKind::Prefix(op, rt) => Rvalue::Prefix(*op, self.lower_operand(rt)),
+            ExprKind::Infix(_, OpInfix::LogicAnd | OpInfix::LogicOr, _) => {
+                Rvalue::Use(self.lower_operand(expr))
+            }
+            ExprKind::Infix(lt, op, rt) => {
+                let left = self.lower_operand(lt);
+                let right = self.lower_operand(rt);
+                Rvalue::Infix(left, *op, right)
+            }
+            ExprKind::Struct(id, values) => Rvalue::Struct(*id, self.lower_operands(values)),
+            ExprKind::Variant(id, variant, payload) => {
+                Rvalue::Variant(*id, *variant, self.lower_operands(payload))
+            }
+            ExprKind::Array(elements) => Rvalue::Array(self.lower_operands(elements)),
+            ExprKind::Unwrap(value) => Rvalue::Unwrap(self.lower_operand(value)),
+            ExprKind::Closure(id, values) => Rvalue::Closure(*id, self.lower_operands(values)),
+        }
+    }
+
+    /// Lowers a variable that is read. A variable that a comparison with `nil` narrowed is read
+    /// as a temporary of the narrowed type, so every operand has the type of its expression.
+    fn lower_variable(&mut self, expr: &ExprS) -> Place {
+        let place = self.lower_place(expr);
+        let declared = match place.base {
+            PlaceBase::Local(id) if place.projections.is_empty() => &self.locals[id.0].ty,
+            PlaceBase::Captured(index) if place.projections.is_empty() => {
+                match &self.function.kind {
+                    FunctionKind::Lambda { captures } => &captures[index].ty,
+                    _ => unreachable!("Only lambdas capture variables"),
+                }
+            }
+            _ => return place,
+        };
+        if *declared == expr.value.ty {
+            return place;
+        }
+        let temp = self.temporary(expr.value.ty.clone());
+        let rvalue = Rvalue::Use(Operand::Copy(place));
+        self.push(expr, Statement::Assign(Place::local(temp), rvalue));
+        Place::local(temp)
+    }
+
+    /// Lowers an expression that is assigned to or read from memory. Other expressions are
+    /// stored in a temporary first.
+    fn lower_place(&mut self, expr: &ExprS) -> Place {
+        match &expr.value.kind {
+            ExprKind::Local(id) => Place::local(*id),
+            ExprKind::Captured(index) => Place {
+                base: PlaceBase::Captured(*index),
+                projections: Vec::new(),
+            },
+            ExprKind::Field(object, field) => self
+                .lower_variable(object)
+                .project(Projection::Field(*field)),
+            ExprKind::Index(object, index) => {
+                let place = self.lower_variable(object);
+                let at = Located::empty(index.source, index.span.clone());
+                let index = self.lower_operand(index);
+                place.project(Projection::Index(index, at))
+            }
+            _ => match self.lower_operand(expr) {
+                Operand::Copy(place) => place,
+                Operand::Constant(..) => unreachable!("Constants are not stored in memory"),
+            },
+        }
+    }
+}
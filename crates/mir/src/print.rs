@@ -0,0 +1,283 @@
+use std::fmt::Write;
+
+use hir::ir::{expr::Literal, ty::Ty, Function, FunctionId, FunctionKind, Package as Hir};
+use syntax::builtins::Intrinsic;
+
+use crate::ir::{
+    BasicBlock, Body, Operand, Package, Place, PlaceBase, Projection, Rvalue, Statement, Terminator,
+};
+
+/// Writes the MIR of a package as text, one function after the other. A function lists its
+/// locals and then its blocks, like `bb0: { _t2 = a + b; return _t2; }`.
+pub struct MirPrinter<'a> {
+    hir: &'a Hir,
+    out: String,
+}
+
+impl<'a> MirPrinter<'a> {
+    pub fn print(hir: &'a Hir, mir: &Package) -> String {
+        let mut printer = Self {
+            hir,
+            out: String::new(),
+        };
+        for id in hir.function_ids() {
+            if let Some(body) = mir.body(id) {
+                printer.print_body(id, body);
+            }
+        }
+        printer.out
+    }
+
+    fn print_body(&mut self, id: FunctionId, body: &Body) {
+        let function = self.hir.function(id);
+        let params = function
+            .params
+            .iter()
+            .map(|p| format!("{}: {}", body.local(*p).name, self.ty(&body.local(*p).ty)))
+            .collect::<Vec<_>>();
+        let ret_ty = self.ty(&function.ret_ty);
+        let _ = writeln!(
+            self.out,
+            "fn {}({}) -> {} {{",
+            self.function_name(id),
+            params.join(", "),
+            ret_ty
+        );
+        if let FunctionKind::Lambda { captures } = &function.kind {
+            for capture in captures {
+                let ty = self.ty(&capture.ty);
+                let _ = writeln!(self.out, "    captured {}: {};", capture.name, ty);
+            }
+        }
+        let locals = (body.locals.iter().enumerate())
+            .filter(|(id, _)| !function.params.iter().any(|p| p.0 == *id));
+        for (_, local) in locals {
+            let ty = self.ty(&local.ty);
+            let stack = if local.on_stack {
+                " // on the stack"
+            } else {
+                ""
+            };
+            let _ = writeln!(self.out, "    let {}: {};{}", local.name, ty, stack);
+        }
+        for id in body.block_ids() {
+            let _ = writeln!(self.out, "\n    bb{}: {{", id.0);
+            self.print_block(body, function, body.block(id));
+            let _ = writeln!(self.out, "    }}");
+        }
+        let _ = writeln!(self.out, "}}\n");
+    }
+
+    fn print_block(&mut self, body: &Body, function: &Function, block: &BasicBlock) {
+        for stmt in &block.stmts {
+            let stmt = match &stmt.value {
+                Statement::Assign(place, rvalue) => format!(
+                    "{} = {}",
+                    self.place(body, function, place),
+                    self.rvalue(body, function, rvalue)
+                ),
+                Statement::Eval(rvalue) => self.rvalue(body, function, rvalue),
+            };
+            let _ = writeln!(self.out, "        {};", stmt);
+        }
+        let terminator = match &block.terminator.value {
+            Terminator::Goto(target) => format!("goto bb{}", target.0),
+            Terminator::Branch(cond, then, else_) => format!(
+                "branch {}, bb{}, bb{}",
+                self.operand(body, function, cond),
+                then.0,
+                else_.0
+            ),
+            Terminator::Switch {
+                scrutinee,
+                enum_,
+                targets,
+                otherwise,
+            } => {
+                let variants = &self.hir.enum_(*enum_).variants;
+                let targets = targets
+                    .iter()
+                    .map(|(variant, target)| {
+                        format!("{} => bb{}", variants[*variant].name, target.0)
+                    })
+                    .chain(std::iter::once(format!("_ => bb{}", otherwise.0)))
+                    .collect::<Vec<_>>();
+                let scrutinee = &body.local(*scrutinee).name;
+                format!("switch {} {{ {} }}", scrutinee, targets.join(", "))
+            }
+            Terminator::Return(Some(value)) => {
+                format!("return {}", self.operand(body, function, value))
+            }
+            Terminator::Return(None) => "return".to_string(),
+            Terminator::Unreachable => "unreachable".to_string(),
+        };
+        let _ = writeln!(self.out, "        {};", terminator);
+    }
+
+    fn rvalue(&self, body: &Body, function: &Function, rvalue: &Rvalue) -> String {
+        let operands = |operands: &[Operand]| {
+            operands
+                .iter()
+                .map(|op| self.operand(body, function, op))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match rvalue {
+            Rvalue::Use(op) => self.operand(body, function, op),
+            Rvalue::FunctionRef(id) => format!("fn {}", self.function_name(*id)),
+            Rvalue::BuiltinRef(builtin) => format!("fn {}", builtin.name),
+            Rvalue::Call(id, args) => {
+                format!("call {}({})", self.function_name(*id), operands(args))
+            }
+            Rvalue::CallBuiltin(builtin, args) => {
+                format!("call {}({})", builtin.name, operands(args))
+            }
+            Rvalue::CallClosure(callee, args) => format!(
+                "call_closure {}({})",
+                self.operand(body, function, callee),
+                operands(args)
+            ),
+            Rvalue::Intrinsic(intrinsic, args) => {
+                let name = match intrinsic {
+                    Intrinsic::Len => "len",
+                    Intrinsic::Push => "push",
+                    Intrinsic::Pop => "pop",
+                };
+                format!("{}({})", name, operands(args))
+            }
+            Rvalue::Prefix(op, rt) => format!("{}{}", op, self.operand(body, function, rt)),
+            Rvalue::Infix(lt, op, rt) => format!(
+                "{} {} {}",
+                self.operand(body, function, lt),
+                op,
+                self.operand(body, function, rt)
+            ),
+            Rvalue::Struct(id, values) => {
+                let s = self.hir.struct_(*id);
+                let fields = (s.fields.iter().zip(values))
+                    .map(|(field, value)| {
+                        format!("{}: {}", field.name, self.operand(body, function, value))
+                    })
+                    .collect::<Vec<_>>();
+                format!("{} {{ {} }}", self.ty(&Ty::Struct(*id)), fields.join(", "))
+            }
+            Rvalue::Variant(id, variant, payload) => {
+                let e = self.hir.enum_(*id);
+                let name = format!("{}::{}", e.name, e.variants[*variant].name);
+                match payload.is_empty() {
+                    true => name,
+                    false => format!("{}({})", name, operands(payload)),
+                }
+            }
+            Rvalue::Array(elements) => format!("[{}]", operands(elements)),
+            Rvalue::Unwrap(value) => format!("{}!", self.operand(body, function, value)),
+            Rvalue::Closure(id, values) => {
+                format!("closure {}({})", self.function_name(*id), operands(values))
+            }
+        }
+    }
+
+    fn operand(&self, body: &Body, function: &Function, operand: &Operand) -> String {
+        match operand {
+            Operand::Copy(place) => self.place(body, function, place),
+            Operand::Constant(literal, _) => match literal {
+                Literal::Bool(b) => format!("const {}", b),
+                Literal::Int(i) => format!("const {}", i),
+                Literal::Float(f) => format!("const {:?}", f),
+                Literal::Str(s) => format!("const {:?}", s),
+                Literal::Nil => "const nil".to_string(),
+            },
+        }
+    }
+
+    /// _match_2.Circle.0, p.x, l[_t3]
+    fn place(&self, body: &Body, function: &Function, place: &Place) -> String {
+        let mut out = match place.base {
+            PlaceBase::Local(id) => body.local(id).name.clone(),
+            PlaceBase::Captured(index) => match &function.kind {
+                FunctionKind::Lambda { captures } => format!("env.{}", captures[index].name),
+                _ => unreachable!("Only lambdas capture variables"),
+            },
+        };
+        let mut ty = body.place_ty(
+            self.hir,
+            function,
+            &Place {
+                base: place.base,
+                projections: Vec::new(),
+            },
+        );
+        for projection in &place.projections {
+            match (projection, &ty) {
+                (Projection::Field(field), Ty::Struct(id)) => {
+                    let field = &self.hir.struct_(*id).fields[*field];
+                    let _ = write!(out, ".{}", field.name);
+                    ty = field.ty.clone();
+                }
+                (Projection::Index(index, _), Ty::Array(elem, _) | Ty::List(elem)) => {
+                    let _ = write!(out, "[{}]", self.operand(body, function, index));
+                    ty = *elem.clone();
+                }
+                (Projection::Payload(variant, index), Ty::Enum(id)) => {
+                    let variant = &self.hir.enum_(*id).variants[*variant];
+                    let _ = write!(out, ".{}.{}", variant.name, index);
+                    ty = variant.payload[*index].clone();
+                }
+                _ => unreachable!("Projections match the type they are applied to"),
+            }
+        }
+        out
+    }
+
+    /// geometry.area, Point.len, first<i64, str>
+    fn function_name(&self, id: FunctionId) -> String {
+        let function = self.hir.function(id);
+        let path = &self.hir.module(function.module).path;
+        let name = match &function.kind {
+            FunctionKind::Method { of } => return format!("{}.{}", self.ty(of), function.name),
+            FunctionKind::Instance { type_args } => {
+                let args = type_args.iter().map(|ty| self.ty(ty)).collect::<Vec<_>>();
+                format!("{}<{}>", function.name, args.join(", "))
+            }
+            _ => function.name.clone(),
+        };
+        match path.is_empty() {
+            true => name,
+            false => format!("{}.{}", path.join("."), name),
+        }
+    }
+
+    fn ty(&self, ty: &Ty) -> String {
+        match ty {
+            Ty::Unit => "()".to_string(),
+            Ty::Bool => "bool".to_string(),
+            Ty::I8 => "i8".to_string(),
+            Ty::I32 => "i32".to_string(),
+            Ty::I64 => "i64".to_string(),
+            Ty::U8 => "u8".to_string(),
+            Ty::U32 => "u32".to_string(),
+            Ty::U64 => "u64".to_string(),
+            Ty::F32 => "f32".to_string(),
+            Ty::F64 => "f64".to_string(),
+            Ty::Str => "str".to_string(),
+            Ty::Struct(id) => {
+                let s = self.hir.struct_(*id);
+                match s.is_instance() {
+                    true => {
+                        let args = s.type_args.iter().map(|ty| self.ty(ty)).collect::<Vec<_>>();
+                        format!("{}<{}>", s.name, args.join(", "))
+                    }
+                    false => s.name.clone(),
+                }
+            }
+            Ty::Enum(id) => self.hir.enum_(*id).name.clone(),
+            Ty::Array(elem, len) => format!("[{}; {}]", self.ty(elem), len),
+            Ty::List(elem) => format!("List<{}>", self.ty(elem)),
+            Ty::Optional(inner) => format!("{}?", self.ty(inner)),
+            Ty::Function(params, ret) => {
+                let params = params.iter().map(|p| self.ty(p)).collect::<Vec<_>>();
+                format!("fun({}) -> {}", params.join(", "), self.ty(ret))
+            }
+        }
+    }
+}
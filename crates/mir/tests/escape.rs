@@ -0,0 +1,197 @@
+use base::source_id::SourceId;
+use hir::lower::AstToHir;
+use mir::{escape::EscapeAnalysis, lower::HirToMir};
+use resolve::resolver::Resolver;
+use syntax::{
+    ast::{Module, Package},
+    parser::Parser,
+};
+use typeck::checker::TypeChecker;
+
+/// The names of the locals of the function `name` whose objects are stored on the stack, in a
+/// package made of the single module `source`.
+fn on_stack(source: &str, name: &str) -> Vec<String> {
+    let source_id = SourceId::from_path("main.pine");
+    let (program, errors) = Parser::parse_file(source_id, source);
+    assert!(errors.is_empty(), "The test program has syntax errors");
+    let mut package = Package {
+        modules: vec![Module {
+            path: vec![],
+            source: source_id,
+            program,
+        }],
+    };
+    assert!(
+        Resolver::resolve(&mut package).is_empty(),
+        "The test program has name errors"
+    );
+    let (types, errors) = TypeChecker::check(&package);
+    assert!(errors.is_empty(), "The test program has type errors");
+    let hir = AstToHir::lower(&package, &types);
+    let mut mir = HirToMir::lower(&hir);
+    EscapeAnalysis::run(&hir, &mut mir);
+
+    let id = hir
+        .function_ids()
+        .find(|id| hir.function(*id).name == name)
+        .expect("The function is declared");
+    let body = mir.body(id).expect("The function has a body");
+    (body.locals.iter())
+        .filter(|local| local.on_stack)
+        .map(|local| local.name.clone())
+        .collect()
+}
+
+/// A program with the type objects the tests use, followed by `main`.
+fn program(main: &str) -> String {
+    format!(
+        "
+        type Point = {{ x: i64, y: i64 }}
+        type Holder = {{ p: Point? }}
+        type Empty = {{ }}
+
+        fun take(p: Point) -> () {{}}
+
+        fun main() -> () {{
+            {}
+        }}
+        ",
+        main
+    )
+}
+
+#[test]
+fn object_only_used_through_fields_stays_on_the_stack() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        p.x = p.y + 1;
+        if (p.x > 2) {
+            p.y = 0;
+        }
+        print_int(p.x + p.y);
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), vec!["p"]);
+}
+
+#[test]
+fn returned_object_escapes() {
+    let source = "
+        type Point = { x: i64, y: i64 }
+
+        fun make() -> Point {
+            let p = Point { x: 1, y: 2 };
+            p.x = 3;
+            return p;
+        }
+
+        fun main() -> () {
+            print_int(make().x);
+        }
+    ";
+    assert_eq!(on_stack(source, "make"), Vec::<String>::new());
+}
+
+#[test]
+fn object_passed_to_a_function_escapes() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        take(p);
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), Vec::<String>::new());
+}
+
+#[test]
+fn object_stored_in_a_field_escapes() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        let h = Holder { p: nil };
+        h.p = p;
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), vec!["h"]);
+}
+
+#[test]
+fn object_stored_in_an_element_escapes() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        let q = Point { x: 3, y: 4 };
+        let ps = [q];
+        ps[0] = p;
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), Vec::<String>::new());
+}
+
+#[test]
+fn object_stored_in_another_variable_escapes() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        let q = p;
+        q.x = 2;
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), Vec::<String>::new());
+}
+
+#[test]
+fn object_captured_by_a_closure_escapes() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        let f = fun() -> i64 { return p.x; };
+        print_int(f());
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), Vec::<String>::new());
+}
+
+#[test]
+fn compared_object_escapes() {
+    let source = program(
+        "
+        let p = Point { x: 1, y: 2 };
+        let q = Point { x: 1, y: 2 };
+        if (p == q) {
+            print_int(1);
+        }
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), Vec::<String>::new());
+}
+
+#[test]
+fn parameters_are_never_on_the_stack() {
+    let source = "
+        type Point = { x: i64, y: i64 }
+
+        fun get(p: Point) -> i64 {
+            p.x = 1;
+            return p.x;
+        }
+
+        fun main() -> () {
+            print_int(get(Point { x: 1, y: 2 }));
+        }
+    ";
+    assert_eq!(on_stack(source, "get"), Vec::<String>::new());
+}
+
+#[test]
+fn objects_without_fields_are_never_on_the_stack() {
+    let source = program(
+        "
+        let e = Empty { };
+        let p = Point { x: 1, y: 2 };
+        print_int(p.x);
+        ",
+    );
+    assert_eq!(on_stack(&source, "main"), vec!["p"]);
+}
@@ -0,0 +1,185 @@
+use hir::ir::{
+    expr::{ExprKind, Literal},
+    stmt::{Block, Stmt},
+    ty::Ty,
+    ExprS, Package, StmtS,
+};
+use syntax::ast::op::{OpInfix, OpPrefix};
+
+/// Replaces operators applied to literals with the literal they evaluate to, from the innermost
+/// expression outwards.
+///
+/// Folding never changes what a program does. Integer arithmetic wraps at the width of its type,
+/// like it does at runtime. Divisions by zero and floats that are not finite are not folded. A
+/// division by a constant zero is rejected by the C compiler, and is a runtime error in the
+/// interpreter.
+pub struct ConstFold;
+
+impl ConstFold {
+    pub fn run(package: &mut Package) {
+        for function in &mut package.functions {
+            if let Some(body) = &mut function.body {
+                Self::fold_block(body);
+            }
+        }
+    }
+
+    fn fold_block(block: &mut Block) {
+        for stmt in &mut block.stmts {
+            Self::fold_stmt(stmt);
+        }
+    }
+
+    fn fold_stmt(stmt: &mut StmtS) {
+        match &mut stmt.value {
+            Stmt::Block(block) => Self::fold_block(block),
+            Stmt::Expr(expr) | Stmt::Let(_, expr) | Stmt::Return(Some(expr)) => {
+                Self::fold_expr(expr)
+            }
+            Stmt::Assign(place, value) => {
+                Self::fold_expr(place);
+                Self::fold_expr(value);
+            }
+            Stmt::If(cond, then, else_) => {
+                Self::fold_expr(cond);
+                Self::fold_block(then);
+                if let Some(else_) = else_ {
+                    Self::fold_block(else_);
+                }
+            }
+            Stmt::While(w) => {
+                Self::fold_expr(&mut w.cond);
+                Self::fold_block(&mut w.body);
+                if let Some(step) = &mut w.step {
+                    Self::fold_stmt(step);
+                }
+            }
+            Stmt::Match(m) => {
+                for arm in &mut m.arms {
+                    Self::fold_block(&mut arm.body);
+                }
+            }
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+
+    fn fold_expr(expr: &mut ExprS) {
+        for operand in expr.value.kind.operands_mut() {
+            Self::fold_expr(operand);
+        }
+        let ty = &expr.value.ty;
+        let folded = match &expr.value.kind {
+            ExprKind::Prefix(op, rt) => match &rt.value.kind {
+                ExprKind::Literal(rt) => Self::fold_prefix(*op, rt, ty).map(ExprKind::Literal),
+                _ => None,
+            },
+            ExprKind::Infix(lt, op, rt) => match (&lt.value.kind, &rt.value.kind) {
+                (ExprKind::Literal(l), ExprKind::Literal(r)) => {
+                    Self::fold_infix(l, *op, r, &lt.value.ty, ty).map(ExprKind::Literal)
+                }
+                // the right side of `and` and `or` only runs if the left side does not decide
+                (ExprKind::Literal(Literal::Bool(b)), rt) => match (op, b) {
+                    (OpInfix::LogicAnd, false) | (OpInfix::LogicOr, true) => {
+                        Some(ExprKind::Literal(Literal::Bool(*b)))
+                    }
+                    (OpInfix::LogicAnd, true) | (OpInfix::LogicOr, false) => Some(rt.clone()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(kind) = folded {
+            expr.value.kind = kind;
+        }
+    }
+
+    fn fold_prefix(op: OpPrefix, rt: &Literal, ty: &Ty) -> Option<Literal> {
+        match (op, rt) {
+            (OpPrefix::Not, Literal::Bool(b)) => Some(Literal::Bool(!b)),
+            (OpPrefix::Negate, Literal::Int(i)) => Self::int(-(*i as i128), ty),
+            (OpPrefix::Negate, Literal::Float(f)) => Self::float(-f, ty),
+            _ => None,
+        }
+    }
+
+    /// `operand_ty` is the type of both operands, `ty` the type of the result.
+    fn fold_infix(
+        lt: &Literal,
+        op: OpInfix,
+        rt: &Literal,
+        operand_ty: &Ty,
+        ty: &Ty,
+    ) -> Option<Literal> {
+        let compare = |ordering: std::cmp::Ordering| {
+            let result = match op {
+                OpInfix::Less => ordering.is_lt(),
+                OpInfix::LessEqual => ordering.is_le(),
+                OpInfix::Greater => ordering.is_gt(),
+                OpInfix::GreaterEqual => ordering.is_ge(),
+                OpInfix::Equal => ordering.is_eq(),
+                OpInfix::NotEqual => ordering.is_ne(),
+                _ => return None,
+            };
+            Some(Literal::Bool(result))
+        };
+        match (lt, rt) {
+            (Literal::Int(a), Literal::Int(b)) => {
+                let (a, b) = (*a as i128, *b as i128);
+                match op {
+                    OpInfix::Add => Self::int(a + b, ty),
+                    OpInfix::Subtract => Self::int(a - b, ty),
+                    OpInfix::Multiply => Self::int(a * b, ty),
+                    OpInfix::Divide | OpInfix::Modulo if b == 0 => None,
+                    OpInfix::Divide => Self::int(a / b, ty),
+                    OpInfix::Modulo => Self::int(a % b, ty),
+                    _ => compare(a.cmp(&b)),
+                }
+            }
+            // floats of 32 bits compute with less precision than the folding would
+            (Literal::Float(a), Literal::Float(b)) if *operand_ty == Ty::F64 => match op {
+                OpInfix::Add => Self::float(a + b, ty),
+                OpInfix::Subtract => Self::float(a - b, ty),
+                OpInfix::Multiply => Self::float(a * b, ty),
+                OpInfix::Divide => Self::float(a / b, ty),
+                _ => compare(a.partial_cmp(b)?),
+            },
+            (Literal::Bool(a), Literal::Bool(b)) => match op {
+                OpInfix::LogicAnd => Some(Literal::Bool(*a && *b)),
+                OpInfix::LogicOr => Some(Literal::Bool(*a || *b)),
+                OpInfix::Equal => Some(Literal::Bool(a == b)),
+                OpInfix::NotEqual => Some(Literal::Bool(a != b)),
+                _ => None,
+            },
+            (Literal::Str(a), Literal::Str(b)) => match op {
+                OpInfix::Add => Some(Literal::Str(format!("{}{}", a, b))),
+                _ => compare(a.cmp(b)),
+            },
+            _ => None,
+        }
+    }
+
+    /// The integer literal of the value wrapped at the width of the type, if it has one.
+    fn int(value: i128, ty: &Ty) -> Option<Literal> {
+        let value = match ty {
+            Ty::I8 => value as i8 as i64,
+            Ty::I32 => value as i32 as i64,
+            Ty::I64 => value as i64,
+            Ty::U8 => value as u8 as i64,
+            Ty::U32 => value as u32 as i64,
+            Ty::U64 => value as u64 as i64,
+            _ => return None,
+        };
+        match ty {
+            // the most negative value has no literal in C
+            Ty::I64 if value == i64::MIN => None,
+            // literals of larger values do not fit into an `i64`
+            Ty::U64 if value < 0 => None,
+            _ => Some(Literal::Int(value)),
+        }
+    }
+
+    fn float(value: f64, ty: &Ty) -> Option<Literal> {
+        (*ty == Ty::F64 && value.is_finite()).then_some(Literal::Float(value))
+    }
+}
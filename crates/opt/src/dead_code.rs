@@ -0,0 +1,84 @@
+use hir::ir::{
+    expr::{ExprKind, Literal},
+    stmt::{Block, Stmt},
+    Package, StmtS,
+};
+
+/// Removes the statements that never run: the branches of an `if` with a constant condition,
+/// loops whose condition is `false`, and the statements after a `return`, `break` or `continue`.
+pub struct DeadCode;
+
+impl DeadCode {
+    pub fn run(package: &mut Package) {
+        for function in &mut package.functions {
+            if let Some(body) = &mut function.body {
+                Self::eliminate_block(body);
+            }
+        }
+    }
+
+    fn eliminate_block(block: &mut Block) {
+        let mut stmts = Vec::with_capacity(block.stmts.len());
+        for stmt in std::mem::take(&mut block.stmts) {
+            let Some(live) = Self::eliminate_stmt(stmt) else {
+                continue;
+            };
+            let diverges = Self::diverges(&live);
+            stmts.push(live);
+            if diverges {
+                break;
+            }
+        }
+        block.stmts = stmts;
+    }
+
+    /// The statement without its dead code, or `None` if the statement never runs.
+    fn eliminate_stmt(mut stmt: StmtS) -> Option<StmtS> {
+        match &mut stmt.value {
+            Stmt::Block(block) => Self::eliminate_block(block),
+            Stmt::If(cond, then, else_) => {
+                if let ExprKind::Literal(Literal::Bool(b)) = cond.value.kind {
+                    let taken = match b {
+                        true => std::mem::take(then),
+                        false => else_.take()?,
+                    };
+                    stmt.value = Stmt::Block(taken);
+                    return Self::eliminate_stmt(stmt);
+                }
+                Self::eliminate_block(then);
+                if let Some(else_) = else_ {
+                    Self::eliminate_block(else_);
+                }
+            }
+            Stmt::While(w) => {
+                if let ExprKind::Literal(Literal::Bool(false)) = w.cond.value.kind {
+                    return None;
+                }
+                Self::eliminate_block(&mut w.body);
+            }
+            Stmt::Match(m) => {
+                for arm in &mut m.arms {
+                    Self::eliminate_block(&mut arm.body);
+                }
+            }
+            Stmt::Expr(_)
+            | Stmt::Let(..)
+            | Stmt::Assign(..)
+            | Stmt::Return(_)
+            | Stmt::Break
+            | Stmt::Continue => {}
+        }
+        Some(stmt)
+    }
+
+    /// Whether the statements after the statement never run.
+    fn diverges(stmt: &StmtS) -> bool {
+        let block_diverges = |block: &Block| block.stmts.last().is_some_and(Self::diverges);
+        match &stmt.value {
+            Stmt::Return(_) | Stmt::Break | Stmt::Continue => true,
+            Stmt::Block(block) => block_diverges(block),
+            Stmt::If(_, then, Some(else_)) => block_diverges(then) && block_diverges(else_),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,16 @@
+pub mod const_fold;
+pub mod dead_code;
+pub mod prune;
+
+use hir::ir::Package;
+
+use crate::{const_fold::ConstFold, dead_code::DeadCode, prune::Prune};
+
+/// Runs the optimizations on the HIR before it is lowered to MIR. Folding constants first lets
+/// dead code elimination see the conditions that are always true or false, and removing the
+/// dead code first lets pruning drop the functions only it referred to.
+pub fn optimize(package: &mut Package) {
+    ConstFold::run(package);
+    DeadCode::run(package);
+    Prune::run(package);
+}
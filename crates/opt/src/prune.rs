@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use hir::ir::{
+    expr::ExprKind,
+    stmt::{Block, Stmt},
+    ExprS, FunctionId, Item, Package, StmtS, StructId,
+};
+
+/// Removes the functions the program never calls or refers to from the items of their modules,
+/// so no code is generated for them, and marks the type objects the program never creates.
+///
+/// Everything `main` reaches is used. A package without `main` is left as it is.
+pub struct Prune {
+    functions: HashSet<FunctionId>,
+    structs: HashSet<StructId>,
+    pending: Vec<FunctionId>,
+}
+
+impl Prune {
+    pub fn run(package: &mut Package) {
+        let root = package
+            .modules
+            .last()
+            .expect("The root module is always loaded");
+        let main = root.items.iter().find_map(|item| match item {
+            Item::Function(id) if package.function(*id).name == "main" => Some(*id),
+            _ => None,
+        });
+        let Some(main) = main else {
+            return;
+        };
+
+        let mut prune = Self {
+            functions: HashSet::new(),
+            structs: HashSet::new(),
+            pending: Vec::new(),
+        };
+        prune.use_function(main);
+        while let Some(id) = prune.pending.pop() {
+            if let Some(body) = &package.function(id).body {
+                prune.visit_block(body);
+            }
+        }
+
+        for module in &mut package.modules {
+            module.items.retain(|item| match item {
+                Item::Function(id) => prune.functions.contains(id),
+                Item::Struct(_) | Item::Enum(_) => true,
+            });
+        }
+        for (id, s) in package.structs.iter_mut().enumerate() {
+            s.constructed = prune.structs.contains(&StructId(id));
+        }
+    }
+
+    fn use_function(&mut self, id: FunctionId) {
+        if self.functions.insert(id) {
+            self.pending.push(id);
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        for stmt in &block.stmts {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &StmtS) {
+        match &stmt.value {
+            Stmt::Block(block) => self.visit_block(block),
+            Stmt::Expr(expr) | Stmt::Let(_, expr) | Stmt::Return(Some(expr)) => {
+                self.visit_expr(expr)
+            }
+            Stmt::Assign(place, value) => {
+                self.visit_expr(place);
+                self.visit_expr(value);
+            }
+            Stmt::If(cond, then, else_) => {
+                self.visit_expr(cond);
+                self.visit_block(then);
+                if let Some(else_) = else_ {
+                    self.visit_block(else_);
+                }
+            }
+            Stmt::While(w) => {
+                self.visit_expr(&w.cond);
+                self.visit_block(&w.body);
+                if let Some(step) = &w.step {
+                    self.visit_stmt(step);
+                }
+            }
+            Stmt::Match(m) => {
+                for arm in &m.arms {
+                    self.visit_block(&arm.body);
+                }
+            }
+            Stmt::Return(None) | Stmt::Break | Stmt::Continue => {}
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &ExprS) {
+        match &expr.value.kind {
+            ExprKind::FunctionRef(id) | ExprKind::Call(id, _) | ExprKind::Closure(id, _) => {
+                self.use_function(*id)
+            }
+            ExprKind::Struct(id, _) => {
+                self.structs.insert(*id);
+            }
+            _ => {}
+        }
+        for operand in expr.value.kind.operands() {
+            self.visit_expr(operand);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Every test file uses only some of the helpers.
+#![allow(dead_code)]
+
+use base::source_id::SourceId;
+use hir::{
+    ir::{stmt::Stmt, ExprS, FunctionId, Package, StmtS},
+    lower::AstToHir,
+};
+use resolve::resolver::Resolver;
+use syntax::{
+    ast::{Module, Package as AstPackage},
+    parser::Parser,
+};
+use typeck::checker::TypeChecker;
+
+/// Lowers a package made of the single module `source` to the HIR, without optimizing it.
+pub fn lower(source: &str) -> Package {
+    let source_id = SourceId::from_path("main.pine");
+    let (program, errors) = Parser::parse_file(source_id, source);
+    assert!(errors.is_empty(), "The test program has syntax errors");
+    let mut package = AstPackage {
+        modules: vec![Module {
+            path: vec![],
+            source: source_id,
+            program,
+        }],
+    };
+    assert!(
+        Resolver::resolve(&mut package).is_empty(),
+        "The test program has name errors"
+    );
+    let (types, errors) = TypeChecker::check(&package);
+    assert!(errors.is_empty(), "The test program has type errors");
+    AstToHir::lower(&package, &types)
+}
+
+/// The function that is declared with `name`.
+pub fn function(package: &Package, name: &str) -> FunctionId {
+    package
+        .function_ids()
+        .find(|id| package.function(*id).name == name)
+        .expect("The function is declared")
+}
+
+/// The statements of the body of the function `name`.
+pub fn body<'a>(package: &'a Package, name: &str) -> &'a [StmtS] {
+    let function = package.function(function(package, name));
+    &function
+        .body
+        .as_ref()
+        .expect("The function has a body")
+        .stmts
+}
+
+/// The values of the `let` statements in the body of the function `name`, in order.
+pub fn let_values<'a>(package: &'a Package, name: &str) -> Vec<&'a ExprS> {
+    body(package, name)
+        .iter()
+        .filter_map(|stmt| match &stmt.value {
+            Stmt::Let(_, value) => Some(value),
+            _ => None,
+        })
+        .collect()
+}
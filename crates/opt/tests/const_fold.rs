@@ -0,0 +1,121 @@
+mod common;
+
+use common::{let_values, lower};
+use hir::ir::expr::{ExprKind, Literal};
+use opt::const_fold::ConstFold;
+
+/// The literals the `let` values of `main` fold to, `None` for values that are not folded.
+fn folded(source: &str) -> Vec<Option<Literal>> {
+    let mut package = lower(source);
+    ConstFold::run(&mut package);
+    let_values(&package, "main")
+        .into_iter()
+        .map(|value| match &value.value.kind {
+            ExprKind::Literal(literal) => Some(literal.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn folds_nested_arithmetic() {
+    let source = "
+        fun main() -> () {
+            let a = 2 * 3 + 4;
+            let b = -(7 - 10) % 2;
+            let c: f64 = 1.5 * 2.0;
+            let d = \"pi\" + \"ne\";
+        }
+    ";
+    assert_eq!(
+        folded(source),
+        vec![
+            Some(Literal::Int(10)),
+            Some(Literal::Int(1)),
+            Some(Literal::Float(3.0)),
+            Some(Literal::Str("pine".to_string())),
+        ]
+    );
+}
+
+#[test]
+fn wraps_at_the_width_of_the_type() {
+    let source = "
+        fun main() -> () {
+            let a: u8 = 200 + 100;
+            let b: i8 = 127 + 1;
+            let c: i32 = 2147483647 * 2;
+            let d: u32 = 0 - 1;
+            let e: u8 = 0 - 1;
+        }
+    ";
+    assert_eq!(
+        folded(source),
+        vec![
+            Some(Literal::Int(44)),
+            Some(Literal::Int(-128)),
+            Some(Literal::Int(-2)),
+            Some(Literal::Int(4294967295)),
+            Some(Literal::Int(255)),
+        ]
+    );
+}
+
+#[test]
+fn leaves_values_without_a_literal() {
+    let source = "
+        fun main() -> () {
+            let a = 1 / 0;
+            let b = 5 % 0;
+            let c: u64 = 9223372036854775807 + 1;
+            let d: f64 = 1.0 / 0.0;
+            let e: f32 = 0.1 + 0.2;
+        }
+    ";
+    assert_eq!(folded(source), vec![None; 5]);
+}
+
+#[test]
+fn folds_comparisons_and_logic() {
+    let source = "
+        fun main() -> () {
+            let a = 1 < 2 and 3 >= 4;
+            let b = !(\"a\" == \"b\");
+            let c = 2.0 > 1.0 or false;
+        }
+    ";
+    assert_eq!(
+        folded(source),
+        vec![
+            Some(Literal::Bool(false)),
+            Some(Literal::Bool(true)),
+            Some(Literal::Bool(true)),
+        ]
+    );
+}
+
+#[test]
+fn constant_left_side_decides_logic() {
+    let source = "
+        fun check() -> bool { return true; }
+
+        fun main() -> () {
+            let a = false and check();
+            let b = true or check();
+            let c = true and check();
+        }
+    ";
+    let mut package = lower(source);
+    ConstFold::run(&mut package);
+    let values = let_values(&package, "main");
+    assert!(matches!(
+        values[0].value.kind,
+        ExprKind::Literal(Literal::Bool(false))
+    ));
+    assert!(matches!(
+        values[1].value.kind,
+        ExprKind::Literal(Literal::Bool(true))
+    ));
+    // the call still runs, so only the operator is removed
+    assert!(matches!(values[2].value.kind, ExprKind::Call(..)));
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::{body, lower};
+use hir::ir::{
+    expr::{ExprKind, Literal},
+    stmt::{Block, Stmt},
+    ExprS, Package, StmtS,
+};
+use opt::{const_fold::ConstFold, dead_code::DeadCode};
+
+fn eliminate(source: &str) -> Package {
+    let mut package = lower(source);
+    ConstFold::run(&mut package);
+    DeadCode::run(&mut package);
+    package
+}
+
+/// The statements as a short text, to compare the shape of a body.
+fn shape(stmts: &[StmtS]) -> Vec<String> {
+    stmts.iter().map(stmt_shape).collect()
+}
+
+fn stmt_shape(stmt: &StmtS) -> String {
+    let block = |block: &Block| format!("{{{}}}", shape(&block.stmts).join(" "));
+    match &stmt.value {
+        Stmt::Block(b) => block(b),
+        Stmt::Expr(expr) => expr_shape(expr),
+        Stmt::Let(..) => "let".to_string(),
+        Stmt::Assign(..) => "assign".to_string(),
+        Stmt::If(_, then, None) => format!("if {}", block(then)),
+        Stmt::If(_, then, Some(else_)) => format!("if {} else {}", block(then), block(else_)),
+        Stmt::While(w) => format!("while {}", block(&w.body)),
+        Stmt::Match(_) => "match".to_string(),
+        Stmt::Return(_) => "return".to_string(),
+        Stmt::Break => "break".to_string(),
+        Stmt::Continue => "continue".to_string(),
+    }
+}
+
+/// `print_int(n)` prints as `n`, so the remaining calls show which statements are left.
+fn expr_shape(expr: &ExprS) -> String {
+    match &expr.value.kind {
+        ExprKind::CallBuiltin(_, args) => match &args[0].value.kind {
+            ExprKind::Literal(Literal::Int(i)) => i.to_string(),
+            _ => "call".to_string(),
+        },
+        _ => "expr".to_string(),
+    }
+}
+
+#[test]
+fn removes_statements_after_return() {
+    let source = "
+        fun f(x: i64) -> i64 {
+            print_int(1);
+            return x;
+            print_int(2);
+            return 0;
+        }
+
+        fun main() -> () {
+            print_int(f(1));
+            return;
+            print_int(3);
+        }
+    ";
+    let package = eliminate(source);
+    assert_eq!(shape(body(&package, "f")), vec!["1", "return"]);
+    assert_eq!(shape(body(&package, "main")), vec!["call", "return"]);
+}
+
+#[test]
+fn removes_statements_after_break_and_continue() {
+    let source = "
+        fun main() -> () {
+            let i = 0;
+            while (i < 10) {
+                i = i + 1;
+                if (i == 5) {
+                    break;
+                    print_int(1);
+                }
+                continue;
+                print_int(2);
+            }
+        }
+    ";
+    let package = eliminate(source);
+    assert_eq!(
+        shape(body(&package, "main")),
+        vec!["let", "while {assign if {break} continue}"]
+    );
+}
+
+#[test]
+fn removes_statements_after_branches_that_all_return() {
+    let source = "
+        fun f(x: bool) -> i64 {
+            if (x) {
+                return 1;
+            } else {
+                { return 2; }
+            }
+            print_int(3);
+        }
+
+        fun main() -> () {}
+    ";
+    let package = eliminate(source);
+    assert_eq!(
+        shape(body(&package, "f")),
+        vec!["if {return} else {{return}}"]
+    );
+}
+
+#[test]
+fn keeps_the_branch_a_constant_condition_takes() {
+    let source = "
+        fun main() -> () {
+            if (1 < 2) {
+                print_int(1);
+            } else {
+                print_int(2);
+            }
+            if (false) {
+                print_int(3);
+            }
+            while (2 > 3) {
+                print_int(4);
+            }
+            print_int(5);
+        }
+    ";
+    let package = eliminate(source);
+    assert_eq!(shape(body(&package, "main")), vec!["{1}", "5"]);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::{function, lower};
+use hir::ir::{Item, Package};
+use opt::{optimize, prune::Prune};
+
+/// The names of the functions and type objects left in the root module, and the names of the
+/// type objects that are still constructed.
+fn pruned(mut package: Package) -> (Vec<String>, Vec<String>) {
+    Prune::run(&mut package);
+    let root = package.modules.last().expect("The root module is last");
+    let items = root
+        .items
+        .iter()
+        .map(|item| match item {
+            Item::Function(id) => package.function(*id).name.clone(),
+            Item::Struct(id) => package.struct_(*id).name.clone(),
+            Item::Enum(id) => package.enum_(*id).name.clone(),
+        })
+        .collect();
+    let constructed = package
+        .structs
+        .iter()
+        .filter(|s| s.constructed)
+        .map(|s| s.name.clone())
+        .collect();
+    (items, constructed)
+}
+
+fn strings(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn removes_functions_main_does_not_reach() {
+    let source = "
+        fun used() -> i64 { return helper(); }
+        fun helper() -> i64 { return 1; }
+        fun unused() -> i64 { return helper() + only_unused(); }
+        fun only_unused() -> i64 { return 2; }
+        fun referenced() -> i64 { return 3; }
+        fun in_lambda() -> i64 { return 4; }
+
+        fun main() -> () {
+            let f = referenced;
+            let g = fun() -> i64 { return in_lambda(); };
+            print_int(used() + f() + g());
+        }
+    ";
+    let (items, _) = pruned(lower(source));
+    assert_eq!(
+        items,
+        strings(&["used", "helper", "referenced", "in_lambda", "main"])
+    );
+}
+
+#[test]
+fn marks_type_objects_that_are_never_created() {
+    let source = "
+        type Created = { x: i64 }
+        type OnlyAsType = { x: i64 }
+        type OnlyInUnused = { x: i64 }
+
+        fun unused() -> OnlyInUnused { return OnlyInUnused { x: 1 }; }
+        fun get(o: OnlyAsType?) -> i64 { return 0; }
+
+        fun main() -> () {
+            let c = Created { x: get(nil) };
+            print_int(c.x);
+        }
+    ";
+    let (items, constructed) = pruned(lower(source));
+    // type objects stay in the module, since their types are still used
+    assert_eq!(
+        items,
+        strings(&["Created", "OnlyAsType", "OnlyInUnused", "get", "main"])
+    );
+    assert_eq!(constructed, strings(&["Created"]));
+}
+
+#[test]
+fn removes_functions_only_dead_code_calls() {
+    let source = "
+        fun debug() -> () { print_int(0); }
+
+        fun main() -> () {
+            if (false) {
+                debug();
+            }
+        }
+    ";
+    let mut package = lower(source);
+    let debug = function(&package, "debug");
+    optimize(&mut package);
+    let root = package.modules.last().expect("The root module is last");
+    assert!(!root.items.contains(&Item::Function(debug)));
+}
+
+#[test]
+fn package_without_main_is_left_as_it_is() {
+    let source = "
+        fun a() -> () {}
+        fun b() -> () {}
+    ";
+    let (items, _) = pruned(lower(source));
+    assert_eq!(items, strings(&["a", "b"]));
+}
@@ -1,24 +1,26 @@
 use base::{compile_context::CompileContext, file_cache::FileCache};
-use clap::{arg, command, Parser};
+use clap::{Parser, Subcommand};
 use codespan_reporting::term::termcolor::{BufferedStandardStream, ColorChoice};
-use std::{env, path::PathBuf, sync};
+use std::{env, io::Write, path::PathBuf, sync};
 
 extern crate driver;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(
         short = 'p',
         long = "path",
         required = true,
         help = "Path to the package to build"
     )]
-    path: String,
+    path: Option<String>,
     #[arg(
         short = 'd',
         long = "emit_irs",
-        help = "Emit the AST and MIR to files in the build directory"
+        help = "Emit the AST, HIR and MIR to files in the build directory"
     )]
     emit_irs: bool,
     #[arg(
@@ -27,17 +29,50 @@ struct Args {
         help = "Run the program immediately after compiling"
     )]
     run_immediately: bool,
+    #[arg(
+        short = 'i',
+        long = "interp",
+        help = "Run the program with the interpreter instead of compiling it to C"
+    )]
+    interpret: bool,
+    #[arg(
+        short = 'l',
+        long = "link",
+        help = "Link against a C library, e.g. `-l m` for libm. Can be repeated"
+    )]
+    link: Vec<String>,
+    #[arg(
+        long = "line_directives",
+        help = "Emit #line directives, so C compiler errors and debuggers point at the Pine source"
+    )]
+    line_directives: bool,
+    #[arg(
+        long = "runtime",
+        help = "Build against the C runtime in this directory instead of the embedded one"
+    )]
+    runtime: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Enter declarations and statements line by line and print the values of expressions
+    Repl,
 }
 
 fn main() {
     // std::env::set_var("RUST_BACKTRACE", "1");
 
     let args = Args::parse();
+    if let Some(Command::Repl) = args.command {
+        driver::repl::Repl::run();
+        return;
+    }
+
     let current_dir = env::current_dir().expect("Failed to get current directory");
     let build_dir = current_dir.join(".build");
     let emit_irs = args.emit_irs;
     let run_immediately = args.run_immediately;
-    let build_source = PathBuf::from(args.path);
+    let build_source = PathBuf::from(args.path.expect("The path is required"));
     let file_cache = FileCache::empty();
 
     let mut context = CompileContext {
@@ -46,6 +81,10 @@ fn main() {
         build_dir,
         build_pkg: build_source,
         run_immediately,
+        interpret: args.interpret,
+        link_libraries: args.link,
+        line_directives: args.line_directives,
+        runtime_dir: args.runtime.map(PathBuf::from),
     };
 
     // only delete if it is called .build
@@ -57,7 +96,7 @@ fn main() {
     let (msg_sender, msg_recv) = sync::mpsc::channel();
 
     let mut compiler = driver::Compiler::new(&mut context);
-    compiler.compile(msg_sender);
+    let exit_code = compiler.compile(msg_sender);
 
     let mut writer = BufferedStandardStream::stderr(ColorChoice::Always);
     let reporting_config = codespan_reporting::term::Config::default();
@@ -73,4 +112,10 @@ fn main() {
         )
         .unwrap();
     }
+
+    // like the program would on its own, runtime errors end with the exit code of the program
+    if let Some(code) = exit_code.filter(|code| *code != 0) {
+        writer.flush().expect("Failed to write the messages");
+        std::process::exit(code);
+    }
 }
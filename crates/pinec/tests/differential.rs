@@ -0,0 +1,241 @@
+//! Runs the same programs with the interpreter and the C backend, which have to agree on the
+//! output, the runtime errors and the exit code.
+
+use std::{env, fs, process::Command};
+
+/// What a program printed and how it ended.
+#[derive(Debug, PartialEq)]
+struct Run {
+    stdout: String,
+    stderr: String,
+    code: Option<i32>,
+}
+
+/// Runs `source` as the only module of a package with `flag` choosing the backend.
+fn run(name: &str, source: &str, flag: &str) -> Run {
+    // every run gets its own directory, since the build directory is the `.build` in it
+    let dir = env::temp_dir().join(format!(
+        "pine-differential-{}-{}{}",
+        std::process::id(),
+        name,
+        flag
+    ));
+    fs::create_dir_all(&dir).expect("Failed to create the test directory");
+    fs::write(dir.join("main.pine"), source).expect("Failed to write the program");
+    let output = Command::new(env!("CARGO_BIN_EXE_pinec"))
+        .args(["-p", "main.pine", flag])
+        .current_dir(&dir)
+        .output()
+        .expect("Failed to run pinec");
+    fs::remove_dir_all(&dir).expect("Failed to remove the test directory");
+
+    // the time it took differs between the backends
+    let stdout = String::from_utf8(output.stdout).expect("The output is UTF-8");
+    let stdout = (stdout.lines())
+        .filter(|line| !line.starts_with("Ran in "))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    Run {
+        stdout,
+        stderr: String::from_utf8(output.stderr).expect("The errors are UTF-8"),
+        code: output.status.code(),
+    }
+}
+
+/// Runs `source` with both backends, checks that they agree and returns what they did.
+fn run_both(name: &str, source: &str) -> Run {
+    let interpreted = run(name, source, "--interp");
+    let compiled = run(name, source, "--run");
+    assert_eq!(interpreted, compiled, "the backends disagree");
+    assert!(
+        !interpreted.stderr.contains("error["),
+        "the program doesn't compile: {}",
+        interpreted.stderr
+    );
+    interpreted
+}
+
+fn lines(lines: &[&str]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[test]
+fn integers_wrap_at_the_width_of_their_type() {
+    let source = "
+        fun main() -> () {
+            let a: u8 = 250;
+            a = a + 10;
+            print_bool(a == 4);
+            let b: i8 = 127;
+            b = b + 1;
+            print_bool(b == -128);
+            let c: i32 = -2147483648;
+            c = c - 1;
+            print_bool(c == 2147483647);
+            let d: u32 = 0;
+            d = d - 1;
+            print_bool(d == 4294967295);
+            let f: u8 = 16;
+            f = f * 16;
+            print_bool(f == 0);
+            let e: i64 = 9223372036854775807;
+            e = e + 1;
+            print_int(e);
+        }
+    ";
+    let run = run_both("wrapping", source);
+    let expected = lines(&[
+        "true",
+        "true",
+        "true",
+        "true",
+        "true",
+        "-9223372036854775808",
+    ]);
+    assert_eq!(run.stdout, expected);
+    assert_eq!(run.code, Some(0));
+}
+
+#[test]
+fn logic_operators_short_circuit() {
+    let source = "
+        fun side(name: str, value: bool) -> bool {
+            print_str(name);
+            return value;
+        }
+
+        fun main() -> () {
+            print_bool(side(\"a\", false) and side(\"b\", true));
+            print_bool(side(\"c\", true) or side(\"d\", true));
+            print_bool(side(\"e\", true) and side(\"f\", false));
+            print_bool(side(\"g\", false) or side(\"h\", true));
+            let l = [1];
+            let i = 5;
+            if (i < len(l) and l[i] == 1) {
+                print_str(\"unreachable\");
+            }
+        }
+    ";
+    let run = run_both("short_circuit", source);
+    let expected = lines(&[
+        "a", "false", "c", "true", "e", "f", "false", "g", "h", "true",
+    ]);
+    assert_eq!(run.stdout, expected);
+    assert_eq!(run.code, Some(0));
+}
+
+#[test]
+fn index_out_of_bounds_ends_the_program() {
+    let source = "
+        fun main() -> () {
+            let l = [1, 2, 3];
+            print_int(l[2]);
+            print_int(l[3]);
+            print_int(4);
+        }
+    ";
+    let run = run_both("bounds", source);
+    assert_eq!(run.stdout, lines(&["3"]));
+    assert!(run.stderr.starts_with("main.pine:5:25"), "{}", run.stderr);
+    assert!(run.stderr.contains("out of bounds"), "{}", run.stderr);
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn unwrapping_nil_ends_the_program() {
+    let source = "
+        type Node = { value: i64, next: Node? }
+
+        fun main() -> () {
+            let n = Node { value: 1, next: nil };
+            print_int(n.value);
+            print_int(n.next!.value);
+            print_int(2);
+        }
+    ";
+    let run = run_both("unwrap", source);
+    assert_eq!(run.stdout, lines(&["1"]));
+    assert!(run.stderr.starts_with("main.pine:7:23"), "{}", run.stderr);
+    assert_eq!(run.code, Some(1));
+}
+
+#[test]
+fn closures_capture_their_variables() {
+    let source = "
+        type Count = { value: i64 }
+
+        fun counter() -> fun() -> i64 {
+            let count = Count { value: 0 };
+            return fun() -> i64 {
+                count.value = count.value + 1;
+                return count.value;
+            };
+        }
+
+        fun adder(n: i64) -> fun(i64) -> i64 {
+            return fun(x: i64) -> i64 { return x + n; };
+        }
+
+        fun twice(f: fun(i64) -> i64) -> fun(i64) -> i64 {
+            return fun(x: i64) -> i64 { return f(f(x)); };
+        }
+
+        fun main() -> () {
+            let c = counter();
+            c();
+            print_int(c());
+            let other = counter();
+            print_int(other());
+            print_int(twice(adder(5))(1));
+            let k = 10;
+            let nested = fun(a: i64) -> fun(i64) -> i64 {
+                return fun(b: i64) -> i64 { return a + b + k; };
+            };
+            print_int(nested(1)(2));
+        }
+    ";
+    let run = run_both("closures", source);
+    assert_eq!(run.stdout, lines(&["2", "1", "11", "13"]));
+    assert_eq!(run.code, Some(0));
+}
+
+#[test]
+fn objects_survive_collections_under_allocation_pressure() {
+    let source = "
+        type Node = { value: i64, next: Node? }
+        type Pair = { a: Node, b: List<i64> }
+
+        fun garbage(n: i64) -> i64 {
+            let l: List<i64> = [n, n + 1, n + 2];
+            let p = Pair { a: Node { value: n, next: nil }, b: l };
+            return p.b[2] - p.a.value;
+        }
+
+        fun main() -> () {
+            let head: Node? = nil;
+            let kept: List<Pair> = [];
+            let total = 0;
+            for i in 0..200000 {
+                total = total + garbage(i);
+                if (i % 1000 == 0) {
+                    head = Node { value: i, next: head };
+                    let b: List<i64> = [i];
+                    push(kept, Pair { a: head!, b: b });
+                }
+            }
+            print_int(total);
+            let sum = 0;
+            let cur = head;
+            while (cur != nil) {
+                sum = sum + cur.value;
+                cur = cur.next;
+            }
+            print_int(sum);
+            print_int(len(kept));
+            print_int(kept[199].b[0] + kept[199].a.value);
+        }
+    ";
+    let run = run_both("gc", source);
+    assert_eq!(run.stdout, lines(&["400000", "19900000", "200", "398000"]));
+    assert_eq!(run.code, Some(0));
+}
@@ -0,0 +1,116 @@
+//! Drives the REPL with scripted inputs on stdin.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// What the session printed, without the prompts.
+struct Session {
+    stdout: String,
+    stderr: String,
+}
+
+fn session(inputs: &[&str]) -> Session {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_pinec"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run pinec");
+    let mut stdin = child.stdin.take().expect("The input is piped");
+    for input in inputs {
+        writeln!(stdin, "{}", input).expect("Failed to write the input");
+    }
+    // closing stdin ends the session
+    drop(stdin);
+    let output = child.wait_with_output().expect("Failed to run the REPL");
+    assert!(output.status.success(), "the REPL failed");
+
+    let stdout = String::from_utf8(output.stdout).expect("The output is UTF-8");
+    Session {
+        stdout: stdout
+            .replace(">> ", "")
+            .replace(".. ", "")
+            .trim()
+            .to_string(),
+        stderr: String::from_utf8(output.stderr).expect("The errors are UTF-8"),
+    }
+}
+
+#[test]
+fn variables_keep_their_values_between_inputs() {
+    let session = session(&[
+        "let x = 1;",
+        "let l: List<i64> = [1, 2];",
+        "push(l, x + 2);",
+        "x = x + 1;",
+        "x",
+        "l",
+    ]);
+    assert_eq!(session.stdout, "2\n[1, 2, 3]");
+    assert_eq!(session.stderr, "");
+}
+
+#[test]
+fn let_defines_a_variable_again() {
+    let session = session(&[
+        "let x = 1;",
+        "let f = fun(y: i64) -> i64 { return x + y; };",
+        "let x = x + 10;",
+        "x",
+        "let x = \"text\";",
+        "x",
+        "f(1)",
+    ]);
+    assert_eq!(session.stdout, "11\n\"text\"\n2");
+    assert_eq!(session.stderr, "");
+}
+
+#[test]
+fn inputs_run_once() {
+    let session = session(&[
+        "print_str(\"once\");",
+        "type Count = { value: i64 }",
+        "let c = Count { value: 0 };",
+        "fun bump(c: Count) -> () {",
+        "    c.value = c.value + 1;",
+        "}",
+        "bump(c);",
+        "bump(c);",
+        "c",
+    ]);
+    assert_eq!(session.stdout, "once\nCount { value: 2 }");
+    assert_eq!(session.stderr, "");
+}
+
+#[test]
+fn inputs_with_errors_are_left_out() {
+    let session = session(&[
+        "let x = 1;",
+        "let y = z;",
+        "let l = [1];",
+        "{ x = 5; print_int(l[3]); }",
+        "x",
+        "y",
+    ]);
+    assert_eq!(session.stdout, "1");
+    assert!(
+        session.stderr.contains("`z` is not defined"),
+        "{}",
+        session.stderr
+    );
+    assert!(
+        session
+            .stderr
+            .contains("<repl:4>:1:22: index 3 is out of bounds"),
+        "{}",
+        session.stderr
+    );
+    assert!(
+        session.stderr.contains("`y` is not defined"),
+        "{}",
+        session.stderr
+    );
+}
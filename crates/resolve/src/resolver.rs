@@ -0,0 +1,614 @@
+use crate::scope::Scopes;
+use base::located::Located;
+use messages::{message::Message, resolver::ResolverError};
+use std::collections::HashMap;
+use syntax::ast::expr::*;
+use syntax::ast::stmt::*;
+use syntax::ast::ty::{qualified_type_name, Type, TypedParam};
+use syntax::ast::{ExprS, Module, Package, StmtS, TypeS};
+use syntax::builtins::{find_builtin, find_intrinsic};
+
+/// Checks that every variable, function and type a package refers to is defined.
+///
+/// Functions and types can be used before they are declared. Parameters and `let` bindings are
+/// lexically scoped and only visible after their definition.
+///
+/// A name that is not a variable can refer to a function, which is then used as a value. Lambdas
+/// see the variables of the enclosing functions.
+///
+/// Functions and types belong to their module. Functions of other modules are called as
+/// `module.name`. Types of the modules a module imports directly can be named as `module.Name`,
+/// or just `Name` if no other of these modules declares a type of that name. Types of the module
+/// itself come first. Every type name the resolver finds is replaced by its qualified name, so
+/// later passes can tell apart types of different modules.
+pub struct Resolver {
+    functions: Scopes,
+    /// The type parameters of the generic declaration that is resolved.
+    type_params: Scopes,
+    variables: Scopes,
+    /// The modules the current module imports, keyed by the name they are accessed by.
+    imports: HashMap<String, (Vec<String>, Located<()>)>,
+    /// The names of the functions every module declares.
+    module_functions: HashMap<Vec<String>, Vec<String>>,
+    /// The type objects and enums every module declares.
+    module_types: HashMap<Vec<String>, Scopes>,
+    current_module: Vec<String>,
+    /// The variant names of every enum, keyed by its qualified name.
+    enums: HashMap<String, Vec<String>>,
+    /// How many loops enclose the current statement.
+    loop_depth: usize,
+    errors: Vec<Message>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            functions: Scopes::new(),
+            type_params: Scopes::new(),
+            variables: Scopes::new(),
+            imports: HashMap::new(),
+            module_functions: HashMap::new(),
+            module_types: HashMap::new(),
+            current_module: Vec::new(),
+            enums: HashMap::new(),
+            loop_depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Resolves the names of the package and qualifies its type names, see
+    /// [`qualified_type_name`]. The type names are only complete if there are no errors.
+    pub fn resolve(package: &mut Package) -> Vec<Message> {
+        let mut resolver = Self::new();
+        resolver.resolve_package(package);
+        resolver.errors
+    }
+
+    fn resolve_package(&mut self, package: &mut Package) {
+        for module in &package.modules {
+            let mut types = Scopes::new();
+            types.push();
+            for decl in &module.program.stmts {
+                let location = Located::empty(decl.source, decl.span.clone());
+                let name = match &decl.value {
+                    Declaration::TypeObject(t) => &t.name,
+                    Declaration::Enum(e) => &e.name,
+                    Declaration::Fun(FunctionDeclaration { name, .. })
+                    | Declaration::Extern(ExternFunction { name, .. }) => {
+                        let functions = self.module_functions.entry(module.path.clone());
+                        functions.or_default().push(name.to_string());
+                        continue;
+                    }
+                    _ => continue,
+                };
+                if let Some(previous) = types.define(name.as_ref(), location) {
+                    let name = Located::new(decl.source, decl.span.clone(), name.to_string());
+                    self.error(ResolverError::DuplicateDefinition { name, previous });
+                    continue;
+                }
+                if let Declaration::Enum(e) = &decl.value {
+                    let variants = e.variants.iter().map(|v| v.name.value.to_string());
+                    let name = qualified_type_name(&module.path, e.name.as_ref());
+                    self.enums.insert(name, variants.collect());
+                }
+            }
+            self.module_types.insert(module.path.clone(), types);
+        }
+
+        for module in &mut package.modules {
+            self.resolve_module(module);
+        }
+    }
+
+    fn resolve_module(&mut self, module: &mut Module) {
+        self.current_module = module.path.clone();
+        self.imports.clear();
+        self.functions = Scopes::new();
+        self.functions.push();
+        // Methods are keyed by the implemented type and the method name.
+        let mut methods: HashMap<(String, String), Located<()>> = HashMap::new();
+
+        let program = &mut module.program;
+        for decl in &program.stmts {
+            let location = Located::empty(decl.source, decl.span.clone());
+            match &decl.value {
+                Declaration::Import(i) => {
+                    let alias = i.alias().to_string();
+                    let name = Located::new(decl.source, decl.span.clone(), alias.clone());
+                    match self.imports.get(&alias) {
+                        Some((_, previous)) => {
+                            let previous = previous.clone();
+                            self.error(ResolverError::DuplicateDefinition { name, previous });
+                        }
+                        None => {
+                            self.imports.insert(alias, (i.module_path(), location));
+                        }
+                    }
+                }
+                Declaration::Fun(FunctionDeclaration { name: fun, .. })
+                | Declaration::Extern(ExternFunction { name: fun, .. }) => {
+                    let name = Located::new(decl.source, decl.span.clone(), fun.to_string());
+                    if Self::is_builtin(fun.as_ref()) {
+                        self.error(ResolverError::BuiltinRedefinition { name });
+                    } else if let Some(previous) = self.functions.define(fun.as_ref(), location) {
+                        self.error(ResolverError::DuplicateDefinition { name, previous });
+                    }
+                }
+                Declaration::Impl(i) => {
+                    for method in &i.methods {
+                        let key = (i.name.value.to_string(), method.value.name.to_string());
+                        let location = Located::empty(method.source, method.span.clone());
+                        if let Some(previous) = methods.get(&key) {
+                            let name = Located::new(
+                                method.source,
+                                method.span.clone(),
+                                method.value.name.to_string(),
+                            );
+                            let previous = previous.clone();
+                            self.error(ResolverError::DuplicateDefinition { name, previous });
+                        } else {
+                            methods.insert(key, location);
+                        }
+                    }
+                }
+                Declaration::TypeObject(_) | Declaration::Enum(_) | Declaration::Error => {}
+            }
+        }
+
+        for decl in &mut program.stmts {
+            match &mut decl.value {
+                Declaration::Fun(f) => self.resolve_function(f),
+                Declaration::Extern(e) => self.resolve_extern(e),
+                Declaration::TypeObject(t) => self.resolve_type_object(t),
+                Declaration::Enum(e) => self.resolve_enum(e),
+                Declaration::Impl(i) => self.resolve_impl(i),
+                Declaration::Import(_) | Declaration::Error => {}
+            }
+        }
+    }
+
+    fn resolve_type_object(&mut self, type_object: &mut TypeObject) {
+        type_object.name.name =
+            qualified_type_name(&self.current_module, type_object.name.as_ref());
+        self.define_type_params(&type_object.type_params);
+        self.variables.push();
+        for member in &mut type_object.members {
+            self.resolve_ty(&mut member.ty);
+            let location = Located::empty(member.name.source, member.name.span.clone());
+            if let Some(previous) = self.variables.define(member.name.value.as_ref(), location) {
+                let name = Located::new(
+                    member.name.source,
+                    member.name.span.clone(),
+                    member.name.value.to_string(),
+                );
+                self.error(ResolverError::DuplicateDefinition { name, previous });
+            }
+        }
+        self.variables.pop();
+        self.type_params.pop();
+    }
+
+    /// Pushes a scope with the type parameters of a generic declaration. The scope is pushed
+    /// for declarations without type parameters as well, so that it can always be popped.
+    ///
+    /// Type parameters cannot share the name of a type, since both are written the same way.
+    fn define_type_params(&mut self, type_params: &[Located<Identifier>]) {
+        self.type_params.push();
+        for param in type_params {
+            let location = Located::empty(param.source, param.span.clone());
+            let name = param.value.as_ref();
+            let previous = match (self.type_params.lookup(name), self.lookup_type(name)) {
+                (Some(previous), _) | (None, Ok((_, previous))) => Some(previous.clone()),
+                (None, Err(_)) => self.type_params.define(name, location),
+            };
+            if let Some(previous) = previous {
+                let name = Located::new(param.source, param.span.clone(), param.value.to_string());
+                self.error(ResolverError::DuplicateDefinition { name, previous });
+            }
+        }
+    }
+
+    fn resolve_enum(&mut self, enum_decl: &mut EnumDeclaration) {
+        enum_decl.name.name = qualified_type_name(&self.current_module, enum_decl.name.as_ref());
+        self.variables.push();
+        for variant in &mut enum_decl.variants {
+            for ty in &mut variant.payload {
+                self.resolve_ty(ty);
+            }
+            let location = Located::empty(variant.name.source, variant.name.span.clone());
+            if let Some(previous) = self.variables.define(variant.name.value.as_ref(), location) {
+                let name = Located::new(
+                    variant.name.source,
+                    variant.name.span.clone(),
+                    variant.name.value.to_string(),
+                );
+                self.error(ResolverError::DuplicateDefinition { name, previous });
+            }
+        }
+        self.variables.pop();
+    }
+
+    fn resolve_impl(&mut self, impl_block: &mut ImplBlock) {
+        // Every method takes `self` of the implemented type, so report a missing type only once.
+        let name = impl_block.name.value.to_string();
+        let types = &self.module_types[&self.current_module];
+        if types.lookup(&name).is_none() {
+            let error = match self.lookup_type(&name) {
+                Ok(_) => ResolverError::ForeignImpl {
+                    name: Located::new(impl_block.name.source, impl_block.name.span.clone(), name),
+                },
+                Err(_) => ResolverError::UndefinedType {
+                    name: Located::new(impl_block.name.source, impl_block.name.span.clone(), name),
+                },
+            };
+            self.error(error);
+            return;
+        }
+        impl_block.name.value.name = qualified_type_name(&self.current_module, &name);
+        for method in &mut impl_block.methods {
+            self.resolve_function(&mut method.value);
+        }
+    }
+
+    fn resolve_function(&mut self, fun: &mut FunctionDeclaration) {
+        self.define_type_params(&fun.type_params);
+        self.resolve_ty(&mut fun.ret_ty);
+        self.loop_depth = 0;
+
+        // Parameters share a scope with the outermost block of the body, just like in C.
+        self.variables.push();
+        for param in &mut fun.params {
+            self.define_param(param);
+        }
+        for stmt in &mut fun.body.stmts {
+            self.resolve_stmt(stmt);
+        }
+        self.variables.pop();
+        self.type_params.pop();
+    }
+
+    fn resolve_extern(&mut self, extern_fun: &mut ExternFunction) {
+        self.resolve_ty(&mut extern_fun.ret_ty);
+        self.variables.push();
+        for param in &mut extern_fun.params {
+            self.define_param(param);
+        }
+        self.variables.pop();
+    }
+
+    fn define_param(&mut self, param: &mut TypedParam) {
+        self.resolve_ty(&mut param.ty);
+        let location = Located::empty(param.name.source, param.name.span.clone());
+        self.define_variable(param.name.value.as_ref(), location);
+    }
+
+    /// Defines a variable in the innermost scope. Variables cannot share the name of an import,
+    /// since `name.f()` has to be either a method or a module function call.
+    fn define_variable(&mut self, name: &str, location: Located<()>) {
+        let previous = match self.imports.get(name) {
+            Some((_, import)) => Some(import.clone()),
+            None => self.variables.define(name, location.clone()),
+        };
+        if let Some(previous) = previous {
+            let name = Located::new(location.source, location.span, name.to_string());
+            self.error(ResolverError::DuplicateDefinition { name, previous });
+        }
+    }
+
+    fn resolve_block(&mut self, block: &mut Block) {
+        self.variables.push();
+        for stmt in &mut block.stmts {
+            self.resolve_stmt(stmt);
+        }
+        self.variables.pop();
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut StmtS) {
+        match &mut stmt.value {
+            Stmt::Block(b) => self.resolve_block(b),
+            Stmt::Expr(e) => self.resolve_expr(&mut e.value),
+            Stmt::If(i) => {
+                self.resolve_expr(&mut i.cond);
+                self.resolve_stmt(&mut i.then);
+                if let Some(else_) = &mut i.else_ {
+                    self.resolve_stmt(else_);
+                }
+            }
+            Stmt::Return(r) => {
+                if let Some(value) = &mut r.value {
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::VariableDeclaration(v) => {
+                if let Some(ty) = &mut v.ty {
+                    self.resolve_ty(ty);
+                }
+                // The initializer cannot refer to the variable it initializes.
+                self.resolve_expr(&mut v.value);
+                let location = Located::empty(stmt.source, stmt.span.clone());
+                self.define_variable(v.var.as_ref(), location);
+            }
+            Stmt::Loop(l) => self.resolve_loop_body(&mut l.body),
+            Stmt::While(w) => {
+                self.resolve_expr(&mut w.cond);
+                self.resolve_loop_body(&mut w.body);
+            }
+            Stmt::For(f) => {
+                self.resolve_expr(&mut f.start);
+                self.resolve_expr(&mut f.end);
+                // The loop variable is only visible in the body.
+                self.variables.push();
+                let location = Located::empty(f.var.source, f.var.span.clone());
+                self.define_variable(f.var.value.as_ref(), location);
+                self.resolve_loop_body(&mut f.body);
+                self.variables.pop();
+            }
+            Stmt::Match(m) => {
+                self.resolve_expr(&mut m.value);
+                for arm in &mut m.arms {
+                    self.resolve_match_arm(arm);
+                }
+            }
+            Stmt::Assign(a) => {
+                self.resolve_expr(&mut a.var);
+                self.resolve_expr(&mut a.value);
+            }
+            Stmt::Break(Break { span }) => self.check_inside_loop(span, "break"),
+            Stmt::Continue(Continue { span }) => self.check_inside_loop(span, "continue"),
+            Stmt::Empty(_) | Stmt::Error => {}
+        }
+    }
+
+    fn check_inside_loop(&mut self, span: &Located<()>, keyword: &str) {
+        if self.loop_depth == 0 {
+            let name = Located::new(span.source, span.span.clone(), keyword.to_string());
+            self.error(ResolverError::OutsideOfLoop { name });
+        }
+    }
+
+    fn resolve_loop_body(&mut self, body: &mut StmtS) {
+        self.loop_depth += 1;
+        self.resolve_stmt(body);
+        self.loop_depth -= 1;
+    }
+
+    fn resolve_match_arm(&mut self, arm: &mut MatchArm) {
+        // The bindings of a pattern are only visible in the body of its arm.
+        self.variables.push();
+        if let Pattern::Variant(p) = &mut arm.pattern.value {
+            let location = Located::empty(arm.pattern.source, arm.pattern.span.clone());
+            self.resolve_variant(&location, &mut p.enum_name, &p.variant);
+            for binding in &p.bindings {
+                let location = Located::empty(binding.source, binding.span.clone());
+                self.define_variable(binding.value.as_ref(), location);
+            }
+        }
+        self.resolve_stmt(&mut arm.body);
+        self.variables.pop();
+    }
+
+    fn resolve_variant(
+        &mut self,
+        location: &Located<()>,
+        enum_name: &mut Identifier,
+        variant: &Identifier,
+    ) {
+        if !self.resolve_type_name(location, &mut enum_name.name) {
+            return;
+        }
+        let variants = self.enums.get(enum_name.as_ref());
+        if !variants.is_some_and(|variants| variants.iter().any(|v| v == variant.as_ref())) {
+            let name = Located::new(location.source, location.span.clone(), variant.to_string());
+            self.error(ResolverError::UndefinedVariant {
+                name,
+                enum_name: enum_name.to_string(),
+            });
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut ExprS) {
+        let location = Located::empty(expr.source, expr.span.clone());
+        match &mut expr.value {
+            Expr::Var(v) => {
+                let name = v.var.as_ref();
+                if self.variables.lookup(name).is_none()
+                    && self.functions.lookup(name).is_none()
+                    && find_builtin(name).is_none()
+                {
+                    let name = Located::new(expr.source, expr.span.clone(), v.var.to_string());
+                    self.error(ResolverError::UndefinedVariable { name });
+                }
+            }
+            Expr::Literal(_) => {}
+            Expr::Call(c) => {
+                match &c.callee.value {
+                    Expr::Var(v) if self.variables.lookup(v.var.as_ref()).is_some() => {}
+                    Expr::Var(v) => self.resolve_function_name(&c.callee, &v.var),
+                    Expr::MemberAccess(m) if self.module_alias(&m.object).is_some() => {
+                        let location = Located::empty(c.callee.source, c.callee.span.clone());
+                        self.resolve_module_function(&location, m)
+                    }
+                    _ => self.resolve_expr(&mut c.callee),
+                }
+                for arg in &mut c.args {
+                    self.resolve_expr(arg);
+                }
+            }
+            Expr::MemberAccess(m) if self.module_alias(&m.object).is_some() => {
+                self.resolve_module_function(&location, m)
+            }
+            Expr::MemberAccess(m) => self.resolve_expr(&mut m.object),
+            Expr::Prefix(p) => self.resolve_expr(&mut p.rt),
+            Expr::Infix(i) => {
+                self.resolve_expr(&mut i.lt);
+                self.resolve_expr(&mut i.rt);
+            }
+            Expr::StructureInit(s) => {
+                self.resolve_type_name(&location, &mut s.name.name);
+                for (_, value) in &mut s.members {
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::EnumVariant(v) => self.resolve_variant(&location, &mut v.enum_name, &v.variant),
+            Expr::Index(i) => {
+                self.resolve_expr(&mut i.object);
+                self.resolve_expr(&mut i.index);
+            }
+            Expr::Array(a) => {
+                for element in &mut a.elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Unwrap(u) => self.resolve_expr(&mut u.value),
+            Expr::Lambda(l) => self.resolve_lambda(l),
+        }
+    }
+
+    fn resolve_lambda(&mut self, lambda: &mut ExprLambda) {
+        self.resolve_ty(&mut lambda.ret_ty);
+        // `break` and `continue` cannot leave the lambda.
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        self.variables.push();
+        for param in &mut lambda.params {
+            self.define_param(param);
+        }
+        for stmt in &mut lambda.body.stmts {
+            self.resolve_stmt(stmt);
+        }
+        self.variables.pop();
+        self.loop_depth = loop_depth;
+    }
+
+    fn resolve_function_name(&mut self, callee: &ExprS, name: &Identifier) {
+        if !Self::is_builtin(name.as_ref()) && self.functions.lookup(name.as_ref()).is_none() {
+            let name = Located::new(callee.source, callee.span.clone(), name.to_string());
+            self.error(ResolverError::UndefinedFunction { name });
+        }
+    }
+
+    /// `math.sqrt` has to name a function of the module imported as `math`.
+    fn resolve_module_function(&mut self, location: &Located<()>, access: &ExprMemberAccess) {
+        let alias = self
+            .module_alias(&access.object)
+            .expect("Callee is a module");
+        let (path, _) = &self.imports[alias];
+        let functions = self.module_functions.get(path);
+        let name = access.member_name.as_ref();
+        if !functions.is_some_and(|functions| functions.iter().any(|f| f == name)) {
+            let name = Located::new(
+                location.source,
+                location.span.clone(),
+                format!("{}.{}", alias, name),
+            );
+            self.error(ResolverError::UndefinedFunction { name });
+        }
+    }
+
+    /// The import alias `expr` refers to, if it is the name of an imported module.
+    fn module_alias<'e>(&self, expr: &'e ExprS) -> Option<&'e str> {
+        match &expr.value {
+            Expr::Var(v) if self.imports.contains_key(v.var.as_ref()) => Some(v.var.as_ref()),
+            _ => None,
+        }
+    }
+
+    fn is_builtin(name: &str) -> bool {
+        find_builtin(name).is_some() || find_intrinsic(name).is_some()
+    }
+
+    fn resolve_ty(&mut self, ty: &mut TypeS) {
+        let location = Located::empty(ty.source, ty.span.clone());
+        self.resolve_type_names(&location, &mut ty.value);
+    }
+
+    fn resolve_type_names(&mut self, location: &Located<()>, ty: &mut Type) {
+        match ty {
+            Type::Array(elem, _) | Type::List(elem) | Type::Optional(elem) => {
+                self.resolve_type_names(location, elem)
+            }
+            Type::Struct(name) => {
+                self.resolve_type_name(location, name);
+            }
+            Type::Generic(name, args) => {
+                if !self.resolve_type_name(location, name) {
+                    return;
+                }
+                for arg in args {
+                    self.resolve_type_names(location, arg);
+                }
+            }
+            Type::Function(params, ret) => {
+                for param in params {
+                    self.resolve_type_names(location, param);
+                }
+                self.resolve_type_names(location, ret);
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the name of a type object or enum by its qualified name. Type parameters keep
+    /// their name. Returns whether `name` refers to a type, and reports it otherwise.
+    fn resolve_type_name(&mut self, location: &Located<()>, name: &mut String) -> bool {
+        if self.type_params.lookup(name).is_some() {
+            return true;
+        }
+        let error = match self.lookup_type(name) {
+            Ok((qualified, _)) => {
+                *name = qualified;
+                return true;
+            }
+            Err(candidates) if candidates.is_empty() => ResolverError::UndefinedType {
+                name: Located::new(location.source, location.span.clone(), name.clone()),
+            },
+            Err(candidates) => ResolverError::AmbiguousType {
+                name: Located::new(location.source, location.span.clone(), name.clone()),
+                candidates,
+            },
+        };
+        self.error(error);
+        false
+    }
+
+    /// The qualified name and the declaration of the type object or enum `name` refers to in the
+    /// current module. Otherwise the names it could refer to, written as `module.Name`.
+    fn lookup_type(&self, name: &str) -> Result<(String, &Located<()>), Vec<String>> {
+        let declared = |path: &Vec<String>, name: &str| {
+            let types = self.module_types.get(path)?;
+            let declaration = types.lookup(name)?;
+            Some((qualified_type_name(path, name), declaration))
+        };
+        if let Some((alias, name)) = name.split_once('.') {
+            let imported = self.imports.get(alias);
+            return imported
+                .and_then(|(path, _)| declared(path, name))
+                .ok_or_else(Vec::new);
+        }
+        if let Some(own) = declared(&self.current_module, name) {
+            return Ok(own);
+        }
+        let mut imported: Vec<_> = (self.imports.iter())
+            .filter_map(|(alias, (path, _))| Some((alias, declared(path, name)?)))
+            .collect();
+        match imported.len() {
+            1 => Ok(imported.remove(0).1),
+            _ => {
+                let mut candidates: Vec<_> = (imported.iter())
+                    .map(|(alias, _)| format!("{}.{}", alias, name))
+                    .collect();
+                candidates.sort();
+                Err(candidates)
+            }
+        }
+    }
+
+    fn error(&mut self, err: ResolverError) {
+        self.errors.push(err.into());
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
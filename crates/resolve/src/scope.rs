@@ -0,0 +1,44 @@
+use base::located::Located;
+use std::collections::HashMap;
+
+/// A stack of lexical scopes, mapping names to the location of their definition.
+#[derive(Debug, Default)]
+pub struct Scopes {
+    scopes: Vec<HashMap<String, Located<()>>>,
+}
+
+impl Scopes {
+    pub fn new() -> Self {
+        Self { scopes: Vec::new() }
+    }
+
+    pub fn push(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Defines `name` in the innermost scope.
+    ///
+    /// Returns the previous definition if `name` was already defined in the innermost scope.
+    pub fn define(&mut self, name: &str, location: Located<()>) -> Option<Located<()>> {
+        let scope = self
+            .scopes
+            .last_mut()
+            .expect("Cannot define a name without a scope");
+        match scope.get(name) {
+            Some(previous) => Some(previous.clone()),
+            None => {
+                scope.insert(name.to_string(), location);
+                None
+            }
+        }
+    }
+
+    /// Looks up `name` starting at the innermost scope.
+    pub fn lookup(&self, name: &str) -> Option<&Located<()>> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+}
@@ -0,0 +1,165 @@
+use base::source_id::SourceId;
+use messages::message::Message;
+use resolve::resolver::Resolver;
+use syntax::{
+    ast::{stmt::Declaration, ty::Type, Module, Package},
+    parser::Parser,
+};
+
+/// A package made of modules with the given paths and sources. The root module comes last and
+/// has an empty path.
+fn package(modules: &[(&str, &str)]) -> Package {
+    let modules = modules
+        .iter()
+        .map(|(path, source)| {
+            let source_id = SourceId::from_path(format!("{}.pine", path));
+            let (program, errors) = Parser::parse_file(source_id, source);
+            assert!(errors.is_empty(), "The test program has syntax errors");
+            let path = match path.is_empty() {
+                true => vec![],
+                false => path.split('.').map(str::to_string).collect(),
+            };
+            Module {
+                path,
+                source: source_id,
+                program,
+            }
+        })
+        .collect();
+    Package { modules }
+}
+
+/// The codes of the resolver errors in the package.
+fn resolve_errors(package: &mut Package) -> Vec<usize> {
+    Resolver::resolve(package)
+        .into_iter()
+        .map(|err| match err {
+            Message::Resolve(err) => err.code(),
+            _ => panic!("The resolver reported a message that is not a resolver error"),
+        })
+        .collect()
+}
+
+/// The parameter types of the function `name` in the root module.
+fn param_types(package: &Package, name: &str) -> Vec<Type> {
+    let root = package.modules.last().expect("The root module is last");
+    root.program
+        .stmts
+        .iter()
+        .find_map(|decl| match &decl.value {
+            Declaration::Fun(f) if f.name.as_ref() == name => {
+                Some(f.params.iter().map(|p| p.ty.value.clone()).collect())
+            }
+            _ => None,
+        })
+        .expect("The function is declared")
+}
+
+const UNDEFINED_TYPE: usize = 2;
+const DUPLICATE_DEFINITION: usize = 3;
+const AMBIGUOUS_TYPE: usize = 8;
+
+#[test]
+fn modules_declare_types_of_the_same_name() {
+    let mut package = package(&[
+        ("util.box", "type Box = { value: i64 }"),
+        ("shapes", "type Box = { width: i64 }"),
+        (
+            "",
+            "
+            import util.box;
+            import shapes;
+
+            type Box = { open: bool }
+
+            fun f(a: Box, b: box.Box, c: shapes.Box?) -> () {}
+            fun main() -> () {}
+            ",
+        ),
+    ]);
+    assert_eq!(resolve_errors(&mut package), vec![]);
+    assert_eq!(
+        param_types(&package, "f"),
+        vec![
+            Type::Struct("Box".to_string()),
+            Type::Struct("util.box.Box".to_string()),
+            Type::Optional(Box::new(Type::Struct("shapes.Box".to_string()))),
+        ]
+    );
+}
+
+#[test]
+fn imported_type_without_module() {
+    let mut package = package(&[
+        ("geometry", "type Point = { x: i64 }"),
+        (
+            "",
+            "
+            import geometry;
+
+            fun f(p: Point, ps: List<geometry.Point>) -> () {}
+            fun main() -> () {}
+            ",
+        ),
+    ]);
+    assert_eq!(resolve_errors(&mut package), vec![]);
+    let point = Type::Struct("geometry.Point".to_string());
+    assert_eq!(
+        param_types(&package, "f"),
+        vec![point.clone(), Type::List(Box::new(point))]
+    );
+}
+
+#[test]
+fn types_are_not_visible_through_other_modules() {
+    let mut package = package(&[
+        ("inner", "type Secret = { x: i64 }"),
+        (
+            "outer",
+            "import inner;\nfun get() -> inner.Secret { return Secret { x: 1 }; }",
+        ),
+        (
+            "",
+            "
+            import outer;
+
+            fun f(s: Secret, t: outer.Secret, u: inner.Secret) -> () {}
+            fun main() -> () {}
+            ",
+        ),
+    ]);
+    assert_eq!(resolve_errors(&mut package), vec![UNDEFINED_TYPE; 3]);
+}
+
+#[test]
+fn type_declared_by_several_imports_is_ambiguous() {
+    let mut package = package(&[
+        ("a", "type Box = { x: i64 }"),
+        ("b", "type Box = { y: i64 }"),
+        (
+            "",
+            "
+            import a;
+            import b;
+
+            fun f(x: Box, y: a.Box) -> () {}
+            fun main() -> () {}
+            ",
+        ),
+    ]);
+    assert_eq!(resolve_errors(&mut package), vec![AMBIGUOUS_TYPE]);
+}
+
+#[test]
+fn duplicate_type_in_one_module() {
+    let mut package = package(&[(
+        "",
+        "
+        type Box = { x: i64 }
+        enum Box { Empty }
+
+        fun main() -> () {}
+        ",
+    )]);
+    assert_eq!(resolve_errors(&mut package), vec![DUPLICATE_DEFINITION]);
+}
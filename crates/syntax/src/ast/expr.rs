@@ -2,7 +2,9 @@ use std::fmt::{self, Display, Formatter};
 
 use super::{
     op::{OpInfix, OpPrefix},
-    ExprS,
+    stmt::Block,
+    ty::TypedParam,
+    ExprS, TypeS,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -14,6 +16,52 @@ pub enum Expr {
     Prefix(Box<ExprPrefix>),
     Infix(Box<ExprInfix>),
     StructureInit(ExprStructureInit),
+    EnumVariant(ExprEnumVariant),
+    Index(Box<ExprIndex>),
+    Array(ExprArray),
+    Unwrap(Box<ExprUnwrap>),
+    Lambda(Box<ExprLambda>),
+}
+
+/// An anonymous function like `fun(x: i64) -> i64 { return x + n; }`.
+///
+/// The lambda captures the variables of the enclosing functions it uses by value, when it is
+/// created.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprLambda {
+    pub params: Vec<TypedParam>,
+    pub ret_ty: TypeS,
+    pub body: Block,
+}
+
+/// `value!` is the value of an optional that must not be `nil`. Unwrapping `nil` aborts the
+/// program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprUnwrap {
+    pub value: ExprS,
+}
+
+/// `object[index]` on an array or a list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprIndex {
+    pub object: ExprS,
+    pub index: ExprS,
+}
+
+/// An array literal like `[1, 2, 3]`. Depending on the expected type it creates an array or
+/// a list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprArray {
+    pub elements: Vec<ExprS>,
+}
+
+/// A variant of an enum, e.g. `Shape::Circle`.
+///
+/// Variants with a payload are constructed by calling them like a function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExprEnumVariant {
+    pub enum_name: Identifier,
+    pub variant: Identifier,
 }
 
 #[derive(Clone, Debug, PartialEq)]
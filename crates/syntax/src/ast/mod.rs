@@ -3,8 +3,11 @@ pub mod op;
 pub mod stmt;
 pub mod ty;
 
-use self::{stmt::Declaration, ty::Type};
-use base::located::Located;
+use self::{
+    stmt::{Declaration, Import},
+    ty::Type,
+};
+use base::{located::Located, source_id::SourceId};
 use expr::Expr;
 use stmt::Stmt;
 
@@ -17,3 +20,58 @@ pub type TypeS = Located<Type>;
 pub struct Program {
     pub stmts: Vec<DeclS>,
 }
+
+impl Program {
+    pub fn imports(&self) -> impl Iterator<Item = &Import> {
+        self.stmts.iter().filter_map(|decl| match &decl.value {
+            Declaration::Import(i) => Some(i),
+            _ => None,
+        })
+    }
+}
+
+/// A single input of the REPL.
+#[derive(Debug)]
+pub enum ReplInput {
+    Declaration(DeclS),
+    Stmt(StmtS),
+    /// An expression without a trailing `;`, whose value is printed.
+    Expr(ExprS),
+}
+
+/// A source file of a package.
+#[derive(Debug)]
+pub struct Module {
+    /// The path the module is imported by, relative to the package root. `geometry/shapes.pine`
+    /// has the path `["geometry", "shapes"]`. Empty for the root module.
+    pub path: Vec<String>,
+    pub source: SourceId,
+    pub program: Program,
+}
+
+impl Module {
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// The dotted path of the module, or the file name for the root module.
+    pub fn name(&self) -> String {
+        match self.is_root() {
+            true => self.source.filename(),
+            false => self.path.join("."),
+        }
+    }
+}
+
+/// All modules reachable from the root module through imports.
+#[derive(Debug, Default)]
+pub struct Package {
+    /// Every module comes after the modules it imports, so the root module is last.
+    pub modules: Vec<Module>,
+}
+
+impl Package {
+    pub fn module(&self, path: &[String]) -> Option<&Module> {
+        self.modules.iter().find(|m| m.path == path)
+    }
+}
@@ -4,8 +4,14 @@ use super::{expr::Identifier, ty::TypedParam, ExprS, StmtS, TypeS};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Declaration {
+    Import(Import),
     Fun(FunctionDeclaration),
+    Extern(ExternFunction),
     TypeObject(TypeObject),
+    Enum(EnumDeclaration),
+    Impl(ImplBlock),
+    /// A declaration that failed to parse. Only present if the parser reported an error.
+    Error,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -15,10 +21,16 @@ pub enum Stmt {
     If(Box<IfElse>),
     Return(Return),
     Break(Break),
+    Continue(Continue),
     VariableDeclaration(VariableDeclaration),
     Loop(Box<Loop>),
+    While(Box<While>),
+    For(Box<For>),
+    Match(Box<Match>),
     Empty(Empty),
     Assign(Box<Assign>),
+    /// A statement that failed to parse. Only present if the parser reported an error.
+    Error,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -32,12 +44,55 @@ pub struct Block {
     pub stmts: Vec<StmtS>,
 }
 
+/// `import geometry.shapes;` makes the functions of `geometry/shapes.pine` available as
+/// `shapes.name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    pub path: Vec<Located<Identifier>>,
+}
+
+impl Import {
+    /// The name the module is accessed by, which is the last segment of its path.
+    pub fn alias(&self) -> &Identifier {
+        &self
+            .path
+            .last()
+            .expect("Imports have at least one segment")
+            .value
+    }
+
+    pub fn module_path(&self) -> Vec<String> {
+        self.path.iter().map(|s| s.value.to_string()).collect()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct TypeObject {
     pub name: Identifier,
+    /// The type parameters of a generic type object, e.g. `A` and `B` in `type Pair<A, B>`.
+    pub type_params: Vec<Located<Identifier>>,
     pub members: Vec<TypedParam>,
 }
 
+impl TypeObject {
+    pub fn is_generic(&self) -> bool {
+        !self.type_params.is_empty()
+    }
+}
+
+/// A sum type whose variants can carry a payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumDeclaration {
+    pub name: Identifier,
+    pub variants: Vec<EnumVariant>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnumVariant {
+    pub name: Located<Identifier>,
+    pub payload: Vec<TypeS>,
+}
+
 /// An expression statement evaluates an expression and discards the result.
 #[derive(Clone, Debug, PartialEq)]
 pub struct StmtExpr {
@@ -47,11 +102,39 @@ pub struct StmtExpr {
 #[derive(Clone, Debug, PartialEq)]
 pub struct FunctionDeclaration {
     pub name: Identifier,
+    /// Generic functions are instantiated for every combination of type arguments they are
+    /// called with.
+    pub type_params: Vec<Located<Identifier>>,
     pub params: Vec<TypedParam>,
     pub ret_ty: TypeS,
     pub body: Block,
 }
 
+impl FunctionDeclaration {
+    pub fn is_generic(&self) -> bool {
+        !self.type_params.is_empty()
+    }
+}
+
+/// A function implemented in C, e.g. `extern "math.h" fun sqrt(x: f64) -> f64;`.
+///
+/// Strings are passed to and returned from C as null terminated `const char*`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternFunction {
+    /// The C header that declares the function. Without a header a prototype is generated.
+    pub header: Option<String>,
+    pub name: Identifier,
+    pub params: Vec<TypedParam>,
+    pub ret_ty: TypeS,
+}
+
+/// Methods attached to a type. The first parameter of every method is `self`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImplBlock {
+    pub name: Located<Identifier>,
+    pub methods: Vec<Located<FunctionDeclaration>>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct IfElse {
     pub cond: ExprS,
@@ -69,10 +152,16 @@ pub struct Break {
     pub span: Located<()>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Continue {
+    pub span: Located<()>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct VariableDeclaration {
     pub var: Identifier,
-    pub ty: TypeS,
+    /// The annotated type. If missing, the type is inferred from the value.
+    pub ty: Option<TypeS>,
     pub value: ExprS,
 }
 
@@ -81,7 +170,49 @@ pub struct Loop {
     pub body: StmtS,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct While {
+    pub cond: ExprS,
+    pub body: StmtS,
+}
+
+/// `for i in start..end { }` counts `i` from `start` up to, but not including, `end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct For {
+    pub var: Located<Identifier>,
+    pub start: ExprS,
+    pub end: ExprS,
+    pub body: StmtS,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Empty {
     pub span: Located<()>,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Match {
+    pub value: ExprS,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Located<Pattern>,
+    pub body: StmtS,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pattern {
+    /// `_` matches every variant.
+    Wildcard,
+    Variant(PatternVariant),
+}
+
+/// `Shape::Rect(w, h)` matches the variant and binds its payload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternVariant {
+    pub enum_name: Identifier,
+    pub variant: Identifier,
+    pub bindings: Vec<Located<Identifier>>,
+}
@@ -1,4 +1,8 @@
 use base::located::Located;
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::RangeInclusive,
+};
 
 use super::expr::Identifier;
 
@@ -21,5 +25,110 @@ pub enum Type {
     F32,
     F64,
     String,
+    /// A type object or enum, or a type parameter. `module.Name` names a type of an imported
+    /// module. After name resolution, types carry their qualified name, see
+    /// [`qualified_type_name`].
     Struct(String),
+    /// A fixed-size array `[T; N]`.
+    Array(Box<Type>, usize),
+    /// A growable `List<T>`.
+    List(Box<Type>),
+    /// `T?` is either a value of `T` or `nil`.
+    Optional(Box<Type>),
+    /// A generic type object with its type arguments, e.g. `Pair<i64, str>`.
+    ///
+    /// Inside of a generic declaration, its type parameters are written like type objects.
+    Generic(String, Vec<Type>),
+    /// `fun(i32, str) -> bool`, the type of functions and lambdas with these parameter and
+    /// return types.
+    Function(Vec<Type>, Box<Type>),
+}
+
+/// The name of a type object or enum declared in the module at `module_path` after name
+/// resolution, e.g. `geometry.shapes.Circle`. Types of the root module keep their own name.
+pub fn qualified_type_name(module_path: &[String], name: &str) -> String {
+    match module_path.is_empty() {
+        true => name.to_string(),
+        false => format!("{}.{}", module_path.join("."), name),
+    }
+}
+
+/// The name a type was declared with, without the path of its module.
+pub fn unqualified_type_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+impl Type {
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Type::I8 | Type::I32 | Type::I64 | Type::U8 | Type::U32 | Type::U64
+        )
+    }
+
+    /// The values an integer type can hold.
+    pub fn integer_range(&self) -> Option<RangeInclusive<i128>> {
+        let (min, max) = match self {
+            Type::I8 => (i8::MIN as i128, i8::MAX as i128),
+            Type::I32 => (i32::MIN as i128, i32::MAX as i128),
+            Type::I64 => (i64::MIN as i128, i64::MAX as i128),
+            Type::U8 => (0, u8::MAX as i128),
+            Type::U32 => (0, u32::MAX as i128),
+            Type::U64 => (0, u64::MAX as i128),
+            _ => return None,
+        };
+        Some(min..=max)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Type::F32 | Type::F64)
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    /// Whether the type is represented by a pointer, which is `NULL` for `nil`. Only these
+    /// types can be optional.
+    pub fn is_nullable(&self) -> bool {
+        matches!(
+            self,
+            Type::String
+                | Type::Struct(_)
+                | Type::Array(..)
+                | Type::List(_)
+                | Type::Generic(..)
+                | Type::Function(..)
+        )
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Unit => write!(f, "()"),
+            Type::Bool => write!(f, "bool"),
+            Type::I8 => write!(f, "i8"),
+            Type::I32 => write!(f, "i32"),
+            Type::I64 => write!(f, "i64"),
+            Type::U8 => write!(f, "u8"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::F32 => write!(f, "f32"),
+            Type::F64 => write!(f, "f64"),
+            Type::String => write!(f, "str"),
+            Type::Struct(name) => write!(f, "{name}"),
+            Type::Array(ty, len) => write!(f, "[{ty}; {len}]"),
+            Type::List(ty) => write!(f, "List<{ty}>"),
+            Type::Optional(ty) => write!(f, "{ty}?"),
+            Type::Generic(name, args) => {
+                let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+                write!(f, "{name}<{}>", args.join(", "))
+            }
+            Type::Function(params, ret) => {
+                let params = params.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                write!(f, "fun({}) -> {ret}", params.join(", "))
+            }
+        }
+    }
 }
@@ -0,0 +1,57 @@
+use crate::ast::ty::Type;
+
+/// A function provided by the C runtime that can be called without being declared.
+#[derive(Debug)]
+pub struct BuiltinFunction {
+    pub name: &'static str,
+    pub params: &'static [Type],
+    pub ret_ty: Type,
+}
+
+pub const BUILTIN_FUNCTIONS: &[BuiltinFunction] = &[
+    BuiltinFunction {
+        name: "print_int",
+        params: &[Type::I64],
+        ret_ty: Type::Unit,
+    },
+    BuiltinFunction {
+        name: "print_bool",
+        params: &[Type::Bool],
+        ret_ty: Type::Unit,
+    },
+    BuiltinFunction {
+        name: "print_str",
+        params: &[Type::String],
+        ret_ty: Type::Unit,
+    },
+    BuiltinFunction {
+        name: "str_len",
+        params: &[Type::String],
+        ret_ty: Type::I64,
+    },
+];
+
+pub fn find_builtin(name: &str) -> Option<&'static BuiltinFunction> {
+    BUILTIN_FUNCTIONS.iter().find(|f| f.name == name)
+}
+
+/// A builtin whose signature depends on the types of its arguments. These are checked and
+/// lowered by hand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Intrinsic {
+    /// `len(a)` of an array or list.
+    Len,
+    /// `push(list, value)` appends to a list.
+    Push,
+    /// `pop(list)` removes and returns the last element of a list.
+    Pop,
+}
+
+pub fn find_intrinsic(name: &str) -> Option<Intrinsic> {
+    match name {
+        "len" => Some(Intrinsic::Len),
+        "push" => Some(Intrinsic::Push),
+        "pop" => Some(Intrinsic::Pop),
+        _ => None,
+    }
+}
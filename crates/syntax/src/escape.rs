@@ -0,0 +1,105 @@
+use base::{located::Located, source_id::SourceId};
+use messages::lexer::LexerError;
+use std::{iter::Peekable, str::CharIndices};
+
+/// Resolves the escape sequences in the contents of a string literal.
+///
+/// `offset` is the position of `raw` in the source file and is used for the spans of errors.
+pub fn unescape(raw: &str, source: SourceId, offset: usize) -> Result<String, LexerError> {
+    let mut result = String::with_capacity(raw.len());
+    let mut chars = raw.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let Some((_, escape)) = chars.next() else {
+            unreachable!("The string regex does not allow a trailing backslash")
+        };
+        let location = |end: usize| Located::empty(source, offset + start..offset + end);
+        match escape {
+            'n' => result.push('\n'),
+            'r' => result.push('\r'),
+            't' => result.push('\t'),
+            '0' => result.push('\0'),
+            '\\' => result.push('\\'),
+            '"' => result.push('"'),
+            '\'' => result.push('\''),
+            // A backslash at the end of a line continues the string on the next line, without
+            // the line break and the indentation.
+            '\n' | '\r' => while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {},
+            'x' => {
+                let digits = take_while(&mut chars, raw, |c| c.is_ascii_hexdigit(), 2);
+                let end = chars.peek().map_or(raw.len(), |(i, _)| *i);
+                match u8::from_str_radix(digits, 16) {
+                    Ok(value) if digits.len() == 2 && value <= 0x7f => result.push(value as char),
+                    _ => {
+                        return Err(LexerError::MalformedHexEscape {
+                            location: location(end),
+                        })
+                    }
+                }
+            }
+            'u' => {
+                let c = unescape_unicode(&mut chars, raw, location)?;
+                result.push(c);
+            }
+            _ => {
+                let end = chars.peek().map_or(raw.len(), |(i, _)| *i);
+                return Err(LexerError::UnknownEscape {
+                    location: location(end),
+                    escape,
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Parses the `{1F332}` part of a `\u{1F332}` escape.
+fn unescape_unicode(
+    chars: &mut Peekable<CharIndices>,
+    raw: &str,
+    location: impl Fn(usize) -> Located<()>,
+) -> Result<char, LexerError> {
+    let end = |chars: &mut Peekable<CharIndices>| chars.peek().map_or(raw.len(), |(i, _)| *i);
+    if chars.next_if(|(_, c)| *c == '{').is_none() {
+        return Err(LexerError::MalformedUnicodeEscape {
+            location: location(end(chars)),
+        });
+    }
+    let digits = take_while(chars, raw, |c| c.is_ascii_hexdigit(), usize::MAX);
+    let closed = chars.next_if(|(_, c)| *c == '}').is_some();
+    if !closed || digits.is_empty() || digits.len() > 6 {
+        return Err(LexerError::MalformedUnicodeEscape {
+            location: location(end(chars)),
+        });
+    }
+    let value = u32::from_str_radix(digits, 16).expect("At most six hex digits");
+    char::from_u32(value).ok_or_else(|| LexerError::InvalidCodepoint {
+        location: location(end(chars)),
+        value,
+    })
+}
+
+/// Consumes up to `max` characters matching `pred` and returns them as a slice of `raw`.
+fn take_while<'a>(
+    chars: &mut Peekable<CharIndices>,
+    raw: &'a str,
+    pred: impl Fn(char) -> bool,
+    max: usize,
+) -> &'a str {
+    let start = chars.peek().map_or(raw.len(), |(i, _)| *i);
+    let mut end = start;
+    let mut count = 0;
+    while count < max {
+        match chars.next_if(|(_, c)| pred(*c)) {
+            Some((i, c)) => {
+                end = i + c.len_utf8();
+                count += 1;
+            }
+            None => break,
+        }
+    }
+    &raw[start..end]
+}
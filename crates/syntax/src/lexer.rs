@@ -1,4 +1,4 @@
-use crate::token::Token;
+use crate::{escape::unescape, token::Token};
 use base::{located::Located, source_id::SourceId};
 use logos::Logos;
 use messages::lexer::LexerError;
@@ -33,7 +33,9 @@ impl<'a> Iterator for Lexer<'a> {
                 let mut span = self.inner.span();
 
                 // Check for unterminated string.
-                if self.inner.slice().starts_with('"') {
+                let slice = self.inner.slice();
+                let raw_string = slice.starts_with('r') && slice.contains('"');
+                if slice.starts_with('"') || raw_string {
                     return Some(Err(LexerError::UnterminatedString {
                         location: Located::empty(self.source_id, span),
                     }));
@@ -58,6 +60,14 @@ impl<'a> Iterator for Lexer<'a> {
                     ),
                 }))
             }
+            Token::String(raw) => {
+                let span = self.inner.span();
+                // The contents start after the opening quote.
+                Some(
+                    unescape(&raw, self.source_id, span.start + 1)
+                        .map(|s| (span.start, Token::String(s), span.end)),
+                )
+            }
             token => {
                 let span = self.inner.span();
                 Some(Ok((span.start, token, span.end)))
@@ -1,4 +1,6 @@
 pub mod ast;
+pub mod builtins;
+mod escape;
 mod lexer;
 pub mod parser;
 mod token;
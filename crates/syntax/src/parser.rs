@@ -1,25 +1,122 @@
-use crate::{ast::Program, grammar, lexer::Lexer, token::Token};
+use crate::{
+    ast::{Program, ReplInput},
+    grammar,
+    lexer::Lexer,
+    token::Token,
+};
 use base::{located::Located, source_id::SourceId};
-use lalrpop_util::ParseError as LalrpopParseError;
+use lalrpop_util::{ErrorRecovery, ParseError as LalrpopParseError};
 use messages::{lexer::LexerError, message::Message, parser::ParserError};
 
 pub type Parser = grammar::ProgramParser;
 
+type ParseError = LalrpopParseError<usize, Token, LexerError>;
+type Recovery = ErrorRecovery<usize, Token, LexerError>;
+type Tokens = std::vec::IntoIter<Result<(usize, Token, usize), LexerError>>;
+
 impl Parser {
-    pub fn parse_file(source: SourceId, file: &str) -> Result<Program, Message> {
-        let lexer = Lexer::new(source, file);
-        let parser = Parser::new();
-        let parse_res = parser.parse(source, lexer);
+    /// Parses a whole file and keeps going after syntax errors.
+    ///
+    /// The parser recovers at statement (`;`) and declaration (`}`) boundaries, so the returned
+    /// `Program` may contain `Stmt::Error` and `Declaration::Error` nodes. Errors inside the
+    /// braces of a struct initializer or a match arm are skipped up to their closing brace. Every error that was
+    /// encountered on the way is returned in source order. If the parser cannot recover at all
+    /// an empty `Program` is returned.
+    pub fn parse_file(source: SourceId, file: &str) -> (Program, Vec<Message>) {
+        let (program, errors) = Self::parse_with(source, file, |recovered, tokens| {
+            Parser::new().parse(source, recovered, tokens)
+        });
+        (program.unwrap_or_default(), errors)
+    }
 
-        parse_res.map_err(|e| Self::uplift_parse_err(source, file, e))
+    /// Parses a single declaration, statement or expression, as entered in the REPL.
+    ///
+    /// Returns `None` if the parser cannot recover from the errors.
+    pub fn parse_repl_input(source: SourceId, input: &str) -> (Option<ReplInput>, Vec<Message>) {
+        Self::parse_with(source, input, |recovered, tokens| {
+            grammar::ReplInputParser::new().parse(source, recovered, tokens)
+        })
     }
 
-    /// Converts a lalrpop `ParseError` into our internal representation of an error.
-    pub fn uplift_parse_err(
+    /// Runs `parse` on the tokens of `file` and collects the errors of the lexer and the parser.
+    fn parse_with<T>(
         source: SourceId,
         file: &str,
-        err: LalrpopParseError<usize, Token, LexerError>,
-    ) -> Message {
+        parse: impl FnOnce(&mut Vec<Recovery>, Tokens) -> Result<T, ParseError>,
+    ) -> (Option<T>, Vec<Message>) {
+        let mut lexer_errors = Vec::new();
+        let mut recovered = Vec::new();
+
+        // Lexer errors are fatal to lalrpop, so we report them ourselves and hand an error token
+        // to the parser instead, which it can recover from like any other unexpected token.
+        let tokens = Lexer::new(source, file)
+            .map(|token| match token {
+                Ok(token) => Ok(token),
+                Err(err) => {
+                    let span = err.span();
+                    lexer_errors.push(err);
+                    Ok((span.start, Token::Error, span.end))
+                }
+            })
+            .collect::<Vec<_>>();
+        let parse_res = parse(&mut recovered, tokens.into_iter());
+
+        let mut errors: Vec<(usize, Message)> = lexer_errors
+            .into_iter()
+            .map(|err| (err.span().start, err.into()))
+            .collect();
+        let mut push_parse_err = |err: ParseError| {
+            if !Self::is_lexer_error_token(&err) {
+                let start = Self::parse_err_start(&err);
+                errors.push((start, Self::uplift_parse_err(source, file, err)));
+            }
+        };
+
+        for recovery in recovered {
+            push_parse_err(recovery.error);
+        }
+        let parsed = match parse_res {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                push_parse_err(err);
+                None
+            }
+        };
+
+        errors.sort_by_key(|(start, _)| *start);
+        (parsed, errors.into_iter().map(|(_, msg)| msg).collect())
+    }
+
+    /// Error tokens are produced for input the lexer already reported, so they are not reported twice.
+    fn is_lexer_error_token(err: &ParseError) -> bool {
+        matches!(
+            err,
+            LalrpopParseError::UnrecognizedToken {
+                token: (_, Token::Error, _),
+                ..
+            } | LalrpopParseError::ExtraToken {
+                token: (_, Token::Error, _),
+            }
+        )
+    }
+
+    fn parse_err_start(err: &ParseError) -> usize {
+        match err {
+            LalrpopParseError::InvalidToken { location }
+            | LalrpopParseError::UnrecognizedEof { location, .. } => *location,
+            LalrpopParseError::UnrecognizedToken {
+                token: (start, _, _),
+                ..
+            }
+            | LalrpopParseError::ExtraToken {
+                token: (start, _, _),
+            } => *start,
+            LalrpopParseError::User { error } => error.span().start,
+        }
+    }
+
+    /// Converts a lalrpop `ParseError` into our internal representation of an error.
+    pub fn uplift_parse_err(source: SourceId, file: &str, err: ParseError) -> Message {
         match err {
             LalrpopParseError::ExtraToken {
                 token: (start, _, end),
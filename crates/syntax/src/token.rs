@@ -12,10 +12,16 @@ pub enum Token {
     LtBrace,
     #[token("}")]
     RtBrace,
+    #[token("[")]
+    LtBracket,
+    #[token("]")]
+    RtBracket,
     #[token(",")]
     Comma,
     #[token(".")]
     Dot,
+    #[token("..")]
+    DotDot,
     #[token("-")]
     Minus,
     #[token("+")]
@@ -32,6 +38,14 @@ pub enum Token {
     Colon,
     #[token("->")]
     ArrowRight,
+    #[token("=>")]
+    FatArrow,
+    #[token("::")]
+    ColonColon,
+    #[token("_")]
+    Underscore,
+    #[token("?")]
+    Question,
 
     // One or two character tokens.
     #[token("!")]
@@ -54,8 +68,12 @@ pub enum Token {
     // Literals.
     #[regex(r#"(\p{XID_Start}|_)\p{XID_Continue}*"#, lex_identifier)]
     Identifier(String),
-    #[regex(r#""[^"]*""#, lex_string)]
+    /// The contents of a string literal. Escape sequences are resolved by the `Lexer`.
+    #[regex(r#""([^"\\]|\\(.|\n))*""#, lex_string)]
     String(String),
+    /// `r"..."` or `r#"..."#`, without any escape sequences.
+    #[regex(r#"r#*""#, lex_raw_string)]
+    RawString(String),
     #[regex("[0-9][0-9_]*", lex_int_dec)]
     #[regex(r"0[xX][a-fA-F0-9][a-fA-F0-9_]*", lex_int_hex)]
     #[regex("0[bB][0-1][0-1_]*", lex_int_bin)]
@@ -76,6 +94,8 @@ pub enum Token {
     Fun,
     #[token("if")]
     If,
+    #[token("nil")]
+    Nil,
     #[token("or")]
     Or,
     #[token("return")]
@@ -88,8 +108,26 @@ pub enum Token {
     Let,
     #[token("loop")]
     Loop,
+    #[token("while")]
+    While,
+    #[token("for")]
+    For,
+    #[token("in")]
+    In,
     #[token("break")]
     Break,
+    #[token("continue")]
+    Continue,
+    #[token("enum")]
+    Enum,
+    #[token("match")]
+    Match,
+    #[token("impl")]
+    Impl,
+    #[token("import")]
+    Import,
+    #[token("extern")]
+    Extern,
 
     // types
     #[token("bool")]
@@ -112,6 +150,8 @@ pub enum Token {
     TyF64,
     #[token("str")]
     TyStr,
+    #[token("List")]
+    TyList,
 
     #[regex(r"//.*", logos::skip)]
     #[regex(r"[ \r\n\t\f]+", logos::skip)]
@@ -150,6 +190,21 @@ fn lex_string(lexer: &mut logos::Lexer<Token>) -> String {
     slice[1..slice.len() - 1].to_string()
 }
 
+/// Raw strings end at the first quote followed by as many `#` as the opening quote.
+fn lex_raw_string(lexer: &mut logos::Lexer<Token>) -> Option<String> {
+    let hashes = lexer.slice().len() - 2;
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    let Some(end) = lexer.remainder().find(&terminator) else {
+        // An unterminated raw string swallows the rest of the file, so its contents are not
+        // lexed as code.
+        lexer.bump(lexer.remainder().len());
+        return None;
+    };
+    let content = lexer.remainder()[..end].to_string();
+    lexer.bump(end + terminator.len());
+    Some(content)
+}
+
 fn lex_identifier(lexer: &mut logos::Lexer<Token>) -> String {
     let slice = lexer.slice();
     slice.to_string()
@@ -0,0 +1,170 @@
+use std::ops::Range;
+
+use base::source_id::SourceId;
+use messages::message::Message;
+use syntax::{
+    ast::{stmt::Declaration, Program},
+    parser::Parser,
+};
+
+/// A syntax error by its kind, its code and the span of the source it points at.
+type SyntaxError = (&'static str, usize, Range<usize>);
+
+fn parse(source: &str) -> (Program, Vec<SyntaxError>) {
+    let source_id = SourceId::from_path("main.pine");
+    let (program, errors) = Parser::parse_file(source_id, source);
+    let errors = errors
+        .into_iter()
+        .map(|err| match err {
+            Message::Lexer(err) => ("LEX", err.code(), err.span()),
+            Message::Parse(err) => {
+                let span = err.labels()[0].range.clone();
+                ("SYN", err.code(), span)
+            }
+            _ => panic!("The parser reported a message that is not a syntax error"),
+        })
+        .collect();
+    (program, errors)
+}
+
+/// The span of the `nth` occurrence of `pattern` in `source`, counting from 0.
+fn span_of(source: &str, pattern: &str, nth: usize) -> Range<usize> {
+    let start = source
+        .match_indices(pattern)
+        .nth(nth)
+        .expect("The pattern occurs in the source")
+        .0;
+    start..start + pattern.len()
+}
+
+const UNRECOGNIZED_EOF: usize = 2;
+const UNRECOGNIZED_TOKEN: usize = 3;
+const UNEXPECTED_INPUT: usize = 0;
+
+#[test]
+fn error_in_an_expression() {
+    let source = "
+        type Point = { x: i64, y: i64 }
+
+        fun main() -> () {
+            let p = Point { x: 1 +, y: 2 };
+            print_int(p.x);
+        }
+    ";
+    let (program, errors) = parse(source);
+    let comma = span_of(source, ",", 1);
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_TOKEN, comma)]);
+    assert_eq!(program.stmts.len(), 2);
+}
+
+#[test]
+fn error_in_a_call() {
+    let source = "
+        fun main() -> () {
+            print_int(1 *);
+            print_int(2);
+        }
+    ";
+    let (_, errors) = parse(source);
+    let paren = span_of(source, ")", 2);
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_TOKEN, paren)]);
+}
+
+#[test]
+fn error_in_a_statement() {
+    let source = "
+        fun main() -> () {
+            let = 3;
+            print_int(4);
+        }
+    ";
+    let (_, errors) = parse(source);
+    let eq = span_of(source, "=", 0);
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_TOKEN, eq)]);
+}
+
+#[test]
+fn error_in_a_declaration() {
+    let source = "
+        type Point = { x: i64 y: i64 }
+
+        fun main() -> () {}
+    ";
+    let (program, errors) = parse(source);
+    let y = span_of(source, "y", 1);
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_TOKEN, y)]);
+    assert!(matches!(program.stmts[0].value, Declaration::Error));
+    assert!(matches!(program.stmts[1].value, Declaration::Fun(_)));
+}
+
+#[test]
+fn error_in_the_last_statement_of_a_block() {
+    let source = "
+        fun main() -> () {
+            let x = 1 +
+        }
+
+        fun other() -> () {}
+    ";
+    let (program, errors) = parse(source);
+    let brace = span_of(source, "}", 0);
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_TOKEN, brace)]);
+    assert_eq!(program.stmts.len(), 2);
+}
+
+#[test]
+fn error_in_a_match_arm() {
+    let source = "
+        enum E { A, B }
+
+        fun main() -> () {
+            let e = E::A;
+            match (e) {
+                E::A => {}
+                E::B + => {}
+            }
+            print_int(1);
+        }
+    ";
+    let (_, errors) = parse(source);
+    let plus = span_of(source, "+", 0);
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_TOKEN, plus)]);
+}
+
+#[test]
+fn every_error_is_reported_in_source_order() {
+    let source = "
+        fun main() -> () {
+            let a = 1 +;
+            let b = $ ;
+            let c = (2;
+        }
+    ";
+    let (_, errors) = parse(source);
+    assert_eq!(
+        errors,
+        vec![
+            ("SYN", UNRECOGNIZED_TOKEN, span_of(source, ";", 0)),
+            ("LEX", UNEXPECTED_INPUT, span_of(source, "$", 0)),
+            ("SYN", UNRECOGNIZED_TOKEN, span_of(source, ";", 2)),
+        ]
+    );
+}
+
+#[test]
+fn missing_closing_brace_at_the_end() {
+    let source = "fun main() -> () { print_int(1);";
+    let (_, errors) = parse(source);
+    let end = source.len()..source.len() + 1;
+    assert_eq!(errors, vec![("SYN", UNRECOGNIZED_EOF, end)]);
+}
+
+#[test]
+fn closing_brace_without_declaration() {
+    let source = "fun main() -> () {} }";
+    let (_, errors) = parse(source);
+    assert_eq!(
+        errors,
+        vec![("SYN", UNRECOGNIZED_TOKEN, span_of(source, "}", 1))]
+    );
+}
@@ -0,0 +1,1691 @@
+use crate::table::{Capture, TypeTable};
+use base::located::Located;
+use messages::{message::Message, typeck::TypeError};
+use std::collections::{HashMap, HashSet};
+use syntax::ast::expr::*;
+use syntax::ast::op::{OpInfix, OpPrefix};
+use syntax::ast::stmt::*;
+use syntax::ast::ty::{Type, TypedParam};
+use syntax::ast::{ExprS, Module, Package, StmtS, TypeS};
+use syntax::builtins::{find_intrinsic, Intrinsic, BUILTIN_FUNCTIONS};
+
+/// A function declared in a module, identified by the module path and its name.
+type FunctionKey = (Vec<String>, String);
+
+/// A type parameter of a generic function.
+type TypeParam = (FunctionKey, String);
+
+#[derive(Clone)]
+struct FunctionSignature {
+    /// The module path and name of a declared function. Builtins, methods and closures have
+    /// none.
+    key: Option<FunctionKey>,
+    /// The type parameters of a generic function, which can occur in the other types.
+    type_params: Vec<String>,
+    params: Vec<Type>,
+    ret_ty: Type,
+}
+
+struct Variable {
+    ty: Type,
+    /// The declared optional type, if a comparison with `nil` narrowed the variable to its
+    /// inner type.
+    narrowed_from: Option<Type>,
+    /// The index of the scope the variable is defined in.
+    depth: usize,
+}
+
+/// A type parameter of a generic function passed on in the type argument of a generic function
+/// it uses.
+struct Instantiation {
+    from: TypeParam,
+    to: TypeParam,
+    ty: Type,
+    /// Whether the type argument wraps the type parameter, like `Box<T>` for `T`.
+    grows: bool,
+    location: Located<()>,
+}
+
+/// A lambda whose body is being checked.
+struct Lambda {
+    /// The index of the scope of its parameters. Variables of lower scopes are captured.
+    depth: usize,
+    captures: Vec<Capture>,
+}
+
+/// Infers the type of every expression and checks that they are used consistently.
+///
+/// Expects a package that passed name resolution. Expressions that fail to typecheck are
+/// left out of the `TypeTable` and do not cause follow-up errors.
+pub struct TypeChecker {
+    /// The builtins and the functions of the module that is being checked.
+    functions: HashMap<String, FunctionSignature>,
+    /// The functions of every module, keyed by the module path.
+    module_functions: HashMap<Vec<String>, HashMap<String, FunctionSignature>>,
+    /// The paths of the modules imported by the current module, keyed by their alias.
+    imports: HashMap<String, Vec<String>>,
+    module_path: Vec<String>,
+    /// Methods keyed by the implemented type and the method name. Their signature omits `self`.
+    methods: HashMap<(String, String), FunctionSignature>,
+    structs: HashMap<String, Vec<(String, Type)>>,
+    /// The type parameters of every generic type object.
+    generic_structs: HashMap<String, Vec<String>>,
+    /// The type parameters of the declaration that is being checked.
+    type_params: Vec<String>,
+    /// The generic function whose body is being checked.
+    function: Option<FunctionKey>,
+    instantiations: Vec<Instantiation>,
+    /// The variants of every enum together with the types of their payload.
+    enums: HashMap<String, Vec<(String, Vec<Type>)>>,
+    scopes: Vec<HashMap<String, Variable>>,
+    /// The lambdas enclosing the current expression, from the outermost to the innermost.
+    lambdas: Vec<Lambda>,
+    ret_ty: Type,
+    table: TypeTable,
+    errors: Vec<Message>,
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        Self {
+            functions: HashMap::new(),
+            module_functions: HashMap::new(),
+            imports: HashMap::new(),
+            module_path: Vec::new(),
+            methods: HashMap::new(),
+            structs: HashMap::new(),
+            generic_structs: HashMap::new(),
+            type_params: Vec::new(),
+            function: None,
+            instantiations: Vec::new(),
+            enums: HashMap::new(),
+            scopes: Vec::new(),
+            lambdas: Vec::new(),
+            ret_ty: Type::Unit,
+            table: TypeTable::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn check(package: &Package) -> (TypeTable, Vec<Message>) {
+        let mut checker = Self::new();
+        for module in &package.modules {
+            checker.register_declarations(module);
+        }
+        for module in &package.modules {
+            checker.check_declaration_types(module);
+        }
+        for module in &package.modules {
+            checker.check_module(module);
+        }
+        checker.check_instantiation_cycles();
+        (checker.table, checker.errors)
+    }
+
+    fn register_declarations(&mut self, module: &Module) {
+        for decl in &module.program.stmts {
+            match &decl.value {
+                Declaration::Fun(FunctionDeclaration {
+                    name,
+                    type_params,
+                    params,
+                    ret_ty,
+                    ..
+                }) => {
+                    let type_params = type_params.iter().map(|p| p.value.to_string()).collect();
+                    self.register_function(module, name, type_params, params, ret_ty);
+                }
+                Declaration::Extern(ExternFunction {
+                    name,
+                    params,
+                    ret_ty,
+                    ..
+                }) => {
+                    self.register_function(module, name, vec![], params, ret_ty);
+                }
+                Declaration::TypeObject(t) => {
+                    if t.is_generic() {
+                        let type_params = t.type_params.iter().map(|p| p.value.to_string());
+                        let type_params = type_params.collect();
+                        self.generic_structs.insert(t.name.to_string(), type_params);
+                    }
+                    let members = t
+                        .members
+                        .iter()
+                        .map(|m| (m.name.value.to_string(), m.ty.value.clone()))
+                        .collect();
+                    self.structs.insert(t.name.to_string(), members);
+                }
+                Declaration::Enum(e) => {
+                    let variants = e
+                        .variants
+                        .iter()
+                        .map(|v| {
+                            let payload = v.payload.iter().map(|ty| ty.value.clone()).collect();
+                            (v.name.value.to_string(), payload)
+                        })
+                        .collect();
+                    self.enums.insert(e.name.to_string(), variants);
+                }
+                Declaration::Impl(i) => {
+                    for method in &i.methods {
+                        let signature = FunctionSignature {
+                            key: None,
+                            type_params: vec![],
+                            params: (method.value.params.iter().skip(1))
+                                .map(|p| p.ty.value.clone())
+                                .collect(),
+                            ret_ty: method.value.ret_ty.value.clone(),
+                        };
+                        let key = (i.name.value.to_string(), method.value.name.to_string());
+                        self.methods.insert(key, signature);
+                    }
+                }
+                Declaration::Import(_) | Declaration::Error => {}
+            }
+        }
+    }
+
+    fn register_function(
+        &mut self,
+        module: &Module,
+        name: &Identifier,
+        type_params: Vec<String>,
+        params: &[TypedParam],
+        ret_ty: &TypeS,
+    ) {
+        let signature = FunctionSignature {
+            key: Some((module.path.clone(), name.to_string())),
+            type_params,
+            params: params.iter().map(|p| p.ty.value.clone()).collect(),
+            ret_ty: ret_ty.value.clone(),
+        };
+        let functions = self.module_functions.entry(module.path.clone());
+        functions.or_default().insert(name.to_string(), signature);
+    }
+
+    /// Checks the annotated types of the declarations. Needs every type object to be
+    /// registered, since generic ones are used with the number of their type parameters.
+    fn check_declaration_types(&mut self, module: &Module) {
+        for decl in &module.program.stmts {
+            match &decl.value {
+                Declaration::Fun(f) => {
+                    self.type_params = f.type_params.iter().map(|p| p.value.to_string()).collect();
+                    for param in &f.params {
+                        self.check_annotation(&param.ty);
+                    }
+                    self.check_annotation(&f.ret_ty);
+                }
+                Declaration::Extern(e) => {
+                    self.type_params.clear();
+                    for param in &e.params {
+                        self.check_annotation(&param.ty);
+                    }
+                    self.check_annotation(&e.ret_ty);
+                }
+                Declaration::TypeObject(t) => {
+                    self.type_params = t.type_params.iter().map(|p| p.value.to_string()).collect();
+                    for member in &t.members {
+                        self.check_annotation(&member.ty);
+                    }
+                }
+                Declaration::Enum(e) => {
+                    self.type_params.clear();
+                    for ty in e.variants.iter().flat_map(|v| &v.payload) {
+                        self.check_annotation(ty);
+                    }
+                }
+                Declaration::Impl(i) => {
+                    self.type_params.clear();
+                    for method in &i.methods {
+                        for param in method.value.params.iter().skip(1) {
+                            self.check_annotation(&param.ty);
+                        }
+                        self.check_annotation(&method.value.ret_ty);
+                    }
+                }
+                Declaration::Import(_) | Declaration::Error => {}
+            }
+        }
+    }
+
+    fn check_module(&mut self, module: &Module) {
+        self.functions = self
+            .module_functions
+            .get(&module.path)
+            .cloned()
+            .unwrap_or_default();
+        for builtin in BUILTIN_FUNCTIONS {
+            let signature = FunctionSignature {
+                key: None,
+                type_params: vec![],
+                params: builtin.params.to_vec(),
+                ret_ty: builtin.ret_ty.clone(),
+            };
+            self.functions.insert(builtin.name.to_string(), signature);
+        }
+        self.imports = module
+            .program
+            .imports()
+            .map(|i| (i.alias().to_string(), i.module_path()))
+            .collect();
+        self.module_path = module.path.clone();
+
+        for decl in &module.program.stmts {
+            self.function = None;
+            match &decl.value {
+                Declaration::Fun(f) => {
+                    if f.is_generic() {
+                        self.function = Some((module.path.clone(), f.name.to_string()));
+                    }
+                    self.check_function(f);
+                }
+                Declaration::Impl(i)
+                    if self.generic_structs.contains_key(i.name.value.as_ref()) =>
+                {
+                    self.error(TypeError::GenericImpl {
+                        location: Located::empty(i.name.source, i.name.span.clone()),
+                        ty: i.name.value.to_string(),
+                    });
+                }
+                Declaration::Impl(i) => {
+                    for method in &i.methods {
+                        self.check_function(&method.value);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_function(&mut self, fun: &FunctionDeclaration) {
+        self.type_params = fun
+            .type_params
+            .iter()
+            .map(|p| p.value.to_string())
+            .collect();
+        self.ret_ty = fun.ret_ty.value.clone();
+        self.scopes.push(HashMap::new());
+        for param in &fun.params {
+            self.define_param(param);
+        }
+        for stmt in &fun.body.stmts {
+            self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+
+        if self.ret_ty != Type::Unit && !Self::block_returns(&fun.body) {
+            self.error(TypeError::MissingReturn {
+                location: Located::empty(fun.ret_ty.source, fun.ret_ty.span.clone()),
+                expected: self.ret_ty.to_string(),
+            });
+        }
+    }
+
+    fn define_param(&mut self, param: &TypedParam) {
+        self.define(param.name.value.as_ref(), param.ty.value.clone());
+    }
+
+    fn define(&mut self, name: &str, ty: Type) {
+        let depth = self.scopes.len() - 1;
+        let scope = self.scopes.last_mut().expect("No scope to define in");
+        let variable = Variable {
+            ty,
+            narrowed_from: None,
+            depth,
+        };
+        scope.insert(name.to_string(), variable);
+    }
+
+    /// The type of a variable where it is used. Inside of a lambda, the variables of the
+    /// enclosing functions have their declared type, since the lambda can run after they were
+    /// assigned `nil`.
+    fn lookup(&self, name: &str) -> Option<Type> {
+        let lambda_depth = self.lambdas.last().map_or(0, |l| l.depth);
+        let (index, variable) = (self.scopes.iter().enumerate().rev())
+            .find_map(|(index, scope)| scope.get(name).map(|v| (index, v)))?;
+        match &variable.narrowed_from {
+            Some(declared) if index < lambda_depth => Some(declared.clone()),
+            _ => Some(variable.ty.clone()),
+        }
+    }
+
+    fn lookup_variable(&self, name: &str) -> Option<&Variable> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Pushes a scope in which the given optional variables have their inner type.
+    fn push_narrowed_scope(&mut self, names: Vec<&Identifier>) {
+        let mut scope = HashMap::new();
+        for name in names {
+            if let Some(Type::Optional(inner)) = self.lookup(name.as_ref()) {
+                let variable = Variable {
+                    ty: inner.as_ref().clone(),
+                    narrowed_from: Some(Type::Optional(inner)),
+                    depth: self.lookup_variable(name.as_ref()).unwrap().depth,
+                };
+                scope.insert(name.to_string(), variable);
+            }
+        }
+        self.scopes.push(scope);
+    }
+
+    /// The variables that cannot be `nil` if `cond` evaluates to `outcome`.
+    ///
+    /// `x != nil` proves that `x` is not `nil` if it is true, `x == nil` if it is false.
+    fn non_nil_vars(cond: &ExprS, outcome: bool) -> Vec<&Identifier> {
+        match &cond.value {
+            Expr::Infix(i) => match i.op {
+                OpInfix::Equal | OpInfix::NotEqual if outcome == (i.op == OpInfix::NotEqual) => {
+                    match (&i.lt.value, &i.rt.value) {
+                        (Expr::Var(v), _) if Self::is_nil(&i.rt) => vec![&v.var],
+                        (_, Expr::Var(v)) if Self::is_nil(&i.lt) => vec![&v.var],
+                        _ => vec![],
+                    }
+                }
+                OpInfix::LogicAnd if outcome => {
+                    let mut vars = Self::non_nil_vars(&i.lt, true);
+                    vars.extend(Self::non_nil_vars(&i.rt, true));
+                    vars
+                }
+                OpInfix::LogicOr if !outcome => {
+                    let mut vars = Self::non_nil_vars(&i.lt, false);
+                    vars.extend(Self::non_nil_vars(&i.rt, false));
+                    vars
+                }
+                _ => vec![],
+            },
+            Expr::Prefix(p) if p.op == OpPrefix::Not => Self::non_nil_vars(&p.rt, !outcome),
+            _ => vec![],
+        }
+    }
+
+    /// Checks a statement that only runs if `cond` evaluated to `outcome`.
+    fn check_narrowed_stmt(&mut self, stmt: &StmtS, cond: &ExprS, outcome: bool) {
+        self.push_narrowed_scope(Self::non_nil_vars(cond, outcome));
+        self.check_stmt(stmt);
+        self.scopes.pop();
+    }
+
+    /// Checks that generic type objects get the right number of type arguments and that only
+    /// pointer types are optional, so `i64?` is rejected.
+    fn check_annotation(&mut self, ty: &TypeS) {
+        let location = Located::empty(ty.source, ty.span.clone());
+        self.check_annotated_type(&location, &ty.value);
+    }
+
+    fn check_annotated_type(&mut self, location: &Located<()>, ty: &Type) {
+        match ty {
+            Type::Array(elem, _) | Type::List(elem) => self.check_annotated_type(location, elem),
+            Type::Optional(elem) if elem.is_nullable() && !self.is_type_param(elem) => {
+                self.check_annotated_type(location, elem)
+            }
+            Type::Optional(elem) => {
+                self.error(TypeError::NotNullable {
+                    location: location.clone(),
+                    ty: elem.to_string(),
+                });
+            }
+            Type::Function(params, ret) => {
+                for param in params {
+                    self.check_annotated_type(location, param);
+                }
+                self.check_annotated_type(location, ret);
+            }
+            Type::Struct(name) | Type::Generic(name, _) => {
+                let args: &[Type] = match ty {
+                    Type::Generic(_, args) => args,
+                    _ => &[],
+                };
+                let expected = self.generic_structs.get(name).map_or(0, Vec::len);
+                if args.len() != expected && !self.is_type_param(ty) {
+                    self.error(TypeError::TypeArgumentCount {
+                        location: location.clone(),
+                        ty: name.to_string(),
+                        expected,
+                        found: args.len(),
+                    });
+                }
+                for arg in args {
+                    self.check_annotated_type(location, arg);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_type_param(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Struct(name) if self.type_params.contains(name))
+    }
+
+    /// Whether every path through the block ends in a `return`.
+    fn block_returns(block: &Block) -> bool {
+        block.stmts.iter().any(|s| Self::stmt_returns(&s.value))
+    }
+
+    fn stmt_returns(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Return(_) => true,
+            Stmt::Block(b) => Self::block_returns(b),
+            Stmt::If(i) => match &i.else_ {
+                Some(else_) => {
+                    Self::stmt_returns(&i.then.value) && Self::stmt_returns(&else_.value)
+                }
+                None => false,
+            },
+            // A loop can only be left through a `break` or a `return`.
+            Stmt::Loop(l) => !Self::stmt_breaks(&l.body.value),
+            // Non-exhaustive matches are rejected, so it is enough if every arm returns.
+            Stmt::Match(m) => {
+                !m.arms.is_empty() && m.arms.iter().all(|a| Self::stmt_returns(&a.body.value))
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the statement contains a `break` that leaves the enclosing loop.
+    fn stmt_breaks(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::Break(_) => true,
+            Stmt::Block(b) => b.stmts.iter().any(|s| Self::stmt_breaks(&s.value)),
+            Stmt::If(i) => {
+                Self::stmt_breaks(&i.then.value)
+                    || i.else_
+                        .as_ref()
+                        .is_some_and(|e| Self::stmt_breaks(&e.value))
+            }
+            Stmt::Match(m) => m.arms.iter().any(|a| Self::stmt_breaks(&a.body.value)),
+            _ => false,
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.stmts {
+            self.check_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn check_stmt(&mut self, stmt: &StmtS) {
+        match &stmt.value {
+            Stmt::Block(b) => self.check_block(b),
+            Stmt::Expr(e) => {
+                self.infer_expr(&e.value, None);
+            }
+            Stmt::If(i) => {
+                self.check_expr(&i.cond, &Type::Bool);
+                self.check_narrowed_stmt(&i.then, &i.cond, true);
+                if let Some(else_) = &i.else_ {
+                    self.check_narrowed_stmt(else_, &i.cond, false);
+                }
+            }
+            Stmt::Return(r) => {
+                let ret_ty = self.ret_ty.clone();
+                match &r.value {
+                    Some(value) => {
+                        self.check_expr(value, &ret_ty);
+                    }
+                    None if ret_ty != Type::Unit => {
+                        self.error(TypeError::Mismatch {
+                            location: Located::empty(stmt.source, stmt.span.clone()),
+                            expected: ret_ty.to_string(),
+                            found: Type::Unit.to_string(),
+                        });
+                    }
+                    None => {}
+                }
+            }
+            Stmt::VariableDeclaration(v) => {
+                let ty = match &v.ty {
+                    Some(ty) => {
+                        self.check_annotation(ty);
+                        self.check_expr(&v.value, &ty.value);
+                        ty.value.clone()
+                    }
+                    None => match self.infer_expr(&v.value, None) {
+                        Some(ty) => ty,
+                        None => return,
+                    },
+                };
+                if ty == Type::Unit {
+                    self.error(TypeError::UnitVariable {
+                        location: Located::empty(stmt.source, stmt.span.clone()),
+                    });
+                }
+                self.define(v.var.as_ref(), ty);
+            }
+            Stmt::Loop(l) => self.check_loop(|checker| checker.check_stmt(&l.body)),
+            Stmt::While(w) => self.check_loop(|checker| {
+                checker.check_expr(&w.cond, &Type::Bool);
+                checker.check_narrowed_stmt(&w.body, &w.cond, true);
+            }),
+            Stmt::For(f) => self.check_for(f),
+            Stmt::Match(m) => self.check_match(m),
+            Stmt::Assign(a) => {
+                if !matches!(
+                    a.var.value,
+                    Expr::Var(_) | Expr::MemberAccess(_) | Expr::Index(_)
+                ) {
+                    self.error(TypeError::InvalidAssignTarget {
+                        location: Self::location(&a.var),
+                    });
+                    return;
+                }
+                let Some(ty) = self.infer_expr(&a.var, None) else {
+                    return;
+                };
+                let Expr::Var(v) = &a.var.value else {
+                    self.check_expr(&a.value, &ty);
+                    return;
+                };
+                // A lambda holds a copy of the variables it captures, so an assignment would
+                // not change the variable of the enclosing function.
+                if self.table.is_captured(&a.var) {
+                    self.error(TypeError::AssignToCaptured {
+                        location: Self::location(&a.var),
+                        name: v.var.to_string(),
+                    });
+                    return;
+                }
+                let declared = self
+                    .lookup_variable(v.var.as_ref())
+                    .and_then(|v| v.narrowed_from.clone());
+                let Some(declared) = declared else {
+                    self.check_expr(&a.value, &ty);
+                    return;
+                };
+                // A narrowed variable accepts its declared type, but stays narrowed only if
+                // the new value cannot be `nil` either.
+                if self.check_expr(&a.value, &declared) != Some(ty) {
+                    self.widen(v.var.as_ref());
+                }
+            }
+            Stmt::Break(_) | Stmt::Continue(_) | Stmt::Empty(_) | Stmt::Error => {}
+        }
+    }
+
+    fn check_match(&mut self, m: &Match) {
+        let Some(ty) = self.infer_expr(&m.value, None) else {
+            return;
+        };
+        let variants = match &ty {
+            Type::Struct(name) => self.enums.get(name).cloned(),
+            _ => None,
+        };
+        let Some(variants) = variants else {
+            self.error(TypeError::NotAnEnum {
+                location: Self::location(&m.value),
+                ty: ty.to_string(),
+            });
+            return;
+        };
+
+        let mut covered: Vec<&str> = Vec::new();
+        let mut has_wildcard = false;
+        for arm in &m.arms {
+            let location = Located::empty(arm.pattern.source, arm.pattern.span.clone());
+            let is_covered = match &arm.pattern.value {
+                Pattern::Wildcard => false,
+                Pattern::Variant(p) => covered.contains(&p.variant.as_ref()),
+            };
+            if has_wildcard || is_covered {
+                self.error(TypeError::UnreachableArm {
+                    location: location.clone(),
+                });
+            }
+
+            self.scopes.push(HashMap::new());
+            match &arm.pattern.value {
+                Pattern::Wildcard => has_wildcard = true,
+                Pattern::Variant(p) if ty != Type::Struct(p.enum_name.to_string()) => {
+                    self.error(TypeError::Mismatch {
+                        location,
+                        expected: ty.to_string(),
+                        found: p.enum_name.to_string(),
+                    });
+                }
+                Pattern::Variant(p) => {
+                    let (variant, payload) = variants
+                        .iter()
+                        .find(|(variant, _)| variant == p.variant.as_ref())
+                        .expect("Variants are checked by the resolver");
+                    if payload.len() != p.bindings.len() {
+                        self.error(TypeError::PatternArity {
+                            location,
+                            expected: payload.len(),
+                            found: p.bindings.len(),
+                        });
+                    }
+                    for (binding, ty) in p.bindings.iter().zip(payload) {
+                        self.define(binding.value.as_ref(), ty.clone());
+                    }
+                    covered.push(variant);
+                }
+            }
+            self.check_stmt(&arm.body);
+            self.scopes.pop();
+        }
+
+        let missing = variants
+            .iter()
+            .filter(|(variant, _)| !covered.contains(&variant.as_str()))
+            .map(|(variant, _)| format!("{}::{}", ty, variant))
+            .collect::<Vec<_>>();
+        if !has_wildcard && !missing.is_empty() {
+            self.error(TypeError::NonExhaustiveMatch {
+                location: Self::location(&m.value),
+                missing,
+            });
+        }
+    }
+
+    /// The bounds of a range have to be integers of the same type, which is also the type of the
+    /// loop variable.
+    fn check_for(&mut self, f: &For) {
+        // Infer the side with a fixed type first, so that a literal on the other side can adapt.
+        let (first, second) = match Self::is_untyped_literal(&f.start) {
+            true => (&f.end, &f.start),
+            false => (&f.start, &f.end),
+        };
+        let ty = self
+            .infer_expr(first, None)
+            .and_then(|ty| self.check_expr(second, &ty));
+        if let Some(ty) = &ty {
+            if !ty.is_integer() {
+                self.error(TypeError::Mismatch {
+                    location: Self::location(&f.start),
+                    expected: "an integer".to_string(),
+                    found: ty.to_string(),
+                });
+            }
+        }
+
+        self.scopes.push(HashMap::new());
+        if let Some(ty) = ty {
+            self.define(f.var.value.as_ref(), ty);
+        }
+        self.check_loop(|checker| checker.check_stmt(&f.body));
+        self.scopes.pop();
+    }
+
+    /// Checks the part of a loop that runs on every iteration.
+    ///
+    /// A variable the loop widens is also widened when the next iteration starts, so the loop
+    /// is checked again with those variables widened, until it widens no more of them. Only
+    /// the errors of the last check are reported.
+    fn check_loop(&mut self, check: impl Fn(&mut Self)) {
+        loop {
+            let narrowed = self.narrowed_vars();
+            let errors = self.errors.len();
+            check(self);
+            let widened = narrowed.iter().any(|name| {
+                let variable = self.lookup_variable(name).expect("Variable is defined");
+                variable.narrowed_from.is_none()
+            });
+            if !widened {
+                return;
+            }
+            self.errors.truncate(errors);
+        }
+    }
+
+    /// The names of the variables that are narrowed where they are used.
+    fn narrowed_vars(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut names = Vec::new();
+        // an inner scope shadows the variables of the same name in the outer ones
+        for (name, variable) in self.scopes.iter().rev().flatten() {
+            if seen.insert(name) && variable.narrowed_from.is_some() {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    /// Gives a narrowed variable its declared optional type again.
+    fn widen(&mut self, name: &str) {
+        let variable = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+            .expect("Assigned variable is defined");
+        if let Some(declared) = variable.narrowed_from.take() {
+            variable.ty = declared;
+        }
+    }
+
+    /// Checks that `expr` has the type `expected`. A value of `T` can be used as a `T?`.
+    fn check_expr(&mut self, expr: &ExprS, expected: &Type) -> Option<Type> {
+        let ty = self.infer_expr(expr, Some(expected))?;
+        let wrapped = matches!(expected, Type::Optional(inner) if **inner == ty);
+        if ty != *expected && !wrapped {
+            self.error(TypeError::Mismatch {
+                location: Self::location(expr),
+                expected: expected.to_string(),
+                found: ty.to_string(),
+            });
+            return None;
+        }
+        Some(ty)
+    }
+
+    /// Infers the type of `expr` and records it in the type table.
+    ///
+    /// Number literals have no fixed type. If the `hint` is a matching number type the literal
+    /// takes that type, otherwise integers default to `i64` and floats to `f64`.
+    fn infer_expr(&mut self, expr: &ExprS, hint: Option<&Type>) -> Option<Type> {
+        let ty = match &expr.value {
+            Expr::Var(v) => self.infer_var(expr, v, hint),
+            Expr::Literal(l) => self.infer_literal(expr, l, hint),
+            Expr::Call(c) => self.infer_call(expr, c, hint),
+            Expr::MemberAccess(m) => self.infer_member_access(expr, m, hint),
+            Expr::Prefix(p) => self.infer_prefix(expr, p, hint),
+            Expr::Infix(i) => self.infer_infix(expr, i, hint),
+            Expr::StructureInit(s) => self.infer_structure_init(expr, s, hint),
+            Expr::EnumVariant(v) => self.infer_enum_variant(expr, v, &[]),
+            Expr::Index(i) => self.infer_index(expr, i),
+            Expr::Array(a) => self.infer_array(expr, a, hint),
+            Expr::Unwrap(u) => self.infer_unwrap(expr, u),
+            Expr::Lambda(l) => self.infer_lambda(expr, l),
+        }?;
+        self.table.insert(expr, ty.clone());
+        Some(ty)
+    }
+
+    /// A variable, or a function that is used as a value. Variables of the enclosing functions
+    /// are captured by the lambdas they are used in.
+    fn infer_var(&mut self, expr: &ExprS, var: &ExprVar, hint: Option<&Type>) -> Option<Type> {
+        let name = var.var.as_ref();
+        let Some(ty) = self.lookup(name) else {
+            let signature = self.functions.get(name)?.clone();
+            let module_path = self.module_path.clone();
+            return self.infer_function_ref(expr, module_path, &signature, hint);
+        };
+        let depth = self
+            .lookup_variable(name)
+            .expect("Variable is defined")
+            .depth;
+        if self.lambdas.last().is_some_and(|l| l.depth > depth) {
+            self.capture(name, depth);
+            self.table.insert_captured(expr);
+        }
+        Some(ty)
+    }
+
+    /// Adds the variable defined in the scope `depth` to the captures of every lambda that is
+    /// nested deeper.
+    fn capture(&mut self, name: &str, depth: usize) {
+        let variable = self.lookup_variable(name).expect("Variable is defined");
+        let ty = variable
+            .narrowed_from
+            .as_ref()
+            .unwrap_or(&variable.ty)
+            .clone();
+        for i in 0..self.lambdas.len() {
+            let lambda = &self.lambdas[i];
+            if lambda.depth <= depth || lambda.captures.iter().any(|c| c.name == name) {
+                continue;
+            }
+            let from_env = i > 0 && self.lambdas[i - 1].depth > depth;
+            self.lambdas[i].captures.push(Capture {
+                name: name.to_string(),
+                ty: ty.clone(),
+                from_env,
+            });
+        }
+    }
+
+    /// A function used as a value has a function type. The type arguments of a generic function
+    /// are taken from the expected type.
+    fn infer_function_ref(
+        &mut self,
+        expr: &ExprS,
+        module_path: Vec<String>,
+        signature: &FunctionSignature,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        let ty = Type::Function(signature.params.clone(), Box::new(signature.ret_ty.clone()));
+        let ty = match signature.type_params.is_empty() {
+            true => ty,
+            false => {
+                let mut bindings = HashMap::new();
+                if let Some(hint) = hint {
+                    Self::unify(&ty, hint, &signature.type_params, &mut bindings);
+                }
+                let type_args = self.bound_type_args(expr, &signature.type_params, &bindings)?;
+                self.record_instantiation(expr, signature, &type_args);
+                self.table.insert_type_args(expr, type_args);
+                Self::substitute(&ty, &bindings)
+            }
+        };
+        self.table.insert_function_ref(expr, module_path);
+        Some(ty)
+    }
+
+    /// Checks the body of a lambda like the body of a function. The captured variables are
+    /// recorded in the type table.
+    fn infer_lambda(&mut self, expr: &ExprS, lambda: &ExprLambda) -> Option<Type> {
+        for param in &lambda.params {
+            self.check_annotation(&param.ty);
+        }
+        self.check_annotation(&lambda.ret_ty);
+        let ret_ty = std::mem::replace(&mut self.ret_ty, lambda.ret_ty.value.clone());
+        self.scopes.push(HashMap::new());
+        self.lambdas.push(Lambda {
+            depth: self.scopes.len() - 1,
+            captures: Vec::new(),
+        });
+        for param in &lambda.params {
+            self.define_param(param);
+        }
+        for stmt in &lambda.body.stmts {
+            self.check_stmt(stmt);
+        }
+        let captures = self.lambdas.pop().expect("Lambda was pushed").captures;
+        self.scopes.pop();
+        self.ret_ty = ret_ty;
+
+        if lambda.ret_ty.value != Type::Unit && !Self::block_returns(&lambda.body) {
+            self.error(TypeError::MissingReturn {
+                location: Located::empty(lambda.ret_ty.source, lambda.ret_ty.span.clone()),
+                expected: lambda.ret_ty.value.to_string(),
+            });
+        }
+        self.table.insert_captures(expr, captures);
+        let params = lambda.params.iter().map(|p| p.ty.value.clone()).collect();
+        Some(Type::Function(
+            params,
+            Box::new(lambda.ret_ty.value.clone()),
+        ))
+    }
+
+    fn infer_literal(
+        &mut self,
+        expr: &ExprS,
+        literal: &ExprLiteral,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        match literal {
+            ExprLiteral::Bool(_) => Some(Type::Bool),
+            ExprLiteral::Integer(value) => {
+                let ty = Self::integer_literal_ty(hint);
+                self.check_integer_range(expr, *value as i128, &ty)?;
+                Some(ty)
+            }
+            ExprLiteral::Float(_) => match hint {
+                Some(ty) if ty.is_float() => Some(ty.clone()),
+                _ => Some(Type::F64),
+            },
+            ExprLiteral::String(_) => Some(Type::String),
+            // `nil` takes the optional type it is used as.
+            ExprLiteral::Nil => match hint {
+                Some(ty @ Type::Optional(_)) => Some(ty.clone()),
+                _ => {
+                    self.error(TypeError::Mismatch {
+                        location: Self::location(expr),
+                        expected: hint.map_or("an optional".to_string(), |ty| ty.to_string()),
+                        found: "nil".to_string(),
+                    });
+                    None
+                }
+            },
+        }
+    }
+
+    fn integer_literal_ty(hint: Option<&Type>) -> Type {
+        match hint {
+            Some(ty) if ty.is_integer() => ty.clone(),
+            _ => Type::I64,
+        }
+    }
+
+    /// Reports an integer literal whose value does not fit the type it takes.
+    fn check_integer_range(&mut self, expr: &ExprS, value: i128, ty: &Type) -> Option<()> {
+        let range = ty
+            .integer_range()
+            .expect("Integer literals have an integer type");
+        if !range.contains(&value) {
+            self.error(TypeError::IntegerOutOfRange {
+                location: Self::location(expr),
+                value,
+                ty: ty.to_string(),
+                min: *range.start(),
+                max: *range.end(),
+            });
+            return None;
+        }
+        Some(())
+    }
+
+    fn infer_unwrap(&mut self, expr: &ExprS, unwrap: &ExprUnwrap) -> Option<Type> {
+        match self.infer_expr(&unwrap.value, None)? {
+            Type::Optional(inner) => Some(*inner),
+            ty => {
+                self.error(TypeError::NotOptional {
+                    location: Self::location(expr),
+                    ty: ty.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Reports an optional `object` whose value is accessed without checking for `nil`.
+    fn check_not_nil(&mut self, object: &ExprS, ty: &Type) -> Option<()> {
+        if let Type::Optional(_) = ty {
+            self.error(TypeError::MaybeNil {
+                location: Self::location(object),
+                ty: ty.to_string(),
+            });
+            return None;
+        }
+        Some(())
+    }
+
+    fn infer_call(&mut self, expr: &ExprS, call: &ExprCall, hint: Option<&Type>) -> Option<Type> {
+        if let Expr::EnumVariant(variant) = &call.callee.value {
+            return self.infer_enum_variant(expr, variant, &call.args);
+        }
+        if let Expr::MemberAccess(access) = &call.callee.value {
+            if let Expr::Var(module) = &access.object.value {
+                if let Some(path) = self.imports.get(module.var.as_ref()) {
+                    let signature =
+                        self.module_functions[path][access.member_name.as_ref()].clone();
+                    return self.check_args(expr, &signature, &call.args, hint);
+                }
+            }
+            return self.infer_method_call(expr, call, access, hint);
+        }
+        let Expr::Var(callee) = &call.callee.value else {
+            return self.infer_closure_call(expr, call, hint);
+        };
+        if self.lookup(callee.var.as_ref()).is_some() {
+            return self.infer_closure_call(expr, call, hint);
+        }
+        if let Some(intrinsic) = find_intrinsic(callee.var.as_ref()) {
+            return self.infer_intrinsic(expr, intrinsic, &call.args);
+        }
+        let signature = self.functions.get(callee.var.as_ref())?.clone();
+        self.check_args(expr, &signature, &call.args, hint)
+    }
+
+    /// Calls a value of a function type. Unlike named functions, the callee is recorded in the
+    /// type table.
+    fn infer_closure_call(
+        &mut self,
+        expr: &ExprS,
+        call: &ExprCall,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        let callee_ty = self.infer_expr(&call.callee, None)?;
+        self.check_not_nil(&call.callee, &callee_ty)?;
+        let Type::Function(params, ret_ty) = callee_ty else {
+            self.error(TypeError::NotCallable {
+                location: Self::location(&call.callee),
+            });
+            return None;
+        };
+        let signature = FunctionSignature {
+            key: None,
+            type_params: vec![],
+            params,
+            ret_ty: *ret_ty,
+        };
+        self.check_args(expr, &signature, &call.args, hint)
+    }
+
+    /// `object.method(args)` calls the method of the object's type with the object as `self`.
+    /// Without such a method, it calls the field `method` of a function type.
+    fn infer_method_call(
+        &mut self,
+        expr: &ExprS,
+        call: &ExprCall,
+        access: &ExprMemberAccess,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        let args = &call.args;
+        let object_ty = self.infer_expr(&access.object, None)?;
+        self.check_not_nil(&access.object, &object_ty)?;
+        let signature = match &object_ty {
+            Type::Struct(name) => self
+                .methods
+                .get(&(name.to_string(), access.member_name.to_string()))
+                .cloned(),
+            _ => None,
+        };
+        if let Some(signature) = signature {
+            return self.check_args(expr, &signature, args, None);
+        }
+        let is_field = self.struct_members(&object_ty).is_some_and(|members| {
+            (members.iter()).any(|(member, _)| *member == access.member_name.name)
+        });
+        if is_field {
+            return self.infer_closure_call(expr, call, hint);
+        }
+        self.error(TypeError::UnknownMethod {
+            location: Self::location(expr),
+            ty: object_ty.to_string(),
+            method: access.member_name.to_string(),
+        });
+        None
+    }
+
+    /// Checks the arguments of a call against the parameters and returns the type of the call.
+    ///
+    /// The type arguments of a generic function are inferred from the arguments, or from the
+    /// expected type if they only occur in the return type.
+    fn check_args(
+        &mut self,
+        expr: &ExprS,
+        signature: &FunctionSignature,
+        args: &[ExprS],
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        if signature.params.len() != args.len() {
+            self.error(TypeError::ArgumentCount {
+                location: Self::location(expr),
+                expected: signature.params.len(),
+                found: args.len(),
+            });
+        }
+        if signature.type_params.is_empty() {
+            for (arg, param) in args.iter().zip(signature.params.iter()) {
+                self.check_expr(arg, param);
+            }
+            return Some(signature.ret_ty.clone());
+        }
+
+        let type_params = &signature.type_params;
+        let mut bindings = HashMap::new();
+        let values = args.iter().zip(signature.params.iter()).collect::<Vec<_>>();
+        self.check_generic_values(type_params, &mut bindings, &values);
+        if let Some(hint) = hint {
+            Self::unify(&signature.ret_ty, hint, type_params, &mut bindings);
+        }
+        let type_args = self.bound_type_args(expr, type_params, &bindings)?;
+        self.record_instantiation(expr, signature, &type_args);
+        self.table.insert_type_args(expr, type_args);
+        Some(Self::substitute(&signature.ret_ty, &bindings))
+    }
+
+    /// Records which type parameters of the current generic function the type arguments of a
+    /// generic function it uses contain.
+    fn record_instantiation(
+        &mut self,
+        expr: &ExprS,
+        signature: &FunctionSignature,
+        type_args: &[Type],
+    ) {
+        let (Some(from), Some(to)) = (&self.function, &signature.key) else {
+            return;
+        };
+        for (param, ty) in signature.type_params.iter().zip(type_args) {
+            for own in self
+                .type_params
+                .iter()
+                .filter(|p| Self::contains_type(ty, p))
+            {
+                self.instantiations.push(Instantiation {
+                    from: (from.clone(), own.clone()),
+                    to: (to.clone(), param.clone()),
+                    ty: ty.clone(),
+                    grows: *ty != Type::Struct(own.clone()),
+                    location: Self::location(expr),
+                });
+            }
+        }
+    }
+
+    /// Generic functions are lowered once for every combination of type arguments. A use that
+    /// wraps a type parameter and leads back to itself asks for ever larger type arguments.
+    fn check_instantiation_cycles(&mut self) {
+        let mut uses: HashMap<&TypeParam, Vec<&TypeParam>> = HashMap::new();
+        for i in &self.instantiations {
+            uses.entry(&i.from).or_default().push(&i.to);
+        }
+        let mut errors: Vec<TypeError> = Vec::new();
+        for i in self.instantiations.iter().filter(|i| i.grows) {
+            let reported = errors.iter().any(|e| {
+                matches!(e, TypeError::InfiniteInstantiation { location, .. } if *location == i.location)
+            });
+            if !reported && Self::reaches(&uses, &i.to, &i.from) {
+                errors.push(TypeError::InfiniteInstantiation {
+                    location: i.location.clone(),
+                    function: i.to.0 .1.clone(),
+                    ty: i.ty.to_string(),
+                });
+            }
+        }
+        for err in errors {
+            self.error(err);
+        }
+    }
+
+    /// Whether the type parameter `from` is passed on to `to` through any number of uses.
+    fn reaches(
+        uses: &HashMap<&TypeParam, Vec<&TypeParam>>,
+        from: &TypeParam,
+        to: &TypeParam,
+    ) -> bool {
+        let mut visited = HashSet::new();
+        let mut work = vec![from];
+        while let Some(param) = work.pop() {
+            if param == to {
+                return true;
+            }
+            if visited.insert(param) {
+                work.extend(uses.get(param).into_iter().flatten().copied());
+            }
+        }
+        false
+    }
+
+    /// Checks values that are passed for declared types which contain type parameters, e.g.
+    /// the arguments of a call to a generic function. Type parameters that are not bound yet are
+    /// bound to the matching part of the value's type.
+    fn check_generic_values(
+        &mut self,
+        type_params: &[String],
+        bindings: &mut HashMap<String, Type>,
+        values: &[(&ExprS, &Type)],
+    ) {
+        for (value, declared) in values {
+            let known = Self::substitute(declared, bindings);
+            if !type_params.iter().any(|p| Self::contains_type(&known, p)) {
+                self.check_expr(value, &known);
+                continue;
+            }
+            let Some(ty) = self.infer_expr(value, None) else {
+                continue;
+            };
+            Self::unify(&known, &ty, type_params, bindings);
+            let expected = Self::substitute(declared, bindings);
+            let wrapped = matches!(&expected, Type::Optional(inner) if **inner == ty);
+            if ty != expected && !wrapped {
+                self.error(TypeError::Mismatch {
+                    location: Self::location(value),
+                    expected: expected.to_string(),
+                    found: ty.to_string(),
+                });
+            }
+        }
+    }
+
+    /// The type arguments in the order of the type parameters, if all of them are bound.
+    fn bound_type_args(
+        &mut self,
+        expr: &ExprS,
+        type_params: &[String],
+        bindings: &HashMap<String, Type>,
+    ) -> Option<Vec<Type>> {
+        let mut type_args = Vec::new();
+        for param in type_params {
+            match bindings.get(param) {
+                Some(ty) => type_args.push(ty.clone()),
+                None => {
+                    self.error(TypeError::UnknownTypeArgument {
+                        location: Self::location(expr),
+                        param: param.to_string(),
+                    });
+                    return None;
+                }
+            }
+        }
+        Some(type_args)
+    }
+
+    /// Binds the type parameters in `pattern` to the matching parts of `ty`. Parameters that
+    /// are already bound keep their type.
+    fn unify(
+        pattern: &Type,
+        ty: &Type,
+        type_params: &[String],
+        bindings: &mut HashMap<String, Type>,
+    ) {
+        match (pattern, ty) {
+            (Type::Struct(name), _) if type_params.contains(name) => {
+                bindings.entry(name.clone()).or_insert_with(|| ty.clone());
+            }
+            (Type::Array(pattern, _), Type::Array(ty, _))
+            | (Type::List(pattern), Type::List(ty))
+            | (Type::Optional(pattern), Type::Optional(ty)) => {
+                Self::unify(pattern, ty, type_params, bindings)
+            }
+            // A `T` can be passed as a `T?`.
+            (Type::Optional(pattern), _) => Self::unify(pattern, ty, type_params, bindings),
+            (Type::Generic(name, patterns), Type::Generic(ty_name, tys)) if name == ty_name => {
+                for (pattern, ty) in patterns.iter().zip(tys) {
+                    Self::unify(pattern, ty, type_params, bindings);
+                }
+            }
+            (Type::Function(patterns, pattern_ret), Type::Function(tys, ret)) => {
+                for (pattern, ty) in patterns.iter().zip(tys) {
+                    Self::unify(pattern, ty, type_params, bindings);
+                }
+                Self::unify(pattern_ret, ret, type_params, bindings);
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the bound type parameters in `ty` by their types.
+    fn substitute(ty: &Type, bindings: &HashMap<String, Type>) -> Type {
+        match ty {
+            Type::Struct(name) => bindings.get(name).cloned().unwrap_or_else(|| ty.clone()),
+            Type::Array(elem, len) => Type::Array(Box::new(Self::substitute(elem, bindings)), *len),
+            Type::List(elem) => Type::List(Box::new(Self::substitute(elem, bindings))),
+            Type::Optional(elem) => Type::Optional(Box::new(Self::substitute(elem, bindings))),
+            Type::Generic(name, args) => Type::Generic(
+                name.clone(),
+                args.iter().map(|a| Self::substitute(a, bindings)).collect(),
+            ),
+            Type::Function(params, ret) => Type::Function(
+                params
+                    .iter()
+                    .map(|p| Self::substitute(p, bindings))
+                    .collect(),
+                Box::new(Self::substitute(ret, bindings)),
+            ),
+            _ => ty.clone(),
+        }
+    }
+
+    /// Whether the type parameter `param` occurs in `ty`.
+    fn contains_type(ty: &Type, param: &str) -> bool {
+        match ty {
+            Type::Struct(name) => name == param,
+            Type::Array(elem, _) | Type::List(elem) | Type::Optional(elem) => {
+                Self::contains_type(elem, param)
+            }
+            Type::Generic(_, args) => args.iter().any(|a| Self::contains_type(a, param)),
+            Type::Function(params, ret) => {
+                params.iter().any(|p| Self::contains_type(p, param))
+                    || Self::contains_type(ret, param)
+            }
+            _ => false,
+        }
+    }
+
+    /// The members of a type object with the type arguments filled in.
+    fn struct_members(&self, ty: &Type) -> Option<Vec<(String, Type)>> {
+        match ty {
+            Type::Struct(name) => self.structs.get(name).cloned(),
+            Type::Generic(name, args) => {
+                let type_params = self.generic_structs.get(name)?;
+                let bindings = type_params.iter().cloned().zip(args.iter().cloned());
+                let bindings = bindings.collect::<HashMap<_, _>>();
+                let members = self.structs.get(name)?.iter();
+                let members =
+                    members.map(|(name, ty)| (name.clone(), Self::substitute(ty, &bindings)));
+                Some(members.collect())
+            }
+            _ => None,
+        }
+    }
+
+    fn infer_intrinsic(
+        &mut self,
+        expr: &ExprS,
+        intrinsic: Intrinsic,
+        args: &[ExprS],
+    ) -> Option<Type> {
+        let expected = match intrinsic {
+            Intrinsic::Len | Intrinsic::Pop => 1,
+            Intrinsic::Push => 2,
+        };
+        if args.len() != expected {
+            self.error(TypeError::ArgumentCount {
+                location: Self::location(expr),
+                expected,
+                found: args.len(),
+            });
+            return None;
+        }
+
+        let collection = &args[0];
+        let ty = self.infer_expr(collection, None)?;
+        match (intrinsic, &ty) {
+            (Intrinsic::Len, Type::Array(..) | Type::List(_)) => Some(Type::I64),
+            (Intrinsic::Push, Type::List(elem)) => {
+                self.check_expr(&args[1], elem)?;
+                Some(Type::Unit)
+            }
+            (Intrinsic::Pop, Type::List(elem)) => Some(*elem.clone()),
+            _ => {
+                let expected = match intrinsic {
+                    Intrinsic::Len => "an array or a list",
+                    Intrinsic::Push | Intrinsic::Pop => "a list",
+                };
+                self.error(TypeError::Mismatch {
+                    location: Self::location(collection),
+                    expected: expected.to_string(),
+                    found: ty.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    fn infer_index(&mut self, expr: &ExprS, index: &ExprIndex) -> Option<Type> {
+        let object_ty = self.infer_expr(&index.object, None);
+        self.check_expr(&index.index, &Type::I64);
+        let object_ty = object_ty?;
+        self.check_not_nil(&index.object, &object_ty)?;
+        match object_ty {
+            Type::Array(elem, _) | Type::List(elem) => Some(*elem),
+            ty => {
+                self.error(TypeError::NotIndexable {
+                    location: Self::location(expr),
+                    ty: ty.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// An array literal is a list if a list is expected, otherwise an array of its length.
+    fn infer_array(
+        &mut self,
+        expr: &ExprS,
+        array: &ExprArray,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        let elem_hint = match hint {
+            Some(Type::Array(elem, _) | Type::List(elem)) => Some(elem.as_ref().clone()),
+            _ => None,
+        };
+        let elem = match elem_hint {
+            Some(elem) => elem,
+            None => {
+                let Some(first) = array.elements.first() else {
+                    self.error(TypeError::UnknownElementType {
+                        location: Self::location(expr),
+                    });
+                    return None;
+                };
+                self.infer_expr(first, None)?
+            }
+        };
+
+        let mut valid = true;
+        for element in &array.elements {
+            valid &= self.check_expr(element, &elem).is_some();
+        }
+        if !valid {
+            return None;
+        }
+        match hint {
+            Some(Type::List(_)) => Some(Type::List(Box::new(elem))),
+            _ => Some(Type::Array(Box::new(elem), array.elements.len())),
+        }
+    }
+
+    /// Checks the construction of an enum variant with the given payload.
+    fn infer_enum_variant(
+        &mut self,
+        expr: &ExprS,
+        variant: &ExprEnumVariant,
+        args: &[ExprS],
+    ) -> Option<Type> {
+        let variants = self.enums.get(variant.enum_name.as_ref())?;
+        let (_, payload) = variants
+            .iter()
+            .find(|(name, _)| name == variant.variant.as_ref())?;
+        let payload = payload.clone();
+
+        if payload.len() != args.len() {
+            self.error(TypeError::ArgumentCount {
+                location: Self::location(expr),
+                expected: payload.len(),
+                found: args.len(),
+            });
+        }
+        for (arg, ty) in args.iter().zip(payload.iter()) {
+            self.check_expr(arg, ty);
+        }
+        Some(Type::Struct(variant.enum_name.to_string()))
+    }
+
+    fn infer_member_access(
+        &mut self,
+        expr: &ExprS,
+        access: &ExprMemberAccess,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        // `module.function` is a function of another module that is used as a value
+        if let Expr::Var(module) = &access.object.value {
+            if let Some(path) = self.imports.get(module.var.as_ref()).cloned() {
+                let signature = self.module_functions[&path][access.member_name.as_ref()].clone();
+                return self.infer_function_ref(expr, path, &signature, hint);
+            }
+        }
+        let object_ty = self.infer_expr(&access.object, None)?;
+        self.check_not_nil(&access.object, &object_ty)?;
+        let member_ty = self.struct_members(&object_ty).and_then(|members| {
+            members
+                .into_iter()
+                .find(|(member, _)| *member == access.member_name.name)
+                .map(|(_, ty)| ty)
+        });
+        if member_ty.is_none() {
+            self.error(TypeError::UnknownField {
+                location: Self::location(expr),
+                ty: object_ty.to_string(),
+                field: access.member_name.to_string(),
+            });
+        }
+        member_ty
+    }
+
+    fn infer_prefix(
+        &mut self,
+        expr: &ExprS,
+        prefix: &ExprPrefix,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        // A negated literal is one negative value, so `-128` fits into an `i8` but `-1` does not
+        // fit into a `u32`.
+        if let (OpPrefix::Negate, Expr::Literal(ExprLiteral::Integer(value))) =
+            (prefix.op, &prefix.rt.value)
+        {
+            let ty = Self::integer_literal_ty(hint);
+            self.check_integer_range(expr, -(*value as i128), &ty)?;
+            self.table.insert(&prefix.rt, ty.clone());
+            return Some(ty);
+        }
+        let ty = self.infer_expr(&prefix.rt, hint)?;
+        let valid = match prefix.op {
+            OpPrefix::Negate => ty.is_numeric(),
+            OpPrefix::Not => ty == Type::Bool,
+        };
+        if !valid {
+            self.error(TypeError::InvalidPrefixOperand {
+                location: Self::location(expr),
+                op: prefix.op.to_string(),
+                rt: ty.to_string(),
+            });
+            return None;
+        }
+        Some(ty)
+    }
+
+    fn infer_infix(
+        &mut self,
+        expr: &ExprS,
+        infix: &ExprInfix,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        // Only arithmetic passes the expected type on to its operands.
+        let operand_hint = match infix.op {
+            OpInfix::Add
+            | OpInfix::Subtract
+            | OpInfix::Multiply
+            | OpInfix::Divide
+            | OpInfix::Modulo => hint,
+            OpInfix::LogicAnd | OpInfix::LogicOr => Some(&Type::Bool),
+            _ => None,
+        };
+
+        // Infer the side with a fixed type first, so that a literal on the other side can
+        // adapt to it.
+        let (lt, rt) =
+            if Self::is_untyped_literal(&infix.lt) && !Self::is_untyped_literal(&infix.rt) {
+                let rt = self.infer_expr(&infix.rt, operand_hint);
+                let lt = self.infer_expr(&infix.lt, rt.as_ref().or(operand_hint));
+                (lt?, rt?)
+            } else {
+                let lt = self.infer_expr(&infix.lt, operand_hint);
+                // The right side of `and` only runs if the left side is true, the right side of
+                // `or` only if it is false.
+                let narrowed = match infix.op {
+                    OpInfix::LogicAnd => Self::non_nil_vars(&infix.lt, true),
+                    OpInfix::LogicOr => Self::non_nil_vars(&infix.lt, false),
+                    _ => vec![],
+                };
+                self.push_narrowed_scope(narrowed);
+                let rt = self.infer_expr(&infix.rt, lt.as_ref().or(operand_hint));
+                self.scopes.pop();
+                (lt?, rt?)
+            };
+
+        let result = match infix.op {
+            // Strings are concatenated with `+`.
+            OpInfix::Add if lt == Type::String && rt == Type::String => Some(Type::String),
+            OpInfix::Add | OpInfix::Subtract | OpInfix::Multiply | OpInfix::Divide => {
+                (lt == rt && lt.is_numeric()).then(|| lt.clone())
+            }
+            OpInfix::Modulo => (lt == rt && lt.is_integer()).then(|| lt.clone()),
+            OpInfix::Less | OpInfix::LessEqual | OpInfix::Greater | OpInfix::GreaterEqual => {
+                (lt == rt && (lt.is_numeric() || lt == Type::String)).then_some(Type::Bool)
+            }
+            // Optionals can only be compared with `nil`.
+            OpInfix::Equal | OpInfix::NotEqual => match lt {
+                Type::Optional(_) => (lt == rt
+                    && (Self::is_nil(&infix.lt) || Self::is_nil(&infix.rt)))
+                .then_some(Type::Bool),
+                _ => (lt == rt && lt != Type::Unit).then_some(Type::Bool),
+            },
+            OpInfix::LogicAnd | OpInfix::LogicOr => {
+                (lt == Type::Bool && rt == Type::Bool).then_some(Type::Bool)
+            }
+        };
+
+        if result.is_none() {
+            self.error(TypeError::InvalidInfixOperands {
+                location: Self::location(expr),
+                op: infix.op.to_string(),
+                lt: lt.to_string(),
+                rt: rt.to_string(),
+            });
+        }
+        result
+    }
+
+    /// The type arguments of a generic type object are taken from the expected type, or
+    /// inferred from the values of the members.
+    fn infer_structure_init(
+        &mut self,
+        expr: &ExprS,
+        init: &ExprStructureInit,
+        hint: Option<&Type>,
+    ) -> Option<Type> {
+        let name = init.name.to_string();
+        let Some(members) = self.structs.get(&name).cloned() else {
+            self.error(TypeError::NotAStruct {
+                location: Self::location(expr),
+                ty: name,
+            });
+            return None;
+        };
+        let type_params = self.generic_structs.get(&name).cloned().unwrap_or_default();
+        let mut bindings = HashMap::new();
+        let hint = match hint {
+            Some(Type::Optional(inner)) => Some(inner.as_ref()),
+            hint => hint,
+        };
+        if let Some(Type::Generic(hint_name, args)) = hint {
+            if *hint_name == name {
+                bindings = type_params
+                    .iter()
+                    .cloned()
+                    .zip(args.iter().cloned())
+                    .collect();
+            }
+        }
+
+        let mut initialized: Vec<&str> = Vec::new();
+        let mut values = Vec::new();
+        for (field, value) in &init.members {
+            if initialized.contains(&field.as_ref()) {
+                self.error(TypeError::DuplicateField {
+                    location: Self::location(value),
+                    field: field.to_string(),
+                });
+                continue;
+            }
+            initialized.push(field.as_ref());
+
+            match members.iter().find(|(member, _)| member == field.as_ref()) {
+                Some((_, member_ty)) => values.push((value, member_ty)),
+                None => {
+                    self.error(TypeError::UnknownField {
+                        location: Self::location(value),
+                        ty: name.clone(),
+                        field: field.to_string(),
+                    });
+                }
+            }
+        }
+        self.check_generic_values(&type_params, &mut bindings, &values);
+
+        let missing = members
+            .iter()
+            .filter(|(member, _)| !initialized.contains(&member.as_str()))
+            .map(|(member, _)| member.clone())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            self.error(TypeError::MissingFields {
+                location: Self::location(expr),
+                ty: name.clone(),
+                fields: missing,
+            });
+        }
+
+        if type_params.is_empty() {
+            return Some(Type::Struct(name));
+        }
+        let type_args = self.bound_type_args(expr, &type_params, &bindings)?;
+        Some(Type::Generic(name, type_args))
+    }
+
+    /// Whether the type of `expr` depends on the context it is used in.
+    fn is_untyped_literal(expr: &ExprS) -> bool {
+        match &expr.value {
+            Expr::Literal(ExprLiteral::Integer(_) | ExprLiteral::Float(_) | ExprLiteral::Nil) => {
+                true
+            }
+            Expr::Prefix(p) => p.op == OpPrefix::Negate && Self::is_untyped_literal(&p.rt),
+            _ => false,
+        }
+    }
+
+    fn is_nil(expr: &ExprS) -> bool {
+        matches!(expr.value, Expr::Literal(ExprLiteral::Nil))
+    }
+
+    fn location(expr: &ExprS) -> Located<()> {
+        Located::empty(expr.source, expr.span.clone())
+    }
+
+    fn error(&mut self, err: TypeError) {
+        self.errors.push(err.into());
+    }
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,109 @@
+use base::source_id::SourceId;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+use syntax::ast::{ty::Type, ExprS};
+
+/// A variable of an enclosing function that is used by a lambda.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capture {
+    pub name: String,
+    pub ty: Type,
+    /// Whether the variable is captured by the enclosing lambda as well, so that its value is
+    /// taken from the environment of that lambda.
+    pub from_env: bool,
+}
+
+/// The type of every expression in a program, keyed by the location of the expression.
+#[derive(Debug, Default)]
+pub struct TypeTable {
+    exprs: HashMap<(SourceId, Range<usize>), Type>,
+    /// The type arguments of calls to generic functions and of generic functions used as values.
+    type_args: HashMap<(SourceId, Range<usize>), Vec<Type>>,
+    /// The variables every lambda captures.
+    captures: HashMap<(SourceId, Range<usize>), Vec<Capture>>,
+    /// The variables inside of lambdas that refer to a captured variable.
+    captured: HashSet<(SourceId, Range<usize>)>,
+    /// Functions that are used as values, with the path of their module.
+    function_refs: HashMap<(SourceId, Range<usize>), Vec<String>>,
+}
+
+impl TypeTable {
+    pub fn new() -> Self {
+        Self {
+            exprs: HashMap::new(),
+            type_args: HashMap::new(),
+            captures: HashMap::new(),
+            captured: HashSet::new(),
+            function_refs: HashMap::new(),
+        }
+    }
+
+    pub fn insert_captures(&mut self, lambda: &ExprS, captures: Vec<Capture>) {
+        self.captures
+            .insert((lambda.source, lambda.span.clone()), captures);
+    }
+
+    /// The variables the lambda `lambda` captures, in the order they are first used.
+    pub fn captures(&self, lambda: &ExprS) -> &[Capture] {
+        self.captures
+            .get(&(lambda.source, lambda.span.clone()))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn insert_captured(&mut self, var: &ExprS) {
+        self.captured.insert((var.source, var.span.clone()));
+    }
+
+    /// Whether the variable `var` is read from the environment of the lambda it is used in.
+    pub fn is_captured(&self, var: &ExprS) -> bool {
+        self.captured.contains(&(var.source, var.span.clone()))
+    }
+
+    pub fn insert_function_ref(&mut self, expr: &ExprS, module_path: Vec<String>) {
+        self.function_refs
+            .insert((expr.source, expr.span.clone()), module_path);
+    }
+
+    /// The module path of the function `expr` names, if it is a function used as a value.
+    pub fn function_ref(&self, expr: &ExprS) -> Option<&[String]> {
+        self.function_refs
+            .get(&(expr.source, expr.span.clone()))
+            .map(Vec::as_slice)
+    }
+
+    pub fn insert_type_args(&mut self, expr: &ExprS, args: Vec<Type>) {
+        self.type_args
+            .insert((expr.source, expr.span.clone()), args);
+    }
+
+    /// The type arguments a generic function is called with in the call `expr`.
+    ///
+    /// Inside of a generic function, they can refer to its own type parameters.
+    pub fn type_args(&self, expr: &ExprS) -> Option<&[Type]> {
+        self.type_args
+            .get(&(expr.source, expr.span.clone()))
+            .map(Vec::as_slice)
+    }
+
+    pub fn insert(&mut self, expr: &ExprS, ty: Type) {
+        self.exprs.insert((expr.source, expr.span.clone()), ty);
+    }
+
+    /// The type of `expr`. Only `None` if the expression did not typecheck.
+    pub fn expr_ty(&self, expr: &ExprS) -> Option<&Type> {
+        self.exprs.get(&(expr.source, expr.span.clone()))
+    }
+
+    /// All annotated expressions, ordered by their location.
+    pub fn sorted(&self) -> Vec<(SourceId, Range<usize>, &Type)> {
+        let mut exprs = self
+            .exprs
+            .iter()
+            .map(|((source, span), ty)| (*source, span.clone(), ty))
+            .collect::<Vec<_>>();
+        exprs.sort_by_key(|(source, span, _)| (source.to_string(), span.start, span.end));
+        exprs
+    }
+}
@@ -0,0 +1,206 @@
+use base::source_id::SourceId;
+use messages::message::Message;
+use syntax::{
+    ast::{Module, Package},
+    parser::Parser,
+};
+use typeck::checker::TypeChecker;
+
+/// The codes of the type errors in a package made of the single module `source`.
+fn type_errors(source: &str) -> Vec<usize> {
+    let source_id = SourceId::from_path("main.pine");
+    let (program, errors) = Parser::parse_file(source_id, source);
+    assert!(errors.is_empty(), "The test program has syntax errors");
+    let package = Package {
+        modules: vec![Module {
+            path: vec![],
+            source: source_id,
+            program,
+        }],
+    };
+    let (_, errors) = TypeChecker::check(&package);
+    errors
+        .into_iter()
+        .map(|err| match err {
+            Message::Type(err) => err.code(),
+            _ => panic!("The type checker reported a message that is not a type error"),
+        })
+        .collect()
+}
+
+const MAYBE_NIL: usize = 19;
+const INTEGER_OUT_OF_RANGE: usize = 25;
+const ASSIGN_TO_CAPTURED: usize = 26;
+const INFINITE_INSTANTIATION: usize = 27;
+
+#[test]
+fn loop_widens_narrowed_variable_for_next_iteration() {
+    let source = "
+        type Point = { x: i64 }
+
+        fun main() -> () {
+            let p: Point? = Point { x: 2 };
+            let i = 0;
+            if (p != nil) {
+                while (i < 2) {
+                    print_int(p.x);
+                    p = nil;
+                    i = i + 1;
+                }
+            }
+        }
+    ";
+    assert_eq!(type_errors(source), vec![MAYBE_NIL]);
+}
+
+#[test]
+fn loop_keeps_narrowed_variable_that_stays_non_nil() {
+    let source = "
+        type Point = { x: i64 }
+
+        fun main() -> () {
+            let p: Point? = Point { x: 2 };
+            if (p != nil) {
+                for i in 0..3 {
+                    print_int(p.x);
+                    p = Point { x: i };
+                }
+            }
+        }
+    ";
+    assert_eq!(type_errors(source), vec![]);
+}
+
+#[test]
+fn while_condition_narrows_every_iteration() {
+    let source = "
+        type Node = { value: i64, next: Node? }
+
+        fun sum(n: Node?) -> i64 {
+            let cur = n;
+            let total = 0;
+            while (cur != nil) {
+                total = total + cur.value;
+                cur = cur.next;
+            }
+            return total;
+        }
+
+        fun main() -> () {}
+    ";
+    assert_eq!(type_errors(source), vec![]);
+}
+
+#[test]
+fn integer_literal_out_of_range() {
+    let source = "
+        fun main() -> () {
+            let u: u8 = 300;
+            let v: i8 = -200;
+            let w: u32 = -1;
+        }
+    ";
+    assert_eq!(type_errors(source), vec![INTEGER_OUT_OF_RANGE; 3]);
+}
+
+#[test]
+fn integer_literal_at_bounds() {
+    let source = "
+        fun main() -> () {
+            let a: i8 = -128;
+            let b: i8 = 127;
+            let c: u8 = 255;
+            let d: u32 = 4294967295;
+            let e: i64 = -9223372036854775807;
+        }
+    ";
+    assert_eq!(type_errors(source), vec![]);
+}
+
+#[test]
+fn lambda_cannot_assign_captured_variable() {
+    let source = "
+        fun main() -> () {
+            let n = 0;
+            let inc = fun() -> () { n = n + 1; };
+            inc();
+        }
+    ";
+    assert_eq!(type_errors(source), vec![ASSIGN_TO_CAPTURED]);
+}
+
+#[test]
+fn lambda_assigns_its_own_variables_and_captured_fields() {
+    let source = "
+        type Counter = { n: i64 }
+
+        fun main() -> () {
+            let c = Counter { n: 0 };
+            let bump = fun() -> () {
+                let step = 1;
+                step = step + 1;
+                c.n = c.n + step;
+            };
+            bump();
+        }
+    ";
+    assert_eq!(type_errors(source), vec![]);
+}
+
+#[test]
+fn generic_function_calling_itself_with_wrapped_type_argument() {
+    let source = "
+        type Box<T> = { v: T }
+
+        fun f<T>(x: T, n: i64) -> () {
+            if (n > 0) {
+                f(Box { v: x }, n - 1);
+            }
+        }
+
+        fun main() -> () {
+            f(1, 3);
+        }
+    ";
+    assert_eq!(type_errors(source), vec![INFINITE_INSTANTIATION]);
+}
+
+#[test]
+fn generic_functions_wrapping_type_argument_through_each_other() {
+    let source = "
+        fun g<A>(x: A, n: i64) -> () {
+            if (n > 0) {
+                h(x, n - 1);
+            }
+        }
+
+        fun h<B>(y: B, n: i64) -> () {
+            let f: fun(i64) -> B = fun(i: i64) -> B { return y; };
+            g(f, n);
+        }
+
+        fun main() -> () {
+            g(1, 3);
+        }
+    ";
+    assert_eq!(type_errors(source), vec![INFINITE_INSTANTIATION]);
+}
+
+#[test]
+fn generic_recursion_with_the_same_or_fixed_type_arguments() {
+    let source = "
+        type Box<T> = { v: T }
+
+        fun f<T>(x: T, n: i64) -> () {
+            if (n > 0) {
+                f(x, n - 1);
+                f(Box { v: 1 }, 0);
+            }
+        }
+
+        fun main() -> () {
+            f(true, 3);
+        }
+    ";
+    assert_eq!(type_errors(source), vec![]);
+}